@@ -0,0 +1,170 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::ArchiveConfig;
+
+// 每个分段包含的行数, 攒满一段才写入存储
+const SEGMENT_LINES: usize = 1000;
+
+pub trait ArchiveStore: Send + Sync {
+    fn put(&self, key: &str, data: &[u8]) -> io::Result<()>;
+    fn get(&self, key: &str) -> io::Result<Vec<u8>>;
+}
+
+pub struct LocalStore {
+    dir: PathBuf,
+}
+
+impl ArchiveStore for LocalStore {
+    fn put(&self, key: &str, data: &[u8]) -> io::Result<()> {
+        let path = self.dir.join(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, data)
+    }
+
+    fn get(&self, key: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.dir.join(key))
+    }
+}
+
+// 通过 aws CLI 读写, 凭证和 region 沿用 CLI 自己的配置
+pub struct S3Store {
+    bucket: String,
+    prefix: String,
+}
+
+impl S3Store {
+    fn url(&self, key: &str) -> String {
+        format!("s3://{}/{}{}", self.bucket, self.prefix, key)
+    }
+
+    fn aws(&self, args: &[&str], input: Option<&[u8]>) -> io::Result<Vec<u8>> {
+        let mut child = Command::new("aws")
+            .args(args)
+            .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let (Some(data), Some(mut stdin)) = (input, child.stdin.take()) {
+            stdin.write_all(data)?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        Ok(output.stdout)
+    }
+}
+
+impl ArchiveStore for S3Store {
+    fn put(&self, key: &str, data: &[u8]) -> io::Result<()> {
+        self.aws(&["s3", "cp", "-", &self.url(key)], Some(data)).map(|_| ())
+    }
+
+    fn get(&self, key: &str) -> io::Result<Vec<u8>> {
+        self.aws(&["s3", "cp", &self.url(key), "-"], None)
+    }
+}
+
+pub fn open_store(config: &ArchiveConfig) -> Arc<dyn ArchiveStore> {
+    match config {
+        ArchiveConfig::Local { dir } => {
+            let dir = dir.clone().map(PathBuf::from).unwrap_or_else(|| {
+                let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+                PathBuf::from(home).join(".rogger").join("archive")
+            });
+            Arc::new(LocalStore { dir })
+        }
+        ArchiveConfig::S3 { bucket, prefix } => Arc::new(S3Store {
+            bucket: bucket.clone(),
+            prefix: prefix.clone().unwrap_or_default(),
+        }),
+    }
+}
+
+// 被 max_history 淘汰的行按分段写入存储, 需要时再整体读回
+pub struct Archive {
+    store: Arc<dyn ArchiveStore>,
+    // 每次启动用独立的目录, 不覆盖之前的归档
+    session: String,
+    pending: Mutex<Vec<String>>,
+    segments: Mutex<u64>,
+    error: Arc<Mutex<Option<String>>>,
+}
+
+impl Archive {
+    pub fn new(store: Arc<dyn ArchiveStore>, name: &str) -> Self {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Archive {
+            store,
+            session: format!("{}/{}", sanitize(name), started),
+            pending: Mutex::new(Vec::new()),
+            segments: Mutex::new(0),
+            error: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    fn segment_key(&self, index: u64) -> String {
+        format!("{}/{:08}.log", self.session, index)
+    }
+
+    pub fn push(&self, mut line: String) {
+        if !line.ends_with('\n') {
+            line.push('\n');
+        }
+        let mut pending = self.pending.lock().unwrap();
+        pending.push(line);
+        if pending.len() < SEGMENT_LINES {
+            return;
+        }
+
+        let data: Vec<u8> = std::mem::take(&mut *pending).concat().into_bytes();
+        let mut segments = self.segments.lock().unwrap();
+        let key = self.segment_key(*segments);
+        *segments += 1;
+
+        // 上传可能很慢, 放到后台线程, 不阻塞 tail
+        let store = Arc::clone(&self.store);
+        let error = Arc::clone(&self.error);
+        thread::spawn(move || {
+            if let Err(e) = store.put(&key, &data) {
+                *error.lock().unwrap() = Some(format!("{}: {}", key, e));
+            }
+        });
+    }
+
+    // 按顺序读回所有已归档的行, 包括尚未攒满一段的部分
+    pub fn load(&self) -> io::Result<Vec<String>> {
+        let segments = *self.segments.lock().unwrap();
+        let mut lines = Vec::new();
+        for index in 0..segments {
+            let data = self.store.get(&self.segment_key(index))?;
+            let text = String::from_utf8_lossy(&data);
+            lines.extend(text.split_inclusive('\n').map(str::to_string));
+        }
+        lines.extend(self.pending.lock().unwrap().iter().cloned());
+        Ok(lines)
+    }
+
+    pub fn last_error(&self) -> Option<String> {
+        self.error.lock().unwrap().clone()
+    }
+}
+
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
@@ -0,0 +1,272 @@
+use std::io;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use serde::Deserialize;
+use ssh2::{KeyboardInteractivePrompt, Prompt, Session};
+
+use crate::config;
+use crate::i18n::{tr, Msg};
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthMethod {
+    Agent,
+    Key,
+    Password,
+    KeyboardInteractive,
+    Command,
+}
+
+impl AuthMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthMethod::Agent => "agent",
+            AuthMethod::Key => "key",
+            AuthMethod::Password => "password",
+            AuthMethod::KeyboardInteractive => "keyboard-interactive",
+            AuthMethod::Command => "command",
+        }
+    }
+}
+
+// 新的认证方式实现这个 trait, 再在 provider() 里按配置创建即可
+pub trait AuthProvider {
+    fn method(&self) -> AuthMethod;
+    fn authenticate(&self, sess: &Session, username: &str) -> Result<(), String>;
+}
+
+struct Agent;
+
+impl AuthProvider for Agent {
+    fn method(&self) -> AuthMethod {
+        AuthMethod::Agent
+    }
+
+    fn authenticate(&self, sess: &Session, username: &str) -> Result<(), String> {
+        sess.userauth_agent(username).map_err(|e| e.to_string())
+    }
+}
+
+struct KeyFile {
+    key: PathBuf,
+    cert: Option<PathBuf>,
+}
+
+impl AuthProvider for KeyFile {
+    fn method(&self) -> AuthMethod {
+        AuthMethod::Key
+    }
+
+    // 证书(如 id_ed25519-cert.pub)作为公钥参数传入即可完成证书认证
+    fn authenticate(&self, sess: &Session, username: &str) -> Result<(), String> {
+        sess.userauth_pubkey_file(username, self.cert.as_deref(), &self.key, None)
+            .map_err(|e| e.to_string())
+    }
+}
+
+// 密码直接写在配置里, 或者连接时由 password_cmd 输出 (pass / gopass / op 等)
+enum Secret {
+    Value(String),
+    Command(String),
+}
+
+impl Secret {
+    fn from_config(log: &config::LogConfig) -> Option<Secret> {
+        match (&log.password, &log.password_cmd) {
+            (Some(password), _) => Some(Secret::Value(password.clone())),
+            (None, Some(command)) => Some(Secret::Command(command.clone())),
+            (None, None) => None,
+        }
+    }
+
+    fn resolve(&self) -> Result<String, String> {
+        match self {
+            Secret::Value(value) => Ok(value.clone()),
+            Secret::Command(command) => Ok(secret_output(command)?
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .to_string()),
+        }
+    }
+}
+
+// 在本地执行凭据命令并返回标准输出; 界面运行中不能让命令写终端, 失败时显示 stderr 的最后一行
+fn secret_output(command: &str) -> Result<String, String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("{}: {}", tr(Msg::CommandErr), e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "{} ({}): {}",
+            tr(Msg::CommandExited),
+            output.status,
+            stderr.trim().lines().last().unwrap_or_default()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// ssh_key_cmd 输出的私钥只在内存里使用, 不落盘
+struct KeyCommand {
+    command: String,
+    cert: Option<PathBuf>,
+}
+
+impl AuthProvider for KeyCommand {
+    fn method(&self) -> AuthMethod {
+        AuthMethod::Key
+    }
+
+    fn authenticate(&self, sess: &Session, username: &str) -> Result<(), String> {
+        let key = secret_output(&self.command)?;
+        let cert = match &self.cert {
+            Some(path) => Some(std::fs::read_to_string(path).map_err(|e| e.to_string())?),
+            None => None,
+        };
+        sess.userauth_pubkey_memory(username, cert.as_deref(), &key, None)
+            .map_err(|e| e.to_string())
+    }
+}
+
+struct Password(Secret);
+
+impl AuthProvider for Password {
+    fn method(&self) -> AuthMethod {
+        AuthMethod::Password
+    }
+
+    fn authenticate(&self, sess: &Session, username: &str) -> Result<(), String> {
+        sess.userauth_password(username, &self.0.resolve()?)
+            .map_err(|e| e.to_string())
+    }
+}
+
+struct KeyboardInteractive(Secret);
+
+// 对服务端的每个提示都回答同一个密码, 适用于只要求输入密码的 PAM 配置
+struct Answer(String);
+
+impl KeyboardInteractivePrompt for Answer {
+    fn prompt<'a>(&mut self, _username: &str, _instructions: &str, prompts: &[Prompt<'a>]) -> Vec<String> {
+        prompts.iter().map(|_| self.0.clone()).collect()
+    }
+}
+
+impl AuthProvider for KeyboardInteractive {
+    fn method(&self) -> AuthMethod {
+        AuthMethod::KeyboardInteractive
+    }
+
+    fn authenticate(&self, sess: &Session, username: &str) -> Result<(), String> {
+        sess.userauth_keyboard_interactive(username, &mut Answer(self.0.resolve()?))
+            .map_err(|e| e.to_string())
+    }
+}
+
+// 连接时执行 password_cmd, 输出的第一行作为密码, 先试 password 再试 keyboard-interactive
+struct SecretCommand(String);
+
+impl AuthProvider for SecretCommand {
+    fn method(&self) -> AuthMethod {
+        AuthMethod::Command
+    }
+
+    fn authenticate(&self, sess: &Session, username: &str) -> Result<(), String> {
+        let secret = Secret::Command(self.0.clone()).resolve()?;
+        match sess.userauth_password(username, &secret) {
+            Ok(()) if sess.authenticated() => Ok(()),
+            _ => sess
+                .userauth_keyboard_interactive(username, &mut Answer(secret))
+                .map_err(|e| e.to_string()),
+        }
+    }
+}
+
+// 按配置创建认证方式; 缺少必需的字段时返回 None
+fn provider(method: AuthMethod, log: &config::LogConfig) -> Option<Box<dyn AuthProvider>> {
+    Some(match method {
+        AuthMethod::Agent => Box::new(Agent),
+        AuthMethod::Key => match (&log.ssh_key, &log.ssh_key_cmd) {
+            (Some(key), _) => Box::new(KeyFile {
+                key: PathBuf::from(key),
+                cert: log.ssh_cert.as_ref().map(PathBuf::from),
+            }),
+            (None, Some(command)) => Box::new(KeyCommand {
+                command: command.clone(),
+                cert: log.ssh_cert.as_ref().map(PathBuf::from),
+            }),
+            (None, None) => return None,
+        },
+        AuthMethod::Password => Box::new(Password(Secret::from_config(log)?)),
+        AuthMethod::KeyboardInteractive => Box::new(KeyboardInteractive(Secret::from_config(log)?)),
+        AuthMethod::Command => Box::new(SecretCommand(log.password_cmd.clone()?)),
+    })
+}
+
+// 没有配置 auth 时依次尝试 agent、私钥和密码; 只配置了 password_cmd 时最后执行命令
+fn default_methods(log: &config::LogConfig) -> Vec<AuthMethod> {
+    let mut methods = vec![AuthMethod::Agent, AuthMethod::Key];
+    if log.password.is_some() {
+        methods.push(AuthMethod::Password);
+    } else if log.password_cmd.is_some() {
+        methods.push(AuthMethod::Command);
+    }
+    methods
+}
+
+// --dry-run: 按尝试顺序列出认证方式, 显式列出但缺少配置的也列出来并标注
+pub fn describe(log: &config::LogConfig) -> String {
+    let explicit = log.auth.is_some();
+    let methods = log.auth.clone().unwrap_or_else(|| default_methods(log));
+    let described: Vec<String> = methods
+        .into_iter()
+        .filter_map(|method| match provider(method, log) {
+            Some(_) => Some(method.as_str().to_string()),
+            None if explicit => Some(format!("{} ({})", method.as_str(), tr(Msg::AuthNotConfigured))),
+            None => None,
+        })
+        .collect();
+    if described.is_empty() {
+        return tr(Msg::NoAuthMethod).to_string();
+    }
+    described.join(" → ")
+}
+
+// 按 auth 配置(或默认)的顺序依次尝试, 全部失败时汇总每种方式的错误
+pub fn authenticate(sess: &Session, log: &config::LogConfig) -> io::Result<AuthMethod> {
+    let username = log.username.as_deref().unwrap_or("");
+    let explicit = log.auth.is_some();
+    let methods = log.auth.clone().unwrap_or_else(|| default_methods(log));
+    let mut errors = Vec::new();
+    let mut tried = 0;
+
+    for method in methods {
+        let Some(provider) = provider(method, log) else {
+            // 显式列出但缺少配置的方式要提示, 默认顺序里的直接跳过
+            if explicit {
+                errors.push(format!("{}: {}", method.as_str(), tr(Msg::AuthNotConfigured)));
+            }
+            continue;
+        };
+        if method != AuthMethod::Agent {
+            tried += 1;
+        }
+        match provider.authenticate(sess, username) {
+            Ok(()) if sess.authenticated() => return Ok(provider.method()),
+            Ok(()) => {}
+            Err(e) => errors.push(format!("{}: {}", method.as_str(), e)),
+        }
+    }
+
+    if tried == 0 && !explicit {
+        errors.push(tr(Msg::NoAuthMethod).to_string());
+    }
+
+    Err(io::Error::other(errors.join("; ")))
+}
@@ -0,0 +1,168 @@
+use std::collections::HashSet;
+use std::process::Command;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+use serde_json::{Map, Value};
+
+use crate::command::{idle, wait_output};
+use crate::config;
+use crate::i18n::{tr, Msg};
+use crate::ssh::{ConnectionStatus, LogSink, SourceRequest};
+use crate::timestamp;
+
+// Log Analytics 的写入延迟通常有几十秒, 轮询太频繁没有意义
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+const PAGE_SIZE: usize = 1000;
+const TIME_COLUMN: &str = "TimeGenerated";
+const MESSAGE_COLUMNS: [&str; 4] = ["Message", "message", "RenderedDescription", "ResultDescription"];
+// 不作为 键=值 附加显示的列
+const HIDDEN_COLUMNS: [&str; 3] = [TIME_COLUMN, "TableName", "SeverityLevel"];
+
+// 最后读到的 TimeGenerated 和该时刻已经显示的行, 下一轮用 >= 查询并去重
+#[derive(Default)]
+struct Cursor {
+    timestamp: Option<String>,
+    seen: HashSet<String>,
+}
+
+// Application Insights 的 SeverityLevel 0-4, 其它表的 Level 列直接显示
+fn level(row: &Map<String, Value>) -> &str {
+    let severity = match &row.get("SeverityLevel") {
+        Some(Value::String(s)) => s.parse::<u64>().ok(),
+        Some(value) => value.as_u64(),
+        None => None,
+    };
+    match severity {
+        Some(0) => "DEBUG",
+        Some(1) => "INFO",
+        Some(2) => "WARN",
+        Some(3) => "ERROR",
+        Some(_) => "FATAL",
+        None => row.get("Level").and_then(Value::as_str).unwrap_or("INFO"),
+    }
+}
+
+fn column_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+// "时间 级别 消息 列=值 ...", 配置了 fields 时只附加这些列; 没有消息列时附加所有非空列
+fn format_row(row: &Map<String, Value>, fields: &[String]) -> String {
+    let message = MESSAGE_COLUMNS
+        .iter()
+        .find_map(|column| row.get(*column).and_then(Value::as_str));
+    let mut line = format!(
+        "{} {} {}",
+        row.get(TIME_COLUMN).and_then(Value::as_str).unwrap_or_default(),
+        level(row),
+        message.unwrap_or_default().trim_end().replace('\n', " ")
+    );
+    let extra: Vec<(&String, &Value)> = if fields.is_empty() {
+        match message {
+            Some(_) => Vec::new(),
+            None => row
+                .iter()
+                .filter(|(column, _)| !HIDDEN_COLUMNS.contains(&column.as_str()))
+                .collect(),
+        }
+    } else {
+        fields.iter().filter_map(|field| row.get_key_value(field)).collect()
+    };
+    for (column, value) in extra {
+        if !value.is_null() && value.as_str() != Some("") {
+            line.push_str(&format!(" {}={}", column, column_text(value)));
+        }
+    }
+    line.trim_end().to_string() + "\n"
+}
+
+// 在用户的 KQL 后面追加时间条件和排序
+fn query(log: &config::LogConfig, base: &str, cursor: &Cursor) -> String {
+    let since = match &cursor.timestamp {
+        Some(timestamp) => format!("{} >= datetime({})", TIME_COLUMN, timestamp),
+        None => format!("{} > ago({})", TIME_COLUMN, log.since.as_deref().unwrap_or("10m")),
+    };
+    format!(
+        "{}\n| where {}\n| order by {} asc\n| take {}",
+        base.trim().trim_end_matches(';'),
+        since,
+        TIME_COLUMN,
+        PAGE_SIZE
+    )
+}
+
+fn poll(
+    log: &config::LogConfig,
+    workspace: &str,
+    base: &str,
+    sink: &LogSink,
+    requests: &Receiver<SourceRequest>,
+    cursor: &mut Cursor,
+) -> Result<(), String> {
+    let mut command = Command::new("az");
+    command
+        .args(["monitor", "log-analytics", "query", "--workspace", workspace])
+        .args(["--analytics-query", &query(log, base, cursor)])
+        .args(["--output", "json"]);
+    let output = wait_output(command, None, sink, requests)
+        .map_err(|e| format!("{}: {}", tr(Msg::CommandErr), e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "{}: {}",
+            tr(Msg::CommandExited),
+            stderr.trim().lines().last().unwrap_or_default()
+        ));
+    }
+
+    sink.stats.lock().unwrap().bytes_received += output.stdout.len() as u64;
+    let rows: Vec<Map<String, Value>> = serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+    let fields = log.fields.as_deref().unwrap_or_default();
+    for row in &rows {
+        let timestamp = row.get(TIME_COLUMN).and_then(Value::as_str).unwrap_or_default();
+        if cursor.timestamp.as_deref() != Some(timestamp) {
+            cursor.timestamp = Some(timestamp.to_string());
+            cursor.seen.clear();
+        }
+        // 结果行没有唯一 id, 用整行内容去重
+        if cursor.seen.insert(Value::Object(row.clone()).to_string()) {
+            sink.push_line(format_row(row, fields));
+        }
+    }
+    Ok(())
+}
+
+// 按 poll_interval 轮询 `az monitor log-analytics query`, 出错时退避
+pub fn tail(log: &config::LogConfig, sink: LogSink, requests: Receiver<SourceRequest>) {
+    let (Some(workspace), Some(base)) = (log.workspace.as_deref(), log.query.as_deref()) else {
+        sink.set_status(ConnectionStatus::Error(tr(Msg::NoWorkspace).to_string()));
+        loop {
+            idle(Duration::from_secs(1), &sink, &requests);
+        }
+    };
+    let poll_interval = log
+        .poll_interval
+        .as_deref()
+        .and_then(timestamp::parse_duration)
+        .unwrap_or(POLL_INTERVAL);
+    let mut cursor = Cursor::default();
+    let mut interval = poll_interval;
+    loop {
+        match poll(log, workspace, base, &sink, &requests, &mut cursor) {
+            Ok(()) => {
+                sink.set_status(ConnectionStatus::Connected);
+                interval = poll_interval;
+            }
+            Err(e) => {
+                sink.set_status(ConnectionStatus::Error(e));
+                interval = (interval * 2).min(MAX_BACKOFF);
+            }
+        }
+        idle(interval, &sink, &requests);
+    }
+}
@@ -0,0 +1,70 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: rogger-ctl [-s SOCKET] COMMAND [ARGS...]
+
+commands:
+  list                     list windows
+  select WINDOW            select a window
+  maximize WINDOW          select and maximize a window
+  filter WINDOW [PATTERN]  add a filter showing only matching lines, no pattern to clear them
+  exclude WINDOW [PATTERN] add a filter hiding matching lines, no pattern to clear them
+  search WINDOW PATTERN    search in a window and jump to the first match
+  goto WINDOW HH:MM[:SS]   jump to the line nearest that time of day";
+
+// 与 rogger 使用同一个默认路径
+fn default_socket() -> io::Result<PathBuf> {
+    if let Some(path) = std::env::var_os("ROGGER_SOCKET") {
+        return Ok(PathBuf::from(path));
+    }
+    let home = std::env::var("HOME").map_err(|_| {
+        io::Error::new(io::ErrorKind::NotFound, "HOME environment variable not set")
+    })?;
+    Ok(PathBuf::from(home).join(".rogger/ctl.sock"))
+}
+
+fn send(socket: PathBuf, args: &[String]) -> io::Result<String> {
+    let mut stream = UnixStream::connect(&socket)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", socket.display(), e)))?;
+    let request = serde_json::to_string(args).map_err(io::Error::other)?;
+    writeln!(stream, "{}", request)?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    Ok(reply.trim_end().to_string())
+}
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let socket = if args.first().map(String::as_str) == Some("-s") && args.len() > 1 {
+        let path = args.remove(1);
+        args.remove(0);
+        Ok(PathBuf::from(path))
+    } else {
+        default_socket()
+    };
+    if args.is_empty() || args[0] == "-h" || args[0] == "--help" {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    }
+
+    match socket.and_then(|socket| send(socket, &args)) {
+        Ok(reply) => match reply.split_once(' ').unwrap_or((&reply, "")) {
+            ("ok", message) => {
+                if !message.is_empty() {
+                    println!("{}", message);
+                }
+                ExitCode::SUCCESS
+            }
+            (_, message) => {
+                eprintln!("rogger-ctl: {}", message);
+                ExitCode::FAILURE
+            }
+        },
+        Err(e) => {
+            eprintln!("rogger-ctl: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
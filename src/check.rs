@@ -0,0 +1,112 @@
+use std::os::unix::fs::PermissionsExt;
+
+use crate::config::{self, SourceType};
+use crate::i18n::{tr, Msg};
+
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+enum Severity {
+    Info,
+    Warn,
+    High,
+}
+
+impl Severity {
+    fn label(self) -> &'static str {
+        match self {
+            Severity::Info => "\x1b[36mINFO\x1b[0m",
+            Severity::Warn => "\x1b[33mWARN\x1b[0m",
+            Severity::High => "\x1b[31mHIGH\x1b[0m",
+        }
+    }
+}
+
+struct Lint {
+    severity: Severity,
+    subject: String,
+    problem: String,
+    fix: String,
+}
+
+fn permission_lint(path: &str, has_secrets: bool) -> Option<Lint> {
+    let mode = std::fs::metadata(config::expand_tilde(path).ok()?).ok()?.permissions().mode();
+    let (severity, problem) = if mode & 0o004 != 0 {
+        (Severity::High, Msg::CheckWorldReadable)
+    } else if mode & 0o040 != 0 {
+        (Severity::Warn, Msg::CheckGroupReadable)
+    } else {
+        return None;
+    };
+    // 没有明文密钥时只是泄露主机和路径, 降一级
+    let severity = match severity {
+        Severity::High if !has_secrets => Severity::Warn,
+        Severity::Warn if !has_secrets => Severity::Info,
+        severity => severity,
+    };
+    Some(Lint {
+        severity,
+        subject: path.to_string(),
+        problem: format!("{} ({:o})", tr(problem), mode & 0o777),
+        fix: format!("chmod 600 {}", path),
+    })
+}
+
+fn lints(path: &str, logs: &[config::LogConfig]) -> Result<Vec<Lint>, String> {
+    let secrets = config::plaintext_secrets(path).map_err(|e| e.to_string())?;
+    let mut lints: Vec<Lint> = secrets
+        .iter()
+        .map(|(owner, key)| Lint {
+            severity: Severity::High,
+            subject: owner.clone(),
+            problem: format!("{} ({})", tr(Msg::CheckPlaintext), key),
+            fix: tr(Msg::CheckPlaintextFix).to_string(),
+        })
+        .collect();
+    lints.extend(permission_lint(path, !secrets.is_empty()));
+
+    // 连接 SSH 时不校验 known_hosts, 中间人可以冒充目标主机
+    for log in logs {
+        let remote = matches!(log.source, SourceType::Ssh | SourceType::Journal)
+            || (log.source == SourceType::Podman && !log.host.is_empty());
+        if remote && log.host_key_check.unwrap_or_default() == config::HostKeyCheck::Off {
+            lints.push(Lint {
+                severity: Severity::Warn,
+                subject: log.name.clone(),
+                problem: format!("{} ({})", tr(Msg::CheckHostKey), log.host),
+                fix: tr(Msg::CheckHostKeyFix).to_string(),
+            });
+        }
+    }
+    lints.sort_by(|a, b| b.severity.partial_cmp(&a.severity).unwrap());
+    Ok(lints)
+}
+
+// rogger check: 校验配置并列出不安全的设置, 有 HIGH 级别的问题时返回 false
+pub fn run(path: &str) -> bool {
+    let logs = match config::read_config(path) {
+        Ok(config) => {
+            crate::i18n::init(config.global.as_ref().and_then(|g| g.locale.as_deref()));
+            config.logs
+        }
+        Err(e) => {
+            println!("{}  {}: {}", Severity::High.label(), path, e);
+            return false;
+        }
+    };
+    let lints = match lints(path, &logs) {
+        Ok(lints) => lints,
+        Err(e) => {
+            println!("{}  {}: {}", Severity::High.label(), path, e);
+            return false;
+        }
+    };
+    for lint in &lints {
+        println!("{}  \x1b[1m{}\x1b[0m  {}", lint.severity.label(), lint.subject, lint.problem);
+        println!("      {} {}", tr(Msg::CheckFix), lint.fix);
+    }
+    if lints.is_empty() {
+        println!("{}", tr(Msg::CheckOk));
+    } else {
+        println!("{} {}", lints.len(), tr(Msg::CheckIssues));
+    }
+    !lints.iter().any(|lint| lint.severity == Severity::High)
+}
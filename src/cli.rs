@@ -0,0 +1,63 @@
+pub struct Args {
+    pub config_path: String,
+    pub replay: Vec<String>,
+    pub speed: f64,
+    pub stdin: bool,
+    pub name: String,
+    pub layout: Option<String>,
+    pub preflight: bool,
+    pub dry_run: bool,
+    pub check: bool,
+}
+
+pub fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        config_path: "~/.rogger/config.toml".to_string(),
+        replay: Vec::new(),
+        speed: 1.0,
+        stdin: false,
+        name: "stdin".to_string(),
+        layout: None,
+        preflight: false,
+        dry_run: false,
+        check: false,
+    };
+
+    let mut iter = std::env::args().skip(1).peekable();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-c" | "--config" => {
+                args.config_path = iter.next().ok_or("--config needs a path")?;
+            }
+            "--replay" => {
+                // 收集到下一个选项为止的所有文件
+                while let Some(file) = iter.next_if(|a| !a.starts_with('-')) {
+                    args.replay.push(file);
+                }
+                if args.replay.is_empty() {
+                    return Err("--replay needs at least one file".to_string());
+                }
+            }
+            "--speed" => {
+                args.speed = iter
+                    .next()
+                    .and_then(|s| s.parse().ok())
+                    .filter(|s: &f64| *s > 0.0)
+                    .ok_or("--speed needs a positive number")?;
+            }
+            "--stdin" => args.stdin = true,
+            "--preflight" => args.preflight = true,
+            "--dry-run" => args.dry_run = true,
+            "check" => args.check = true,
+            "--layout" => {
+                args.layout = Some(iter.next().ok_or("--layout needs a layout name")?);
+            }
+            "--name" => {
+                args.name = iter.next().ok_or("--name needs a window name")?;
+            }
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+
+    Ok(args)
+}
@@ -0,0 +1,91 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::process::Command;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+use crate::command::{idle, supervise};
+use crate::config;
+use crate::i18n::{tr, Msg};
+use crate::ssh::{ConnectionStatus, LogSink, SourceRequest};
+
+// 最后一条事件的时间和同一时间的行, 重启 `aws logs tail` 时从这里续上并去重
+#[derive(Default)]
+struct Cursor {
+    timestamp: String,
+    seen: HashSet<String>,
+}
+
+impl Cursor {
+    // short 格式的行以 ISO 8601 时间开头, 同一时区下可以直接按字符串比较
+    fn accept(&mut self, line: &str) -> bool {
+        let Some(timestamp) = line
+            .split_whitespace()
+            .next()
+            .filter(|t| t.starts_with(|c: char| c.is_ascii_digit()))
+        else {
+            return true;
+        };
+        if timestamp < self.timestamp.as_str() {
+            return false;
+        }
+        if timestamp > self.timestamp.as_str() {
+            self.timestamp = timestamp.to_string();
+            self.seen.clear();
+        }
+        self.seen.insert(line.to_string())
+    }
+}
+
+fn tail_args(log: &config::LogConfig, group: &str, cursor: &Cursor) -> Vec<String> {
+    let since = if cursor.timestamp.is_empty() {
+        log.since.clone().unwrap_or_else(|| "10m".to_string())
+    } else {
+        cursor.timestamp.clone()
+    };
+    let mut argv: Vec<String> = ["aws", "logs", "tail", group, "--follow", "--format", "short"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+    argv.extend(["--since".to_string(), since]);
+    if let Some(stream) = &log.log_stream {
+        argv.extend(["--log-stream-names".to_string(), stream.clone()]);
+    }
+    if let Some(pattern) = &log.filter {
+        argv.extend(["--filter-pattern".to_string(), pattern.clone()]);
+    }
+    // 凭证走 AWS CLI 的默认链: 环境变量、profile、SSO、实例角色
+    if let Some(region) = &log.region {
+        argv.extend(["--region".to_string(), region.clone()]);
+    }
+    if let Some(profile) = &log.profile {
+        argv.extend(["--profile".to_string(), profile.clone()]);
+    }
+    argv
+}
+
+pub fn tail(log: &config::LogConfig, sink: LogSink, requests: Receiver<SourceRequest>) {
+    let Some(group) = log.log_group.clone() else {
+        sink.set_status(ConnectionStatus::Error(tr(Msg::NoLogGroup).to_string()));
+        loop {
+            idle(Duration::from_secs(1), &sink, &requests);
+        }
+    };
+
+    let cursor = RefCell::new(Cursor::default());
+    supervise(
+        &sink,
+        &requests,
+        || {
+            let argv = tail_args(log, &group, &cursor.borrow());
+            let mut command = Command::new(&argv[0]);
+            command.args(&argv[1..]);
+            command
+        },
+        |line| {
+            if cursor.borrow_mut().accept(&line) {
+                sink.push_line(line);
+            }
+        },
+    );
+}
@@ -0,0 +1,292 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config;
+use crate::i18n::{tr, Msg};
+use crate::local::run_local;
+use crate::ssh::{ConnectionStatus, DownloadProgress, LogSink, SourceRequest};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+// stdout 和 stderr 各用一个线程读取, 汇总到同一个 channel
+pub fn forward_lines(stream: impl Read + Send + 'static, lines: Sender<Vec<u8>>) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        loop {
+            let mut buf = Vec::new();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    if lines.send(buf).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+pub fn handle_request(request: SourceRequest, sink: &LogSink) {
+    match request {
+        SourceRequest::Exec { command, reply } => {
+            let _ = reply.send(run_local(&command));
+        }
+        // 命令输出没有对应的文件, 不支持切换、回填和下载
+        SourceRequest::SwitchPath(_)
+        | SourceRequest::Refresh
+        | SourceRequest::Suspend
+        | SourceRequest::Resume => {}
+        SourceRequest::Download(local_path) => {
+            *sink.download.lock().unwrap() = Some(DownloadProgress {
+                local_path,
+                total: 0,
+                done: 0,
+                finished: false,
+                error: Some(tr(Msg::Unsupported).to_string()),
+            });
+        }
+    }
+}
+
+// 等待下一行输出, 并处理 UI 请求
+fn receive_lines(lines: &Receiver<Vec<u8>>, sink: &LogSink, requests: &Receiver<SourceRequest>) -> bool {
+    let open = match lines.recv_timeout(POLL_INTERVAL) {
+        Ok(bytes) => {
+            sink.stats.lock().unwrap().bytes_received += bytes.len() as u64;
+            sink.push_line(sink.decode(&bytes));
+            true
+        }
+        Err(RecvTimeoutError::Timeout) => true,
+        // 输出已经读完, 之后只处理 UI 请求
+        Err(RecvTimeoutError::Disconnected) => {
+            thread::sleep(POLL_INTERVAL);
+            false
+        }
+    };
+
+    while let Ok(request) = requests.try_recv() {
+        handle_request(request, sink);
+    }
+    open
+}
+
+// 外部 CLI 退出后按指数退避重启, 用于需要长期跟随的云日志命令
+pub fn supervise(
+    sink: &LogSink,
+    requests: &Receiver<SourceRequest>,
+    mut command: impl FnMut() -> Command,
+    mut on_line: impl FnMut(String),
+) {
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        let started = Instant::now();
+        let spawned = command()
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+        let reason = match spawned {
+            Ok(mut child) => {
+                sink.set_status(ConnectionStatus::Connected);
+                let (line_sender, lines) = mpsc::channel();
+                if let Some(stdout) = child.stdout.take() {
+                    forward_lines(stdout, line_sender);
+                }
+                // stderr 也要边运行边读, 否则写满管道后命令会卡住, stdout 也跟着停; 只留最后一行用于退出提示
+                let (error_sender, errors) = mpsc::channel();
+                if let Some(stderr) = child.stderr.take() {
+                    forward_lines(stderr, error_sender);
+                }
+                let mut last_error = None;
+                let mut keep_last = |bytes: Vec<u8>| {
+                    let line = String::from_utf8_lossy(&bytes).trim().to_string();
+                    if !line.is_empty() {
+                        last_error = Some(line);
+                    }
+                };
+                loop {
+                    match lines.recv_timeout(POLL_INTERVAL) {
+                        Ok(bytes) => {
+                            sink.stats.lock().unwrap().bytes_received += bytes.len() as u64;
+                            on_line(sink.decode(&bytes));
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                    errors.try_iter().for_each(&mut keep_last);
+                    while let Ok(request) = requests.try_recv() {
+                        handle_request(request, sink);
+                    }
+                }
+                let status = child.wait();
+                while let Ok(bytes) = errors.recv_timeout(POLL_INTERVAL) {
+                    keep_last(bytes);
+                }
+                match (last_error, status) {
+                    (Some(message), _) => format!("{}: {}", tr(Msg::CommandExited), message),
+                    (None, status) => format!(
+                        "{}: {}",
+                        tr(Msg::CommandExited),
+                        status.map(|s| s.to_string()).unwrap_or_default()
+                    ),
+                }
+            }
+            Err(e) => format!("{}: {}", tr(Msg::CommandErr), e),
+        };
+        sink.set_status(ConnectionStatus::Error(reason));
+
+        // 限流或认证失败时退出得很快, 退避避免反复请求
+        if started.elapsed() > Duration::from_secs(60) {
+            backoff = Duration::from_secs(1);
+        }
+        idle(backoff, sink, requests);
+        backoff = (backoff * 2).min(Duration::from_secs(30));
+    }
+}
+
+// 在后台线程运行一次性命令并等待结果, 期间照常处理 UI 请求; input 写入命令的 stdin
+pub fn wait_output(
+    mut command: Command,
+    input: Option<String>,
+    sink: &LogSink,
+    requests: &Receiver<SourceRequest>,
+) -> io::Result<Output> {
+    let (sender, result) = mpsc::channel();
+    thread::spawn(move || {
+        let run = || {
+            let mut child = command
+                .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()?;
+            if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+                stdin.write_all(input.as_bytes())?;
+            }
+            child.wait_with_output()
+        };
+        let _ = sender.send(run());
+    });
+    loop {
+        match result.recv_timeout(POLL_INTERVAL) {
+            Ok(output) => return output,
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Err(io::Error::other(tr(Msg::CommandErr))),
+        }
+        while let Ok(request) = requests.try_recv() {
+            handle_request(request, sink);
+        }
+    }
+}
+
+// 等待期间照常处理 UI 请求
+pub fn idle(duration: Duration, sink: &LogSink, requests: &Receiver<SourceRequest>) {
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline {
+        while let Ok(request) = requests.try_recv() {
+            handle_request(request, sink);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+// 从管道读入, 例如 `producer | rogger --stdin`
+pub fn read_stdin(sink: LogSink, requests: Receiver<SourceRequest>) {
+    let (line_sender, lines) = mpsc::channel();
+    forward_lines(io::stdin(), line_sender);
+
+    let mut closed = false;
+    loop {
+        let open = receive_lines(&lines, &sink, &requests);
+        if !open && !closed {
+            sink.set_status(ConnectionStatus::Error(tr(Msg::StdinClosed).to_string()));
+            closed = true;
+        }
+    }
+}
+
+// type = "compose" 时按 `docker compose ps` 列出的服务展开成多个命令窗口
+pub fn compose_services(log: &config::LogConfig) -> Result<Vec<config::LogConfig>, String> {
+    let project = log.project.clone().unwrap_or_else(|| ".".to_string());
+    let compose = |args: &[&str]| {
+        let mut argv = vec!["docker", "compose", "--project-directory", project.as_str()];
+        argv.extend_from_slice(args);
+        argv.into_iter().map(str::to_string).collect::<Vec<_>>()
+    };
+
+    let argv = compose(&["ps", "--services"]);
+    let output = Command::new(&argv[0])
+        .args(&argv[1..])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("{}: {}", tr(Msg::CommandErr), e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{}: {}",
+            tr(Msg::CommandErr),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|service| !service.is_empty())
+        .map(|service| config::LogConfig {
+            name: format!("{}/{}", log.name, service),
+            source: config::SourceType::Command,
+            command: Some(compose(&["logs", "-f", "--tail", "100", "--no-log-prefix", service])),
+            ..log.clone()
+        })
+        .collect())
+}
+
+pub fn run_command(log: &config::LogConfig, sink: LogSink, requests: Receiver<SourceRequest>) {
+    let argv = log.command.clone().unwrap_or_default();
+    let spawned = match argv.split_first() {
+        Some((program, args)) => Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("{}: {}", tr(Msg::CommandErr), e)),
+        None => Err(format!("{}: {}", tr(Msg::CommandErr), tr(Msg::NoCommand))),
+    };
+
+    let mut child = match spawned {
+        Ok(child) => Some(child),
+        Err(e) => {
+            sink.set_status(ConnectionStatus::Error(e));
+            None
+        }
+    };
+
+    let (line_sender, lines) = mpsc::channel();
+    if let Some(child) = child.as_mut() {
+        if let Some(stdout) = child.stdout.take() {
+            forward_lines(stdout, line_sender.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_lines(stderr, line_sender.clone());
+        }
+    }
+    drop(line_sender);
+
+    loop {
+        receive_lines(&lines, &sink, &requests);
+
+        if let Some(running) = child.as_mut() {
+            if let Ok(Some(status)) = running.try_wait() {
+                sink.set_status(ConnectionStatus::Error(format!(
+                    "{}: {}",
+                    tr(Msg::CommandExited),
+                    status
+                )));
+                child = None;
+            }
+        }
+    }
+}
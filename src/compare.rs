@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::suggest;
+use crate::timestamp;
+use crate::ui::LogWindow;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+const RECENT_LINES: usize = 1000;
+// 同一模式在一个副本里出现这么多次而另一个副本一次都没有, 才认为是分歧
+const MIN_COUNT: usize = 3;
+const MAX_PATTERNS: usize = 5;
+
+// 本窗口出现、对端副本没有出现的模式, 按出现次数排列
+pub struct Divergence {
+    pub peer: String,
+    pub patterns: Vec<(String, usize)>,
+}
+
+struct Side {
+    name: String,
+    content: Arc<Mutex<Vec<String>>>,
+    divergence: Arc<Mutex<Option<Divergence>>>,
+    timestamps: Arc<timestamp::Detector>,
+}
+
+impl Side {
+    fn new(window: &LogWindow) -> Self {
+        Side {
+            name: window.name.clone(),
+            content: Arc::clone(&window.content),
+            divergence: Arc::clone(&window.divergence),
+            timestamps: Arc::clone(&window.timestamps),
+        }
+    }
+
+    fn recent(&self) -> Vec<String> {
+        let content = self.content.lock().unwrap();
+        content[content.len().saturating_sub(RECENT_LINES)..].to_vec()
+    }
+}
+
+fn count_templates(lines: &[String]) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for line in lines {
+        let template = suggest::normalize(line);
+        if !template.is_empty() {
+            *counts.entry(template).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+fn unique(own: &HashMap<String, usize>, other: &HashMap<String, usize>) -> Vec<(String, usize)> {
+    let mut patterns: Vec<(String, usize)> = own
+        .iter()
+        .filter(|(template, &count)| count >= MIN_COUNT && !other.contains_key(*template))
+        .map(|(template, &count)| (template.clone(), count))
+        .collect();
+    patterns.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    patterns.truncate(MAX_PATTERNS);
+    patterns
+}
+
+// 两边都带时间戳时只比较共同覆盖的时间段, 否则比较各自最近的行
+fn overlap(a: (Vec<String>, timestamp::Format), b: (Vec<String>, timestamp::Format)) -> (Vec<String>, Vec<String>) {
+    let start = |(lines, format): &(Vec<String>, timestamp::Format)| {
+        lines.iter().find_map(|line| format.parse(line))
+    };
+    let (Some(start_a), Some(start_b)) = (start(&a), start(&b)) else {
+        return (a.0, b.0);
+    };
+    let cutoff = start_a.max(start_b);
+    let trim = |(lines, format): (Vec<String>, timestamp::Format)| {
+        let from = timestamp::nearest_line(&lines, cutoff, format).unwrap_or(0);
+        lines[from..].to_vec()
+    };
+    (trim(a), trim(b))
+}
+
+fn compare(a: &Side, b: &Side) {
+    let (recent_a, recent_b) = overlap(
+        (a.recent(), a.timestamps.format()),
+        (b.recent(), b.timestamps.format()),
+    );
+    // 一边还没有输出时(例如正在连接)不做比较
+    if recent_a.is_empty() || recent_b.is_empty() {
+        *a.divergence.lock().unwrap() = None;
+        *b.divergence.lock().unwrap() = None;
+        return;
+    }
+    let (counts_a, counts_b) = (count_templates(&recent_a), count_templates(&recent_b));
+    for (side, own, other, peer) in [(a, &counts_a, &counts_b, b), (b, &counts_b, &counts_a, a)] {
+        *side.divergence.lock().unwrap() = Some(Divergence {
+            peer: peer.name.clone(),
+            patterns: unique(own, other),
+        });
+    }
+}
+
+// compare_with 配对的两个窗口定期比较最近的行, 归一化后只在一边出现的模式标记到窗口标题
+pub fn spawn(pairs: &[(String, String)], windows: &[LogWindow]) {
+    let find = |name: &str| windows.iter().find(|window| window.name == name).map(Side::new);
+    let sides: Vec<(Side, Side)> = pairs
+        .iter()
+        .filter_map(|(a, b)| Some((find(a)?, find(b)?)))
+        .collect();
+    if sides.is_empty() {
+        return;
+    }
+
+    thread::spawn(move || loop {
+        thread::sleep(CHECK_INTERVAL);
+        for (a, b) in &sides {
+            compare(a, b);
+        }
+    });
+}
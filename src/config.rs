@@ -1,50 +1,576 @@
+use regex::{Captures, Regex};
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::fs;
-use std::io;
+
+use crate::layout::LayoutConfig;
+use std::io::{self, Write};
 use std::path::PathBuf;
-use toml;
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, Default)]
 pub struct Config {
     pub logs: Vec<LogConfig>,
     // pub regexps: Vec<RegexConfig>,
-    // pub global: GlobalConfig,
+    pub global: Option<GlobalConfig>,
+    pub layouts: Option<BTreeMap<String, LayoutConfig>>,
+    pub highlights: Option<Vec<HighlightConfig>>,
+    pub silences: Option<Vec<SilenceConfig>>,
+    pub filters: Option<Vec<FilterPreset>>,
+    // 团队共用的命名搜索, 按 S 列出, 可以作为搜索或过滤用在任意窗口
+    pub searches: Option<BTreeMap<String, String>>,
+}
+
+// 命名的过滤预设, key 为 0-9 时按对应数字键套用到选中窗口; exclude = true 时作为 ! 过滤
+#[derive(Deserialize, Debug, Clone)]
+pub struct FilterPreset {
+    pub name: String,
+    pub pattern: String,
+    pub key: Option<String>,
+    pub exclude: Option<bool>,
+}
+
+// 匹配的行照常显示, 但不算作告警; 运行时按 z 屏蔽的模式追加到配置文件
+#[derive(Deserialize, Debug, Clone)]
+pub struct SilenceConfig {
+    pub pattern: String,
+}
+
+// 自定义高亮规则, alert = true 的规则在 auto_pause_on_error 时也会触发暂停
+#[derive(Deserialize, Debug, Clone)]
+pub struct HighlightConfig {
+    pub pattern: String,
+    pub color: Option<String>,
+    pub alert: Option<bool>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct GlobalConfig {
+    // false 时窗口默认不折行, 用 W 切换
     pub auto_wrapping: Option<bool>,
+    pub locale: Option<String>,
+    pub otlp: Option<OtlpConfig>,
+    pub max_rss_mb: Option<u64>,
+    pub ssh_algorithms: Option<SshAlgorithms>,
+    pub host_key_check: Option<HostKeyCheck>,
+    pub hyperlinks: Option<bool>,
+    // 把搜索历史保存到 ~/.rogger/search_history, 下次启动时可以继续用上下键调出
+    pub save_search_history: Option<bool>,
+    // rogger-ctl 连接的 Unix socket, 默认 ~/.rogger/ctl.sock
+    pub control_socket: Option<String>,
+    // 普通布局中按主机分组显示窗口, 每组带一行可折叠的标题
+    pub group_by_host: Option<bool>,
 }
 
+// 把读到的行转发到 OpenTelemetry collector (OTLP/HTTP JSON)
 #[derive(Deserialize, Debug, Clone)]
-pub struct RegexConfig {}
+pub struct OtlpConfig {
+    pub endpoint: String,
+    pub service_name: Option<String>,
+}
 
+#[allow(dead_code)]
 #[derive(Deserialize, Debug, Clone)]
+pub struct RegexConfig {}
+
+#[derive(Deserialize, Debug, Clone, Default)]
 pub struct LogConfig {
     pub name: String,
+    #[serde(rename = "type", default)]
+    pub source: SourceType,
+    #[serde(default)]
     pub host: String,
+    #[serde(default)]
     pub port: u16,
-    pub log_path: String,
+    pub log_path: LogPath,
     pub username: Option<String>,
     pub password: Option<String>,
     pub ssh_key: Option<String>,
+    pub ssh_cert: Option<String>,
+    // 认证方式及尝试顺序, 例如 ["agent", "keyboard-interactive"]
+    pub auth: Option<Vec<crate::auth::AuthMethod>>,
+    // 连接时执行的本地命令, 输出作为密码或私钥, 例如 "pass show prod/web"
+    pub password_cmd: Option<String>,
+    pub ssh_key_cmd: Option<String>,
     pub max_history: Option<usize>,
+    pub compression: Option<bool>,
+    pub wrap_marker: Option<String>,
+    pub hanging_indent: Option<usize>,
+    pub word_wrap: Option<bool>,
+    pub browse_dir: Option<String>,
+    pub auto_pause_on_error: Option<bool>,
+    pub pre_connect: Option<String>,
+    pub post_connect: Option<String>,
+    pub ssh_algorithms: Option<SshAlgorithms>,
+    // 按 ~/.ssh/known_hosts 校验服务器公钥, 没有设置时用 [global] 的值, 默认不校验
+    pub host_key_check: Option<HostKeyCheck>,
+    // false 时位置和链接里只有字节偏移, 连接时不再统计偏移之前的行数
+    pub line_numbers: Option<bool>,
+    pub max_kbps: Option<u64>,
+    pub command: Option<Vec<String>>,
+    pub archive: Option<ArchiveConfig>,
+    pub encoding: Option<String>,
+    pub project: Option<String>,
+    pub transforms: Option<Vec<TransformConfig>>,
+    pub highlights: Option<Vec<HighlightConfig>>,
+    pub unit: Option<String>,
+    pub priority: Option<String>,
+    pub since: Option<String>,
+    pub output: Option<String>,
+    pub listen: Option<String>,
+    pub protocol: Option<String>,
+    pub forward: Option<ForwardConfig>,
+    pub url: Option<String>,
+    pub headers: Option<BTreeMap<String, String>>,
+    pub token: Option<String>,
+    pub max_age: Option<String>,
+    // 首次连接时回填的时间范围, 例如 "30m", 代替默认的最后 100 行
+    pub backfill: Option<String>,
+    // 窗口这么久没有显示时断开 SSH 连接, 再次显示时从断开处续传
+    pub idle_disconnect: Option<String>,
+    // 新到的行在这段时间内高亮显示并逐渐淡出, 例如 "3s"
+    pub highlight_new: Option<String>,
+    // 排除过滤(!)同时作用于新收到的行, 匹配的行不再存入缓冲区
+    pub drop_excluded: Option<bool>,
+    // 把缩进输出的多行 JSON 合并成一行后再处理
+    pub json_multiline: Option<bool>,
+    pub log_group: Option<String>,
+    pub log_stream: Option<String>,
+    pub region: Option<String>,
+    pub profile: Option<String>,
+    pub filter: Option<String>,
+    pub credentials: Option<String>,
+    pub sample: Option<usize>,
+    // & 过滤时在每个匹配前后多显示的行数, 运行时用 +/- 调整
+    pub context_lines: Option<usize>,
+    pub query: Option<String>,
+    pub labels: Option<String>,
+    pub index: Option<String>,
+    pub channel: Option<String>,
+    pub stream: Option<String>,
+    pub database: Option<u32>,
+    pub tls: Option<bool>,
+    pub ca_file: Option<String>,
+    pub lnav_formats: Option<Vec<String>>,
+    pub device: Option<String>,
+    pub baud: Option<u32>,
+    pub line_ending: Option<String>,
+    pub fields: Option<Vec<String>>,
+    pub compare_with: Option<String>,
+    pub shared_key: Option<String>,
+    pub container: Option<String>,
+    pub socket: Option<String>,
+    // 不配置时从最早的几十行自动识别
+    pub timestamp_format: Option<crate::timestamp::Format>,
+    pub decompress: Option<bool>,
+    pub live_path: Option<String>,
+    pub workspace: Option<String>,
+    pub poll_interval: Option<String>,
+    // type = "replay" 的回放倍速, 不配置时一次性载入整个文件
+    pub speed: Option<f64>,
+}
+
+// 写入缓冲区前按顺序对每一行做处理
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum TransformConfig {
+    Redact {
+        pattern: String,
+        replacement: Option<String>,
+    },
+    StripAnsi,
+    JsonFlatten,
+    Prefix {
+        text: String,
+    },
+    Script {
+        command: String,
+    },
+    // %{IPORHOST:client} %{WORD:method} ... 提取的字段写成 JSON, 原始行放在 message 里
+    Grok {
+        pattern: String,
+        // Logstash 格式的 patterns 文件, 以及直接写在配置里的定义
+        patterns_files: Option<Vec<String>>,
+        definitions: Option<BTreeMap<String, String>>,
+    },
+}
+
+// 超出 max_history 的行写到哪里
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ArchiveConfig {
+    Local {
+        dir: Option<String>,
+    },
+    S3 {
+        bucket: String,
+        prefix: Option<String>,
+    },
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceType {
+    #[default]
+    Ssh,
+    Local,
+    Command,
+    Compose,
+    Journal,
+    Syslog,
+    Sse,
+    Cloudwatch,
+    Gcp,
+    Loki,
+    #[serde(alias = "opensearch")]
+    Elasticsearch,
+    Redis,
+    Serial,
+    Gelf,
+    Forward,
+    Podman,
+    Azure,
+    Replay,
+    Winevent,
+}
+
+// 按优先级排列的算法列表, 用于只支持旧算法的设备
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct SshAlgorithms {
+    pub kex: Option<Vec<String>>,
+    pub host_key: Option<Vec<String>>,
+    pub ciphers: Option<Vec<String>>,
+    pub macs: Option<Vec<String>>,
+    // 没有显式列表的类型启用 libssh2 支持的全部算法, 包括默认不协商的旧算法
+    pub legacy: Option<bool>,
+}
+
+impl SshAlgorithms {
+    // 逐项合并, 窗口自己的设置优先
+    fn or(&self, defaults: &SshAlgorithms) -> SshAlgorithms {
+        SshAlgorithms {
+            kex: self.kex.clone().or_else(|| defaults.kex.clone()),
+            host_key: self.host_key.clone().or_else(|| defaults.host_key.clone()),
+            ciphers: self.ciphers.clone().or_else(|| defaults.ciphers.clone()),
+            macs: self.macs.clone().or_else(|| defaults.macs.clone()),
+            legacy: self.legacy.or(defaults.legacy),
+        }
+    }
+}
+
+// strict: 只接受 known_hosts 里已有的密钥; accept-new: 第一次连接时记下密钥, 之后密钥变了同样拒绝
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum HostKeyCheck {
+    #[default]
+    Off,
+    AcceptNew,
+    Strict,
+}
+
+// 经同一个 SSH 会话把本地端口转发到远端, 例如服务的管理或 metrics 接口
+#[derive(Deserialize, Debug, Clone)]
+pub struct ForwardConfig {
+    pub local: u16,
+    pub remote: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum LogPath {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl Default for LogPath {
+    fn default() -> Self {
+        LogPath::Single(String::new())
+    }
+}
+
+impl LogPath {
+    pub fn paths(&self) -> Vec<&str> {
+        match self {
+            LogPath::Single(path) => vec![path.as_str()],
+            LogPath::Multiple(paths) => paths.iter().map(String::as_str).collect(),
+        }
+    }
+
+    // 供远端命令直接拼接的路径参数, 通配符不加引号
+    pub fn shell_args(&self) -> String {
+        self.paths()
+            .iter()
+            .map(|p| crate::ssh::shell_glob_quote(p))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn parent_dir(&self) -> String {
+        let first = self.paths()[0];
+        match first.rsplit_once('/') {
+            Some(("", _)) => "/".to_string(),
+            Some((dir, _)) => dir.to_string(),
+            None => ".".to_string(),
+        }
+    }
+
+    // 多个路径或包含通配符时, 一个窗口同时 tail 多个文件
+    pub fn is_multi(&self) -> bool {
+        let paths = self.paths();
+        paths.len() > 1 || paths.iter().any(|p| p.contains(['*', '?']))
+    }
 }
 
 pub fn read_config(path: &str) -> Result<Config, Box<dyn std::error::Error>> {
     let config_path = expand_tilde(path)?;
     let content = fs::read_to_string(config_path)?;
-    let config: Config = toml::from_str(&content)?;
+    let mut value: toml::Value = toml::from_str(&content)?;
+    expand_env_vars(&mut value)?;
+    apply_templates(&mut value)?;
+    let mut config: Config = value.try_into()?;
+    // 顶层 [[highlights]] 对所有窗口生效, 排在各窗口自己的规则之前
+    let global_highlights = config.highlights.clone().unwrap_or_default();
+    let global_algorithms = config.global.as_ref().and_then(|g| g.ssh_algorithms.clone());
+    let global_host_key_check = config.global.as_ref().and_then(|g| g.host_key_check);
+    for log in config.logs.iter_mut() {
+        let own = log.highlights.take().unwrap_or_default();
+        log.highlights = Some(global_highlights.iter().cloned().chain(own).collect());
+        // lnav 格式定义导入的规则排在最后, 手写的规则优先
+        for path in log.lnav_formats.iter().flatten() {
+            let imported = crate::lnav::import(&expand_tilde(path)?)
+                .map_err(|e| format!("{}: {}", log.name, e))?;
+            log.highlights.get_or_insert_with(Vec::new).extend(imported.highlights);
+            let transforms = log.transforms.get_or_insert_with(Vec::new);
+            let flattened = transforms.iter().any(|t| matches!(t, TransformConfig::JsonFlatten));
+            if imported.json && !flattened {
+                transforms.push(TransformConfig::JsonFlatten);
+            }
+        }
+        // [global.ssh_algorithms] 作为所有 SSH 窗口的默认值
+        if let Some(defaults) = &global_algorithms {
+            log.ssh_algorithms = Some(log.ssh_algorithms.take().unwrap_or_default().or(defaults));
+        }
+        log.host_key_check = log.host_key_check.or(global_host_key_check);
+    }
+    for silence in config.silences.iter().flatten() {
+        Regex::new(&silence.pattern)
+            .map_err(|e| format!("invalid silence {}: {}", silence.pattern, e))?;
+    }
+    for (name, pattern) in config.searches.iter().flatten() {
+        Regex::new(pattern).map_err(|e| format!("invalid search {}: {}", name, e))?;
+    }
+    for preset in config.filters.iter().flatten() {
+        Regex::new(&preset.pattern).map_err(|e| format!("invalid filter {}: {}", preset.name, e))?;
+        if let Some(key) = &preset.key {
+            if key.len() != 1 || !key.chars().all(|c| c.is_ascii_digit()) {
+                return Err(format!("filter {}: key must be a digit 0-9, got {}", preset.name, key).into());
+            }
+        }
+    }
+    for log in &config.logs {
+        if let Some(label) = &log.encoding {
+            if encoding_rs::Encoding::for_label(label.as_bytes()).is_none() {
+                return Err(format!("Unknown encoding for {}: {}", log.name, label).into());
+            }
+        }
+        for rule in log.highlights.iter().flatten() {
+            Regex::new(&rule.pattern)
+                .map_err(|e| format!("{}: invalid highlight {}: {}", log.name, rule.pattern, e))?;
+        }
+        if let Some(max_age) = &log.max_age {
+            if crate::timestamp::parse_duration(max_age).is_none() {
+                return Err(format!("Invalid max_age for {}: {}", log.name, max_age).into());
+            }
+        }
+        if let Some(idle) = &log.idle_disconnect {
+            if crate::timestamp::parse_duration(idle).is_none() {
+                return Err(format!("Invalid idle_disconnect for {}: {}", log.name, idle).into());
+            }
+        }
+        if let Some(fade) = &log.highlight_new {
+            if crate::timestamp::parse_duration(fade).is_none() {
+                return Err(format!("Invalid highlight_new for {}: {}", log.name, fade).into());
+            }
+        }
+        if let Some(backfill) = &log.backfill {
+            if crate::timestamp::parse_duration(backfill).is_none() {
+                return Err(format!("Invalid backfill for {}: {}", log.name, backfill).into());
+            }
+        }
+        if let Some(transforms) = &log.transforms {
+            crate::transform::Pipeline::new(transforms)
+                .map_err(|e| format!("{}: {}", log.name, e))?;
+        }
+    }
     Ok(config)
 }
 
-fn expand_tilde(path: &str) -> io::Result<PathBuf> {
-    if path.starts_with("~/") {
+// [templates.NAME] 定义公共字段, [[logs]] 中用 template = "NAME" 和 vars = { .. } 实例化,
+// 模板里的 {{var}} 会被替换, 条目自身的字段优先
+fn apply_templates(value: &mut toml::Value) -> io::Result<()> {
+    let templates = match value.get("templates") {
+        Some(toml::Value::Table(templates)) => templates.clone(),
+        _ => return Ok(()),
+    };
+    let Some(toml::Value::Array(logs)) = value.get_mut("logs") else {
+        return Ok(());
+    };
+
+    for log in logs.iter_mut() {
+        let Some(entry) = log.as_table_mut() else {
+            continue;
+        };
+        let Some(name) = entry.remove("template") else {
+            continue;
+        };
+        let name = name.as_str().unwrap_or_default().to_string();
+        let template = templates.get(&name).and_then(|t| t.as_table()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Template not found: {}", name),
+            )
+        })?;
+
+        let vars: Vec<(String, String)> = match entry.remove("vars") {
+            Some(toml::Value::Table(vars)) => vars
+                .into_iter()
+                .map(|(k, v)| {
+                    let v = v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string());
+                    (k, v)
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        for (key, field) in template {
+            if !entry.contains_key(key) {
+                let mut field = field.clone();
+                substitute_vars(&mut field, &vars);
+                entry.insert(key.clone(), field);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn substitute_vars(value: &mut toml::Value, vars: &[(String, String)]) {
+    match value {
+        toml::Value::String(s) => {
+            for (name, var) in vars {
+                *s = s.replace(&format!("{{{{{}}}}}", name), var);
+            }
+        }
+        toml::Value::Array(items) => items.iter_mut().for_each(|v| substitute_vars(v, vars)),
+        toml::Value::Table(table) => table.iter_mut().for_each(|(_, v)| substitute_vars(v, vars)),
+        _ => {}
+    }
+}
+
+// 追加到配置文件末尾, 不改动已有内容和注释
+pub fn append_highlight(path: &str, rule: &HighlightConfig) -> io::Result<()> {
+    let mut entry = format!(
+        "\n[[highlights]]\npattern = {}\n",
+        toml::Value::String(rule.pattern.clone())
+    );
+    if let Some(color) = &rule.color {
+        entry.push_str(&format!("color = {}\n", toml::Value::String(color.clone())));
+    }
+    if rule.alert == Some(true) {
+        entry.push_str("alert = true\n");
+    }
+
+    let mut file = fs::OpenOptions::new().append(true).open(expand_tilde(path)?)?;
+    file.write_all(entry.as_bytes())
+}
+
+pub fn append_silence(path: &str, pattern: &str) -> io::Result<()> {
+    let entry = format!("\n[[silences]]\npattern = {}\n", toml::Value::String(pattern.to_string()));
+    let mut file = fs::OpenOptions::new().append(true).open(expand_tilde(path)?)?;
+    file.write_all(entry.as_bytes())
+}
+
+const SECRET_KEYS: &[&str] = &["password", "token", "Authorization"];
+
+// rogger check 用: 没有用 ${VAR} 引用环境变量的密码和 token, 返回 (所在窗口, 字段名)
+pub fn plaintext_secrets(path: &str) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(expand_tilde(path)?)?;
+    let value: toml::Value = toml::from_str(&content)?;
+    let mut found = Vec::new();
+    collect_secrets(&value, None, &mut found);
+    Ok(found)
+}
+
+fn collect_secrets(value: &toml::Value, owner: Option<&str>, found: &mut Vec<(String, String)>) {
+    match value {
+        toml::Value::Table(table) => {
+            let owner = table.get("name").and_then(toml::Value::as_str).or(owner);
+            for (key, value) in table {
+                match value {
+                    toml::Value::String(secret) if SECRET_KEYS.contains(&key.as_str()) => {
+                        if !secret.contains("${") {
+                            found.push((owner.unwrap_or("-").to_string(), key.clone()));
+                        }
+                    }
+                    _ => collect_secrets(value, owner.or(Some(key)), found),
+                }
+            }
+        }
+        toml::Value::Array(items) => {
+            for item in items {
+                collect_secrets(item, owner, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn expand_tilde(path: &str) -> io::Result<PathBuf> {
+    if let Some(rest) = path.strip_prefix("~/") {
         let home = std::env::var("HOME").map_err(|_| {
             io::Error::new(io::ErrorKind::NotFound, "HOME environment variable not set")
         })?;
-        Ok(PathBuf::from(home).join(&path[2..]))
+        Ok(PathBuf::from(home).join(rest))
     } else {
         Ok(PathBuf::from(path))
     }
 }
+
+// 解析后只在字符串值里把 ${VAR} 替换为环境变量, 值里的引号、换行不会破坏 TOML, 注释也不受影响;
+// 未设置时报错而不是静默替换为空串
+fn expand_env_vars(value: &mut toml::Value) -> io::Result<()> {
+    match value {
+        toml::Value::String(s) => *s = expand_env_string(s)?,
+        toml::Value::Array(items) => {
+            for item in items {
+                expand_env_vars(item)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            for (_, item) in table.iter_mut() {
+                expand_env_vars(item)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn expand_env_string(content: &str) -> io::Result<String> {
+    let re = Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap();
+    let mut missing = None;
+
+    let expanded = re.replace_all(content, |caps: &Captures| {
+        std::env::var(&caps[1]).unwrap_or_else(|_| {
+            missing.get_or_insert_with(|| caps[1].to_string());
+            String::new()
+        })
+    });
+
+    match missing {
+        Some(name) => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Environment variable not set: {}", name),
+        )),
+        None => Ok(expanded.into_owned()),
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+
+use crate::suggest;
+
+// 不同窗口在这么短的时间内输出同一模板的行, 才算作共同的问题
+const SHARED_WITHIN: Duration = Duration::from_secs(30);
+const KEEP: Duration = Duration::from_secs(600);
+const MAX_HITS: usize = 256;
+const PRUNE_INTERVAL: Duration = Duration::from_secs(5);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static RECENT: Lazy<Mutex<Recent>> = Lazy::new(|| {
+    Mutex::new(Recent {
+        entries: HashMap::new(),
+        sources: Vec::new(),
+        pruned_at: Instant::now(),
+    })
+});
+
+struct Entry {
+    template: String,
+    hits: VecDeque<(Instant, Arc<str>)>,
+}
+
+struct Recent {
+    entries: HashMap<u64, Entry>,
+    // 窗口名只保存一份, 每次出现只记录引用
+    sources: Vec<Arc<str>>,
+    pruned_at: Instant,
+}
+
+// 多个窗口都出现过的模板, 以及每个窗口出现的次数
+pub struct Shared {
+    pub template: String,
+    pub sources: Vec<(String, usize)>,
+    pub last: Instant,
+}
+
+// 只有一个窗口时没有可比较的对象, 不做归一化
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+fn hash(template: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    template.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub fn record(source: &str, line: &str) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let template = suggest::normalize(line);
+    if !template.chars().any(char::is_alphabetic) {
+        return;
+    }
+    let now = Instant::now();
+    let mut recent = RECENT.lock().unwrap();
+    let source = match recent.sources.iter().find(|name| ***name == *source) {
+        Some(name) => Arc::clone(name),
+        None => {
+            let name: Arc<str> = Arc::from(source);
+            recent.sources.push(Arc::clone(&name));
+            name
+        }
+    };
+    let entry = recent.entries.entry(hash(&template)).or_insert_with(|| Entry {
+        template,
+        hits: VecDeque::new(),
+    });
+    if entry.hits.len() >= MAX_HITS {
+        entry.hits.pop_front();
+    }
+    entry.hits.push_back((now, source));
+
+    if now.duration_since(recent.pruned_at) >= PRUNE_INTERVAL {
+        recent.pruned_at = now;
+        recent.entries.retain(|_, entry| {
+            while entry.hits.front().is_some_and(|(at, _)| at.elapsed() > KEEP) {
+                entry.hits.pop_front();
+            }
+            !entry.hits.is_empty()
+        });
+    }
+}
+
+// 按时间排序后, 来源不同且间隔最短的两次出现一定相邻
+fn is_shared(hits: &VecDeque<(Instant, Arc<str>)>) -> bool {
+    hits.iter()
+        .zip(hits.iter().skip(1))
+        .any(|((a, source_a), (b, source_b))| source_a != source_b && b.duration_since(*a) <= SHARED_WITHIN)
+}
+
+// 出现在最多窗口里的排在前面, 其次是最近出现的
+pub fn shared() -> Vec<Shared> {
+    let recent = RECENT.lock().unwrap();
+    let mut shared: Vec<Shared> = recent
+        .entries
+        .values()
+        .filter(|entry| entry.hits.back().is_some_and(|(at, _)| at.elapsed() <= KEEP))
+        .filter(|entry| is_shared(&entry.hits))
+        .map(|entry| {
+            let mut counts: Vec<(String, usize)> = Vec::new();
+            for (at, source) in &entry.hits {
+                if at.elapsed() > KEEP {
+                    continue;
+                }
+                match counts.iter_mut().find(|(name, _)| **name == **source) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((source.to_string(), 1)),
+                }
+            }
+            Shared {
+                template: entry.template.clone(),
+                sources: counts,
+                last: entry.hits.back().unwrap().0,
+            }
+        })
+        .collect();
+    shared.sort_by(|a, b| b.sources.len().cmp(&a.sources.len()).then(b.last.cmp(&a.last)));
+    shared
+}
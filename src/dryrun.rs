@@ -0,0 +1,97 @@
+use crate::auth;
+use crate::command;
+use crate::config::{self, SourceType};
+use crate::i18n::{tr, Msg};
+use crate::ssh::{self, shell_quote};
+
+fn or_unset(value: Option<&str>) -> String {
+    value.unwrap_or("-").to_string()
+}
+
+fn ssh_target(log: &config::LogConfig) -> String {
+    let user = log.username.as_deref().map(|u| format!("{}@", u)).unwrap_or_default();
+    format!("{}{}:{}", user, log.host, log.port)
+}
+
+fn argv(command: &[String]) -> String {
+    command.iter().map(|arg| shell_quote(arg)).collect::<Vec<_>>().join(" ")
+}
+
+// 每个窗口的连接目标, 以及需要时的命令和认证方式
+fn describe(log: &config::LogConfig) -> Vec<(Msg, String)> {
+    let remote = matches!(log.source, SourceType::Ssh | SourceType::Journal)
+        || (log.source == SourceType::Podman && !log.host.is_empty());
+    if remote {
+        let mut lines = vec![
+            (Msg::DryRunTarget, ssh_target(log)),
+            (Msg::DryRunCommand, ssh::initial_command(log)),
+            (Msg::DryRunAuth, auth::describe(log)),
+        ];
+        if let Some(hook) = &log.pre_connect {
+            lines.push((Msg::DryRunHook, hook.clone()));
+        }
+        if let Some(forward) = &log.forward {
+            lines.push((Msg::DryRunForward, format!("{} -> {}", forward.local, forward.remote)));
+        }
+        return lines;
+    }
+    let target = match log.source {
+        SourceType::Local | SourceType::Replay => log.log_path.paths().join(", "),
+        SourceType::Command => return vec![(Msg::DryRunCommand, argv(log.command.as_deref().unwrap_or_default()))],
+        SourceType::Podman => or_unset(log.container.as_deref()),
+        SourceType::Cloudwatch => format!(
+            "{} {} ({})",
+            or_unset(log.log_group.as_deref()),
+            log.log_stream.as_deref().unwrap_or_default(),
+            or_unset(log.region.as_deref())
+        ),
+        SourceType::Gcp | SourceType::Azure => or_unset(log.query.as_deref().or(log.filter.as_deref())),
+        SourceType::Winevent => format!(
+            "{} {}",
+            if log.host.is_empty() { "localhost" } else { &log.host },
+            log.channel.as_deref().unwrap_or("System")
+        ),
+        SourceType::Sse | SourceType::Loki | SourceType::Elasticsearch => or_unset(log.url.as_deref()),
+        SourceType::Redis => format!("{}:{} {}", log.host, log.port, log.channel.as_deref().unwrap_or_default()),
+        SourceType::Syslog => log.listen.clone().unwrap_or_else(|| "0.0.0.0:5514".to_string()),
+        SourceType::Gelf => log.listen.clone().unwrap_or_else(|| "0.0.0.0:12201".to_string()),
+        SourceType::Forward => log.listen.clone().unwrap_or_else(|| "0.0.0.0:24224".to_string()),
+        SourceType::Serial => or_unset(log.device.as_deref()),
+        SourceType::Ssh | SourceType::Journal | SourceType::Compose => String::new(),
+    };
+    vec![(Msg::DryRunTarget, target)]
+}
+
+// compose 在本机查询服务列表, 每个服务是一个 command 窗口
+fn expand(log: config::LogConfig) -> Vec<Result<config::LogConfig, (String, String)>> {
+    if log.source != SourceType::Compose {
+        return vec![Ok(log)];
+    }
+    match command::compose_services(&log) {
+        Ok(services) => services.into_iter().map(Ok).collect(),
+        Err(e) => vec![Err((log.name, e))],
+    }
+}
+
+// --dry-run: 打印模板、环境变量替换之后最终会打开的窗口, 不建立任何连接
+pub fn run(logs: Vec<config::LogConfig>) {
+    let windows: Vec<_> = logs.into_iter().flat_map(expand).collect();
+    let label_width = [Msg::DryRunTarget, Msg::DryRunCommand, Msg::DryRunAuth, Msg::DryRunHook, Msg::DryRunForward]
+        .iter()
+        .map(|msg| tr(*msg).chars().count())
+        .max()
+        .unwrap_or(0);
+    for window in &windows {
+        match window {
+            Ok(log) => {
+                let source = format!("{:?}", log.source).to_lowercase();
+                println!("\x1b[1m{}\x1b[0m  {}", log.name, source);
+                for (label, value) in describe(log) {
+                    println!("    {:<label_width$}  {}", tr(label), value);
+                }
+            }
+            Err((name, e)) => println!("\x1b[1m{}\x1b[0m  compose  \x1b[31m{}\x1b[0m", name, e),
+        }
+    }
+    println!("{} {}", windows.len(), tr(Msg::DryRunWindows));
+}
@@ -0,0 +1,150 @@
+use std::process::Command;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::command::{idle, wait_output};
+use crate::config;
+use crate::i18n::{tr, Msg};
+use crate::ssh::{ConnectionStatus, LogSink, SourceRequest};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+const PAGE_SIZE: usize = 500;
+
+// 以 { 开头的 query 视为 Query DSL, 否则用 query_string (Lucene/类 KQL 语法)
+fn build_query(log: &config::LogConfig) -> Result<Value, String> {
+    let since = log.since.as_deref().unwrap_or("10m");
+    let range = json!({ "range": { "@timestamp": { "gte": format!("now-{}", since) } } });
+    let query = match log.query.as_deref().map(str::trim) {
+        Some(q) if q.starts_with('{') => {
+            serde_json::from_str(q).map_err(|e| format!("Invalid query DSL: {}", e))?
+        }
+        Some(q) if !q.is_empty() => json!({ "query_string": { "query": q } }),
+        _ => json!({ "match_all": {} }),
+    };
+    Ok(json!({ "bool": { "filter": [range, query] } }))
+}
+
+// search_after 严格大于上一页最后一条的排序值, 同一时间戳的文档需要索引里带毫秒以下精度才不会漏
+fn search_body(query: &Value, after: Option<&Value>) -> String {
+    let mut body = json!({
+        "size": PAGE_SIZE,
+        "query": query,
+        "sort": [{ "@timestamp": { "order": "asc", "format": "strict_date_optional_time_nanos" } }],
+    });
+    if let Some(after) = after {
+        body["search_after"] = after.clone();
+    }
+    body.to_string()
+}
+
+// 凭证通过 curl -K - 从 stdin 传入, 不出现在进程参数里
+fn curl_config(log: &config::LogConfig) -> String {
+    let quote = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut config = String::new();
+    if let (Some(username), Some(password)) = (&log.username, &log.password) {
+        config.push_str(&format!("user = \"{}:{}\"\n", quote(username), quote(password)));
+    } else if let Some(token) = &log.token {
+        config.push_str(&format!("header = \"Authorization: ApiKey {}\"\n", quote(token)));
+    }
+    for (name, value) in log.headers.iter().flatten() {
+        config.push_str(&format!("header = \"{}: {}\"\n", quote(name), quote(value)));
+    }
+    config
+}
+
+fn format_hit(hit: &Value) -> String {
+    let source = &hit["_source"];
+    let timestamp = source["@timestamp"].as_str().unwrap_or_default();
+    let level = source["log"]["level"]
+        .as_str()
+        .or_else(|| source["level"].as_str())
+        .map(|level| format!(" {}", level.to_uppercase()))
+        .unwrap_or_default();
+    let message = match source["message"].as_str() {
+        Some(message) => message.to_string(),
+        None => source.to_string(),
+    };
+    format!("{}{} {}\n", timestamp, level, message.trim_end().replace('\n', " "))
+}
+
+// 一页页拉取直到没有更多新文档
+fn poll(
+    log: &config::LogConfig,
+    sink: &LogSink,
+    requests: &Receiver<SourceRequest>,
+    query: &Value,
+    after: &mut Option<Value>,
+) -> Result<(), String> {
+    let url = log.url.as_deref().ok_or_else(|| tr(Msg::NoUrl).to_string())?;
+    let index = log.index.as_deref().unwrap_or("*");
+    let endpoint = format!("{}/{}/_search", url.trim_end_matches('/'), index);
+    loop {
+        let mut command = Command::new("curl");
+        command.args([
+            "-sS",
+            "-K",
+            "-",
+            "-X",
+            "POST",
+            "-H",
+            "Content-Type: application/json",
+            "--data-binary",
+            &search_body(query, after.as_ref()),
+            &endpoint,
+        ]);
+        let output = wait_output(command, Some(curl_config(log)), sink, requests)
+            .map_err(|e| format!("{}: {}", tr(Msg::CommandErr), e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "{}: {}",
+                tr(Msg::CommandExited),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        sink.stats.lock().unwrap().bytes_received += output.stdout.len() as u64;
+
+        let response: Value = serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+        if let Some(error) = response.get("error") {
+            let reason = error["reason"].as_str().map(str::to_string);
+            return Err(reason.unwrap_or_else(|| error.to_string()));
+        }
+        let hits = response["hits"]["hits"].as_array().cloned().unwrap_or_default();
+        for hit in &hits {
+            sink.push_line(format_hit(hit));
+            *after = Some(hit["sort"].clone());
+        }
+        if hits.len() < PAGE_SIZE {
+            return Ok(());
+        }
+    }
+}
+
+pub fn tail(log: &config::LogConfig, sink: LogSink, requests: Receiver<SourceRequest>) {
+    let query = match build_query(log) {
+        Ok(query) => query,
+        Err(e) => {
+            sink.set_status(ConnectionStatus::Error(e));
+            loop {
+                idle(POLL_INTERVAL, &sink, &requests);
+            }
+        }
+    };
+    let mut after = None;
+    let mut interval = POLL_INTERVAL;
+    loop {
+        match poll(log, &sink, &requests, &query, &mut after) {
+            Ok(()) => {
+                sink.set_status(ConnectionStatus::Connected);
+                interval = POLL_INTERVAL;
+            }
+            Err(e) => {
+                sink.set_status(ConnectionStatus::Error(e));
+                interval = (interval * 2).min(MAX_BACKOFF);
+            }
+        }
+        idle(interval, &sink, &requests);
+    }
+}
@@ -0,0 +1,85 @@
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+const SCAN_LINES: usize = 5000;
+const MAX_VALUES: usize = 50;
+const MAX_VALUE_CHARS: usize = 100;
+
+pub struct FieldSummary {
+    pub name: String,
+    pub values: Vec<(String, usize)>,
+}
+
+// 行本身是 JSON 对象, 或者在时间戳等前缀之后跟着一个 JSON 对象
+fn json_object(line: &str) -> Option<Value> {
+    let trimmed = line.trim_end_matches(['\r', '\n']);
+    let start = trimmed.find('{')?;
+    let value: Value = serde_json::from_str(&trimmed[start..]).ok()?;
+    value.is_object().then_some(value)
+}
+
+// {"a":{"b":1},"msg":"x"} 展开为 [("a.b", "1"), ("msg", "x")], 非 JSON 行返回 None
+pub fn json_fields(line: &str) -> Option<Vec<(String, String)>> {
+    let value = json_object(line)?;
+    let mut fields = Vec::new();
+    flatten_into("", &value, &mut fields);
+    Some(fields)
+}
+
+fn flatten_into(prefix: &str, value: &Value, fields: &mut Vec<(String, String)>) {
+    let key = |name: &str| {
+        if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", prefix, name)
+        }
+    };
+    match value {
+        Value::Object(map) => map
+            .iter()
+            .for_each(|(name, v)| flatten_into(&key(name), v, fields)),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .for_each(|(i, v)| flatten_into(&key(&i.to_string()), v, fields)),
+        Value::String(s) => fields.push((prefix.to_string(), s.clone())),
+        other => fields.push((prefix.to_string(), other.to_string())),
+    }
+}
+
+// 统计缓冲区末尾的 JSON 行中出现过的字段和取值, 取值按出现次数排序
+pub fn observed_fields(lines: &[String]) -> Vec<FieldSummary> {
+    let mut counts: BTreeMap<String, HashMap<String, usize>> = BTreeMap::new();
+    let start = lines.len().saturating_sub(SCAN_LINES);
+    for fields in lines[start..].iter().filter_map(|line| json_fields(line)) {
+        for (name, value) in fields {
+            let values = counts.entry(name).or_default();
+            if value.chars().count() <= MAX_VALUE_CHARS {
+                *values.entry(value).or_insert(0) += 1;
+            }
+        }
+    }
+
+    counts
+        .into_iter()
+        .map(|(name, values)| {
+            let mut values: Vec<(String, usize)> = values.into_iter().collect();
+            values.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            values.truncate(MAX_VALUES);
+            FieldSummary { name, values }
+        })
+        .collect()
+}
+
+// 所有条件同时满足才显示, 非 JSON 行在有过滤条件时隐藏
+pub fn matches(line: &str, filter: &[(String, String)]) -> bool {
+    if filter.is_empty() {
+        return true;
+    }
+    let Some(fields) = json_fields(line) else {
+        return false;
+    };
+    filter
+        .iter()
+        .all(|(name, value)| fields.iter().any(|(n, v)| n == name && v == value))
+}
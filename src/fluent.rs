@@ -0,0 +1,260 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::codec::{hex, sha512};
+use crate::command::handle_request;
+use crate::config;
+use crate::i18n::{tr, Msg};
+use crate::inflate;
+use crate::msgpack::{self, Pack};
+use crate::ssh::{ConnectionStatus, LogSink, SourceRequest};
+use crate::syslog::now_millis;
+use crate::timestamp;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const MAX_CHUNK: usize = 64 * 1024 * 1024;
+
+struct Auth {
+    shared_key: String,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+fn random_bytes() -> Vec<u8> {
+    let mut bytes = vec![0u8; 16];
+    if let Ok(mut urandom) = File::open("/dev/urandom") {
+        let _ = urandom.read_exact(&mut bytes);
+    }
+    bytes
+}
+
+fn hostname() -> String {
+    std::fs::read_to_string("/etc/hostname")
+        .map(|name| name.trim().to_string())
+        .unwrap_or_else(|_| "rogger".to_string())
+}
+
+fn digest(parts: &[&[u8]]) -> String {
+    hex(&sha512(&parts.concat()))
+}
+
+fn send(stream: &mut TcpStream, pack: &Pack) -> io::Result<()> {
+    let mut out = Vec::new();
+    msgpack::encode(pack, &mut out);
+    stream.write_all(&out)
+}
+
+fn text(value: &str) -> Pack {
+    Pack::Str(value.to_string())
+}
+
+// HELO -> PING -> PONG, 共享密钥在双方都用 sha512(salt + 主机名 + nonce + key) 证明
+fn handshake(stream: &mut TcpStream, reader: &mut impl Read, auth: &Auth) -> Result<(), String> {
+    let nonce = random_bytes();
+    let user_salt = random_bytes();
+    // auth 为空表示不要求用户名密码
+    let user_auth = auth.username.is_some() && auth.password.is_some();
+    let helo = Pack::Array(vec![
+        text("HELO"),
+        Pack::Map(vec![
+            (text("nonce"), Pack::Bin(nonce.clone())),
+            (text("auth"), Pack::Bin(if user_auth { user_salt.clone() } else { Vec::new() })),
+            (text("keepalive"), Pack::Bool(true)),
+        ]),
+    ]);
+    send(stream, &helo).map_err(|e| e.to_string())?;
+
+    let ping = msgpack::decode(reader).map_err(|e| e.to_string())?;
+    let fields = ping.as_array().unwrap_or_default();
+    let field = |i: usize| fields.get(i).and_then(Pack::as_bytes).unwrap_or_default();
+    if fields.first().and_then(Pack::as_str) != Some("PING") {
+        return Err("expected PING".to_string());
+    }
+    let (client_host, salt) = (field(1), field(2));
+    let key = auth.shared_key.as_bytes();
+    let mut failure = None;
+    if field(3) != digest(&[salt, client_host, &nonce, key]).as_bytes() {
+        failure = Some("shared key mismatch");
+    } else if let (Some(username), Some(password)) = (&auth.username, &auth.password) {
+        let expected = digest(&[&user_salt, username.as_bytes(), password.as_bytes()]);
+        if field(4) != username.as_bytes() || field(5) != expected.as_bytes() {
+            failure = Some("username/password mismatch");
+        }
+    }
+
+    let server_host = hostname();
+    let pong = Pack::Array(vec![
+        text("PONG"),
+        Pack::Bool(failure.is_none()),
+        text(failure.unwrap_or_default()),
+        text(&server_host),
+        text(&digest(&[salt, server_host.as_bytes(), &nonce, key])),
+    ]);
+    send(stream, &pong).map_err(|e| e.to_string())?;
+    match failure {
+        Some(reason) => Err(reason.to_string()),
+        None => Ok(()),
+    }
+}
+
+// 整数秒或 EventTime (ext 类型 0: 大端的秒和纳秒)
+fn event_millis(time: &Pack) -> i64 {
+    match time {
+        Pack::Int(seconds) => seconds * 1000,
+        Pack::Ext(0, data) if data.len() == 8 => {
+            let seconds = u32::from_be_bytes(data[..4].try_into().unwrap()) as i64;
+            let nanos = u32::from_be_bytes(data[4..].try_into().unwrap()) as i64;
+            seconds * 1000 + nanos / 1_000_000
+        }
+        Pack::Float(seconds) => (seconds * 1000.0) as i64,
+        _ => now_millis(),
+    }
+}
+
+// fluent-bit 的容器日志通常把原始行放在 log 字段, 其它记录整体显示为 JSON
+fn format_event(tag: &str, time: &Pack, record: &Pack) -> String {
+    let message = match record.get("log").or_else(|| record.get("message")) {
+        Some(Pack::Str(message)) => message.trim_end_matches(['\r', '\n']).to_string(),
+        _ => record.to_json().to_string(),
+    };
+    format!("{} [{}] {}\n", timestamp::format_date_time(event_millis(time)), tag, message)
+}
+
+fn format_entries(tag: &str, entries: &[Pack]) -> Vec<String> {
+    entries
+        .iter()
+        .filter_map(|entry| match entry.as_array()? {
+            [time, record, ..] => Some(format_event(tag, time, record)),
+            _ => None,
+        })
+        .collect()
+}
+
+// Message: [tag, time, record, option]
+// Forward: [tag, [[time, record], ...], option]
+// PackedForward / CompressedPackedForward: [tag, 连续的 msgpack 条目, option]
+fn events(message: &Pack) -> Result<Vec<String>, String> {
+    let items = message.as_array().ok_or("forward message is not an array")?;
+    let tag = items.first().and_then(Pack::as_str).ok_or("missing tag")?;
+    match items.get(1) {
+        Some(Pack::Array(entries)) => Ok(format_entries(tag, entries)),
+        Some(packed @ (Pack::Str(_) | Pack::Bin(_))) => {
+            let packed = packed.as_bytes().unwrap_or_default();
+            let compressed = items
+                .get(2)
+                .and_then(|option| option.get("compressed"))
+                .and_then(Pack::as_str)
+                == Some("gzip");
+            let packed = if compressed {
+                inflate::gunzip(packed, MAX_CHUNK)?
+            } else {
+                packed.to_vec()
+            };
+            let mut remaining = packed.as_slice();
+            let mut entries = Vec::new();
+            while !remaining.is_empty() {
+                entries.push(msgpack::decode(&mut remaining).map_err(|e| e.to_string())?);
+            }
+            Ok(format_entries(tag, &entries))
+        }
+        Some(time) => Ok(vec![format_event(tag, time, items.get(2).unwrap_or(&Pack::Nil))]),
+        None => Err("missing entries".to_string()),
+    }
+}
+
+// 带 chunk 选项时回复 ack, 发送端据此确认送达
+fn option(message: &Pack) -> Option<&Pack> {
+    let items = message.as_array()?;
+    match items {
+        [_, Pack::Array(_), option, ..] | [_, Pack::Str(_) | Pack::Bin(_), option, ..] => Some(option),
+        [_, _, _, option, ..] => Some(option),
+        _ => None,
+    }
+}
+
+fn serve(mut stream: TcpStream, auth: Option<&Auth>, lines: &Sender<String>) -> Result<(), String> {
+    let peer = stream.peer_addr().map(|p| p.to_string()).unwrap_or_default();
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    if let Some(auth) = auth {
+        handshake(&mut stream, &mut reader, auth).map_err(|e| format!("{}: {}", peer, e))?;
+    }
+    loop {
+        let message = match msgpack::decode(&mut reader) {
+            Ok(message) => message,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(format!("{}: {}", peer, e)),
+        };
+        // 心跳等不是数组的报文直接忽略
+        if message.as_array().is_none() {
+            continue;
+        }
+        for line in events(&message).map_err(|e| format!("{}: {}", peer, e))? {
+            if lines.send(line).is_err() {
+                return Ok(());
+            }
+        }
+        if let Some(chunk) = option(&message).and_then(|o| o.get("chunk")).and_then(Pack::as_str) {
+            let ack = Pack::Map(vec![(text("ack"), text(chunk))]);
+            send(&mut stream, &ack).map_err(|e| e.to_string())?;
+        }
+    }
+}
+
+pub fn listen(log: &config::LogConfig, sink: LogSink, requests: Receiver<SourceRequest>) {
+    let address = log
+        .listen
+        .clone()
+        .unwrap_or_else(|| "0.0.0.0:24224".to_string());
+    let auth = log.shared_key.clone().map(|shared_key| Auth {
+        shared_key,
+        username: log.username.clone(),
+        password: log.password.clone(),
+    });
+    let (line_sender, lines) = mpsc::channel::<String>();
+    let (error_sender, errors) = mpsc::channel::<String>();
+    match TcpListener::bind(&address) {
+        Ok(listener) => {
+            let auth = Arc::new(auth);
+            thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let (lines, errors, auth) =
+                        (line_sender.clone(), error_sender.clone(), Arc::clone(&auth));
+                    thread::spawn(move || {
+                        if let Err(e) = serve(stream, auth.as_ref().as_ref(), &lines) {
+                            let _ = errors.send(e);
+                        }
+                    });
+                }
+            });
+        }
+        Err(e) => sink.set_status(ConnectionStatus::Error(format!(
+            "{} ({}): {}",
+            tr(Msg::ListenErr),
+            address,
+            e
+        ))),
+    }
+
+    loop {
+        match lines.recv_timeout(POLL_INTERVAL) {
+            Ok(line) => {
+                sink.stats.lock().unwrap().bytes_received += line.len() as u64;
+                sink.push_line(line);
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => thread::sleep(POLL_INTERVAL),
+        }
+        // 单个连接出错(例如认证失败)只提示, 不影响其它连接
+        while let Ok(e) = errors.try_recv() {
+            sink.set_status(ConnectionStatus::Error(format!("{}: {}", tr(Msg::FluentErr), e)));
+        }
+        while let Ok(request) = requests.try_recv() {
+            handle_request(request, &sink);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use ssh2::{Channel, Session};
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::config::ForwardConfig;
+
+const BUF_SIZE: usize = 16 * 1024;
+
+// 一条本地连接和对应的 direct-tcpip 通道
+struct Tunnel {
+    tcp: TcpStream,
+    channel: Channel,
+    to_remote: Vec<u8>,
+    to_local: Vec<u8>,
+    local_eof: bool,
+    closed: bool,
+}
+
+pub struct Forwarder {
+    listener: TcpListener,
+    host: String,
+    port: u16,
+    tunnels: Vec<Tunnel>,
+}
+
+impl Forwarder {
+    pub fn bind(config: &ForwardConfig) -> io::Result<Self> {
+        let (host, port) = config
+            .remote
+            .rsplit_once(':')
+            .and_then(|(host, port)| Some((host.to_string(), port.parse().ok()?)))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid forward remote: {}", config.remote),
+                )
+            })?;
+        let listener = TcpListener::bind(("127.0.0.1", config.local))?;
+        listener.set_nonblocking(true)?;
+        Ok(Forwarder {
+            listener,
+            host,
+            port,
+            tunnels: Vec::new(),
+        })
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.tunnels.is_empty()
+    }
+
+    // 在 tail 循环中调用: 接受新连接, 然后以非阻塞方式搬运已有连接的数据
+    pub fn poll(&mut self, sess: &Session) {
+        while let Ok((tcp, _)) = self.listener.accept() {
+            let Ok(channel) = sess.channel_direct_tcpip(&self.host, self.port, None) else {
+                continue;
+            };
+            if tcp.set_nonblocking(true).is_err() {
+                continue;
+            }
+            self.tunnels.push(Tunnel {
+                tcp,
+                channel,
+                to_remote: Vec::new(),
+                to_local: Vec::new(),
+                local_eof: false,
+                closed: false,
+            });
+        }
+        if self.tunnels.is_empty() {
+            return;
+        }
+
+        sess.set_blocking(false);
+        for tunnel in &mut self.tunnels {
+            while tunnel.pump() {}
+        }
+        sess.set_blocking(true);
+
+        self.tunnels.retain_mut(|tunnel| {
+            if tunnel.closed {
+                let _ = tunnel.channel.close();
+            }
+            !tunnel.closed
+        });
+    }
+}
+
+impl Tunnel {
+    // 返回本轮是否有数据流动, 没有时交回 tail 循环
+    fn pump(&mut self) -> bool {
+        let mut progress = false;
+        let mut buf = [0u8; BUF_SIZE];
+
+        if self.to_remote.is_empty() && !self.local_eof {
+            match self.tcp.read(&mut buf) {
+                Ok(0) => {
+                    self.local_eof = true;
+                    let _ = self.channel.send_eof();
+                }
+                Ok(n) => self.to_remote.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(_) => self.closed = true,
+            }
+        }
+        if !self.to_remote.is_empty() {
+            match self.channel.write(&self.to_remote) {
+                Ok(n) => {
+                    self.to_remote.drain(..n);
+                    progress |= n > 0;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(_) => self.closed = true,
+            }
+        }
+
+        if self.to_local.is_empty() {
+            match self.channel.read(&mut buf) {
+                Ok(0) if self.channel.eof() => self.closed = true,
+                Ok(n) => self.to_local.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(_) => self.closed = true,
+            }
+        }
+        if !self.to_local.is_empty() {
+            match self.tcp.write(&self.to_local) {
+                Ok(n) => {
+                    self.to_local.drain(..n);
+                    progress |= n > 0;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(_) => self.closed = true,
+            }
+        }
+
+        progress && !self.closed
+    }
+}
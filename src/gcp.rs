@@ -0,0 +1,144 @@
+use std::collections::HashSet;
+use std::process::Command;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::command::{idle, wait_output};
+use crate::config;
+use crate::i18n::{tr, Msg};
+use crate::ssh::{ConnectionStatus, LogSink, SourceRequest};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+const PAGE_SIZE: &str = "1000";
+
+// 最后读到的时间戳和该时间戳下已经显示的 insertId, 下一轮用 >= 查询并去重
+#[derive(Default)]
+struct Cursor {
+    timestamp: Option<String>,
+    seen: HashSet<String>,
+}
+
+// Cloud Logging 的 LogSeverity 映射成格式化规则能识别的级别关键字
+fn severity_name(severity: &str) -> &'static str {
+    match severity {
+        "DEBUG" => "DEBUG",
+        "NOTICE" => "NOTICE",
+        "WARNING" => "WARN",
+        "ERROR" => "ERROR",
+        "CRITICAL" | "ALERT" | "EMERGENCY" => "FATAL",
+        _ => "INFO",
+    }
+}
+
+fn read_args(log: &config::LogConfig, cursor: &Cursor) -> Vec<String> {
+    let mut filter = log.filter.clone().unwrap_or_default();
+    if let Some(timestamp) = &cursor.timestamp {
+        if !filter.is_empty() {
+            filter = format!("({}) AND ", filter);
+        }
+        filter.push_str(&format!("timestamp>=\"{}\"", timestamp));
+    }
+    let mut argv: Vec<String> = ["gcloud", "logging", "read"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+    argv.push(filter);
+    argv.extend(["--format=json", "--order=asc"].map(str::to_string));
+    argv.push(format!("--limit={}", PAGE_SIZE));
+    if cursor.timestamp.is_none() {
+        argv.push(format!("--freshness={}", log.since.as_deref().unwrap_or("10m")));
+    }
+    if let Some(project) = &log.project {
+        argv.push(format!("--project={}", project));
+    }
+    argv
+}
+
+fn payload(entry: &Value) -> String {
+    if let Some(text) = entry["textPayload"].as_str() {
+        return text.to_string();
+    }
+    let json = &entry["jsonPayload"];
+    if let Some(message) = json["message"].as_str() {
+        return message.to_string();
+    }
+    if !json.is_null() {
+        return json.to_string();
+    }
+    match &entry["protoPayload"] {
+        Value::Null => String::new(),
+        proto => proto.to_string(),
+    }
+}
+
+// "时间 级别 资源类型: 内容", 多行内容合并成一行
+fn format_entry(entry: &Value) -> String {
+    format!(
+        "{} {} {}: {}\n",
+        entry["timestamp"].as_str().unwrap_or_default(),
+        severity_name(entry["severity"].as_str().unwrap_or_default()),
+        entry["resource"]["type"].as_str().unwrap_or("global"),
+        payload(entry).trim_end().replace('\n', " ")
+    )
+}
+
+fn poll(
+    log: &config::LogConfig,
+    sink: &LogSink,
+    requests: &Receiver<SourceRequest>,
+    cursor: &mut Cursor,
+) -> Result<(), String> {
+    let argv = read_args(log, cursor);
+    let mut command = Command::new(&argv[0]);
+    command.args(&argv[1..]);
+    if let Some(path) = &log.credentials {
+        command.env("CLOUDSDK_AUTH_CREDENTIAL_FILE_OVERRIDE", path);
+    }
+    let output = wait_output(command, None, sink, requests)
+        .map_err(|e| format!("{}: {}", tr(Msg::CommandErr), e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "{}: {}",
+            tr(Msg::CommandExited),
+            stderr.trim().lines().last().unwrap_or_default()
+        ));
+    }
+
+    sink.stats.lock().unwrap().bytes_received += output.stdout.len() as u64;
+    let entries: Vec<Value> = serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+    for entry in &entries {
+        let timestamp = entry["timestamp"].as_str().unwrap_or_default();
+        let id = entry["insertId"].as_str().unwrap_or_default();
+        if cursor.timestamp.as_deref() != Some(timestamp) {
+            cursor.timestamp = Some(timestamp.to_string());
+            cursor.seen.clear();
+        }
+        if cursor.seen.insert(id.to_string()) {
+            sink.push_line(format_entry(entry));
+        }
+    }
+    Ok(())
+}
+
+// 没有常驻的 tail 命令可用, 按固定间隔轮询 `gcloud logging read`, 出错时退避
+pub fn tail(log: &config::LogConfig, sink: LogSink, requests: Receiver<SourceRequest>) {
+    let mut cursor = Cursor::default();
+    let mut interval = POLL_INTERVAL;
+    loop {
+        match poll(log, &sink, &requests, &mut cursor) {
+            Ok(()) => {
+                sink.set_status(ConnectionStatus::Connected);
+                interval = POLL_INTERVAL;
+            }
+            Err(e) => {
+                sink.set_status(ConnectionStatus::Error(e));
+                interval = (interval * 2).min(MAX_BACKOFF);
+            }
+        }
+        idle(interval, &sink, &requests);
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::command::handle_request;
+use crate::config;
+use crate::i18n::{tr, Msg};
+use crate::inflate;
+use crate::ssh::{ConnectionStatus, LogSink, SourceRequest};
+use crate::syslog::{now_millis, severity_name};
+use crate::timestamp;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const MAX_DATAGRAM: usize = 65_536;
+const MAX_MESSAGE: usize = 8 * 1024 * 1024;
+// 规范要求 5 秒内收不齐的分块消息丢弃
+const CHUNK_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_CHUNKS: usize = 128;
+
+struct Datagram {
+    peer: SocketAddr,
+    data: Vec<u8>,
+}
+
+struct Partial {
+    parts: Vec<Option<Vec<u8>>>,
+    started: Instant,
+}
+
+// 分块报文: 0x1e 0x0f, 8 字节消息 id, 序号, 总块数, 内容
+#[derive(Default)]
+struct Reassembler {
+    pending: HashMap<[u8; 8], Partial>,
+}
+
+impl Reassembler {
+    fn feed(&mut self, data: Vec<u8>) -> Option<Vec<u8>> {
+        self.pending.retain(|_, partial| partial.started.elapsed() < CHUNK_TIMEOUT);
+        if !data.starts_with(&[0x1e, 0x0f]) {
+            return Some(data);
+        }
+        if data.len() < 12 {
+            return None;
+        }
+        let id: [u8; 8] = data[2..10].try_into().unwrap();
+        let (sequence, count) = (data[10] as usize, data[11] as usize);
+        if count == 0 || count > MAX_CHUNKS || sequence >= count {
+            return None;
+        }
+        let partial = self.pending.entry(id).or_insert_with(|| Partial {
+            parts: vec![None; count],
+            started: Instant::now(),
+        });
+        if partial.parts.len() != count {
+            return None;
+        }
+        partial.parts[sequence] = Some(data[12..].to_vec());
+        if partial.parts.iter().any(Option::is_none) {
+            return None;
+        }
+        let partial = self.pending.remove(&id)?;
+        Some(partial.parts.into_iter().flatten().flatten().collect())
+    }
+}
+
+fn decompress(data: Vec<u8>) -> Result<Vec<u8>, String> {
+    if inflate::is_gzip(&data) {
+        inflate::gunzip(&data, MAX_MESSAGE)
+    } else if inflate::is_zlib(&data) {
+        inflate::unzlib(&data, MAX_MESSAGE)
+    } else {
+        Ok(data)
+    }
+}
+
+fn field_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+// "时间 主机 级别 short_message 字段=值 ...", fields 里的字段名可以省略 GELF 附加字段的 _ 前缀
+fn format_message(message: &Value, fields: &[String], peer: &SocketAddr) -> String {
+    let millis = message["timestamp"]
+        .as_f64()
+        .map(|seconds| (seconds * 1000.0) as i64)
+        .unwrap_or_else(now_millis);
+    let host = message["host"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| peer.ip().to_string());
+    let level = severity_name(message["level"].as_u64().unwrap_or(6).min(7) as u8);
+    let text = message["short_message"]
+        .as_str()
+        .or_else(|| message["full_message"].as_str())
+        .unwrap_or_default()
+        .trim_end_matches(['\r', '\n']);
+
+    let mut line = format!("{} {} {} {}", timestamp::format_date_time(millis), host, level, text);
+    for name in fields {
+        let value = match &message[name.as_str()] {
+            Value::Null => &message[format!("_{}", name).as_str()],
+            value => value,
+        };
+        if !value.is_null() {
+            line.push_str(&format!(" {}={}", name.trim_start_matches('_'), field_text(value)));
+        }
+    }
+    line.push('\n');
+    line
+}
+
+fn receive(socket: UdpSocket, datagrams: Sender<Datagram>) {
+    let mut buf = vec![0u8; MAX_DATAGRAM];
+    while let Ok((n, peer)) = socket.recv_from(&mut buf) {
+        let datagram = Datagram {
+            peer,
+            data: buf[..n].to_vec(),
+        };
+        if datagrams.send(datagram).is_err() {
+            break;
+        }
+    }
+}
+
+pub fn listen(log: &config::LogConfig, sink: LogSink, requests: Receiver<SourceRequest>) {
+    let address = log
+        .listen
+        .clone()
+        .unwrap_or_else(|| "0.0.0.0:12201".to_string());
+    let fields = log.fields.clone().unwrap_or_default();
+    let (datagram_sender, datagrams) = mpsc::channel();
+    match UdpSocket::bind(&address) {
+        Ok(socket) => {
+            thread::spawn(move || receive(socket, datagram_sender));
+        }
+        Err(e) => sink.set_status(ConnectionStatus::Error(format!(
+            "{} ({}): {}",
+            tr(Msg::ListenErr),
+            address,
+            e
+        ))),
+    }
+
+    let mut reassembler = Reassembler::default();
+    loop {
+        match datagrams.recv_timeout(POLL_INTERVAL) {
+            Ok(Datagram { peer, data }) => {
+                sink.stats.lock().unwrap().bytes_received += data.len() as u64;
+                if let Some(payload) = reassembler.feed(data) {
+                    let message = decompress(payload).and_then(|payload| {
+                        serde_json::from_slice::<Value>(&payload).map_err(|e| e.to_string())
+                    });
+                    sink.push_line(match message {
+                        Ok(message) => format_message(&message, &fields, &peer),
+                        Err(e) => format!("{} ({}): {}\n", tr(Msg::GelfErr), peer, e),
+                    });
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => thread::sleep(POLL_INTERVAL),
+        }
+        while let Ok(request) = requests.try_recv() {
+            handle_request(request, &sink);
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde_json::{Map, Number, Value};
+
+// Logstash grok-patterns 里常用的定义; 原版的环视和固化分组 Rust regex 不支持, 改写成了等价或更宽松的写法
+const BUILTIN: &str = r#"
+USERNAME [a-zA-Z0-9._-]+
+USER %{USERNAME}
+EMAILLOCALPART [a-zA-Z0-9!#$%&'*+\-/=?^_`{|}~]{1,64}(?:\.[a-zA-Z0-9!#$%&'*+\-/=?^_`{|}~]{1,62}){0,63}
+EMAILADDRESS %{EMAILLOCALPART}@%{HOSTNAME}
+INT [+-]?[0-9]+
+BASE10NUM [+-]?(?:[0-9]+(?:\.[0-9]+)?|\.[0-9]+)
+NUMBER %{BASE10NUM}
+BASE16NUM [+-]?(?:0x)?[0-9A-Fa-f]+
+BASE16FLOAT [+-]?(?:0x)?(?:[0-9A-Fa-f]+(?:\.[0-9A-Fa-f]*)?|\.[0-9A-Fa-f]+)
+POSINT [1-9][0-9]*
+NONNEGINT [0-9]+
+WORD \b\w+\b
+NOTSPACE \S+
+SPACE \s*
+DATA .*?
+GREEDYDATA .*
+QUOTEDSTRING "(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*'|`(?:[^`\\]|\\.)*`
+QS %{QUOTEDSTRING}
+UUID [A-Fa-f0-9]{8}-(?:[A-Fa-f0-9]{4}-){3}[A-Fa-f0-9]{12}
+URN urn:[0-9A-Za-z][0-9A-Za-z-]{0,31}:(?:%[0-9a-fA-F]{2}|[0-9A-Za-z()+,.:=@;$_!*'/?#-])+
+MAC %{CISCOMAC}|%{WINDOWSMAC}|%{COMMONMAC}
+CISCOMAC (?:[A-Fa-f0-9]{4}\.){2}[A-Fa-f0-9]{4}
+WINDOWSMAC (?:[A-Fa-f0-9]{2}-){5}[A-Fa-f0-9]{2}
+COMMONMAC (?:[A-Fa-f0-9]{2}:){5}[A-Fa-f0-9]{2}
+IPV6 (?:[0-9A-Fa-f]{1,4}:){7}[0-9A-Fa-f]{1,4}|(?:[0-9A-Fa-f]{1,4}:){1,7}:|(?:[0-9A-Fa-f]{1,4}:){1,6}:[0-9A-Fa-f]{1,4}|(?:[0-9A-Fa-f]{1,4}:){1,5}(?::[0-9A-Fa-f]{1,4}){1,2}|(?:[0-9A-Fa-f]{1,4}:){1,4}(?::[0-9A-Fa-f]{1,4}){1,3}|(?:[0-9A-Fa-f]{1,4}:){1,3}(?::[0-9A-Fa-f]{1,4}){1,4}|(?:[0-9A-Fa-f]{1,4}:){1,2}(?::[0-9A-Fa-f]{1,4}){1,5}|[0-9A-Fa-f]{1,4}:(?::[0-9A-Fa-f]{1,4}){1,6}|:(?:(?::[0-9A-Fa-f]{1,4}){1,7}|:)(?:%.+)?
+IPV4 (?:25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)(?:\.(?:25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)){3}
+IP %{IPV4}|%{IPV6}
+HOSTNAME \b(?:[0-9A-Za-z][0-9A-Za-z-]{0,62})(?:\.(?:[0-9A-Za-z][0-9A-Za-z-]{0,62}))*\.?\b
+IPORHOST %{IP}|%{HOSTNAME}
+HOSTPORT %{IPORHOST}:%{POSINT}
+PATH %{UNIXPATH}|%{WINPATH}
+UNIXPATH (?:/[\w_%!$@:.,+~-]*)+
+TTY /dev/(?:pts|tty(?:[pq])?)(?:\w+)?/?(?:[0-9]+)
+WINPATH (?:[A-Za-z]+:|\\)(?:\\[^\\?*]*)+
+URIPROTO [A-Za-z](?:[A-Za-z0-9+\-.]+)+
+URIHOST %{IPORHOST}(?::%{POSINT})?
+URIPATH (?:/[A-Za-z0-9$.+!*'(){},~:;=@#%&_\-]*)+
+URIQUERY [A-Za-z0-9$.+!*'|(){},~@#%&/=:;_?\-\[\]<>]*
+URIPARAM \?%{URIQUERY}
+URIPATHPARAM %{URIPATH}(?:\?%{URIQUERY})?
+URI %{URIPROTO}://(?:%{USER}(?::[^@]*)?@)?(?:%{URIHOST})?(?:%{URIPATH}(?:\?%{URIQUERY})?)?
+MONTH \b(?:[Jj]an(?:uary|uar)?|[Ff]eb(?:ruary|ruar)?|[Mm](?:a|ä)?r(?:ch|z)?|[Aa]pr(?:il)?|[Mm]a(?:y|i)?|[Jj]un(?:e|i)?|[Jj]ul(?:y|i)?|[Aa]ug(?:ust)?|[Ss]ep(?:tember)?|[Oo](?:c|k)?t(?:ober)?|[Nn]ov(?:ember)?|[Dd]e(?:c|z)(?:ember)?)\b
+MONTHNUM 0?[1-9]|1[0-2]
+MONTHNUM2 0[1-9]|1[0-2]
+MONTHDAY (?:0[1-9])|(?:[12][0-9])|(?:3[01])|[1-9]
+DAY Mon(?:day)?|Tue(?:sday)?|Wed(?:nesday)?|Thu(?:rsday)?|Fri(?:day)?|Sat(?:urday)?|Sun(?:day)?
+YEAR (?:\d\d){1,2}
+HOUR 2[0123]|[01]?[0-9]
+MINUTE [0-5][0-9]
+SECOND (?:[0-5]?[0-9]|60)(?:[:.,][0-9]+)?
+TIME %{HOUR}:%{MINUTE}(?::%{SECOND})?
+DATE_US %{MONTHNUM}[/-]%{MONTHDAY}[/-]%{YEAR}
+DATE_EU %{MONTHDAY}[./-]%{MONTHNUM}[./-]%{YEAR}
+ISO8601_TIMEZONE Z|[+-]%{HOUR}(?::?%{MINUTE})
+ISO8601_SECOND %{SECOND}
+TIMESTAMP_ISO8601 %{YEAR}-%{MONTHNUM}-%{MONTHDAY}[T ]%{HOUR}:?%{MINUTE}(?::?%{SECOND})?%{ISO8601_TIMEZONE}?
+DATE %{DATE_US}|%{DATE_EU}
+DATESTAMP %{DATE}[- ]%{TIME}
+TZ [A-Z]{3}
+DATESTAMP_RFC822 %{DAY} %{MONTH} %{MONTHDAY} %{YEAR} %{TIME} %{TZ}
+DATESTAMP_RFC2822 %{DAY}, %{MONTHDAY} %{MONTH} %{YEAR} %{TIME} %{ISO8601_TIMEZONE}
+DATESTAMP_OTHER %{DAY} %{MONTH} %{MONTHDAY} %{TIME} %{TZ} %{YEAR}
+SYSLOGTIMESTAMP %{MONTH} +%{MONTHDAY} %{TIME}
+PROG [\x21-\x5a\x5c\x5e-\x7e]+
+SYSLOGPROG %{PROG:program}(?:\[%{POSINT:pid}\])?
+SYSLOGHOST %{IPORHOST}
+SYSLOGFACILITY <%{NONNEGINT:facility}.%{NONNEGINT:priority}>
+SYSLOGBASE %{SYSLOGTIMESTAMP:timestamp} (?:%{SYSLOGFACILITY} )?%{SYSLOGHOST:logsource} %{SYSLOGPROG}:
+HTTPDATE %{MONTHDAY}/%{MONTH}/%{YEAR}:%{TIME} %{INT}
+HTTPDUSER %{EMAILADDRESS}|%{USER}
+COMMONAPACHELOG %{IPORHOST:clientip} %{HTTPDUSER:ident} %{USER:auth} \[%{HTTPDATE:timestamp}\] "(?:%{WORD:verb} %{NOTSPACE:request}(?: HTTP/%{NUMBER:httpversion})?|%{DATA:rawrequest})" %{NUMBER:response} (?:%{NUMBER:bytes}|-)
+COMBINEDAPACHELOG %{COMMONAPACHELOG} %{QS:referrer} %{QS:agent}
+LOGLEVEL [Aa]lert|ALERT|[Tt]race|TRACE|[Dd]ebug|DEBUG|[Nn]otice|NOTICE|[Ii]nfo?(?:rmation)?|INFO?(?:RMATION)?|[Ww]arn?(?:ing)?|WARN?(?:ING)?|[Ee]rr?(?:or)?|ERR?(?:OR)?|[Cc]rit?(?:ical)?|CRIT?(?:ICAL)?|[Ff]atal|FATAL|[Ss]evere|SEVERE|EMERG(?:ENCY)?|[Ee]merg(?:ency)?
+"#;
+
+static REFERENCE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"%\{(\w+)(?::([\w.\[\]@-]+))?(?::(int|float))?\}").unwrap());
+
+// 防止互相引用的定义无限展开
+const MAX_DEPTH: usize = 32;
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Text,
+    Int,
+    Float,
+}
+
+pub struct Grok {
+    regex: Regex,
+    // 捕获组 g0, g1, ... 对应的字段名和类型; [client][ip] 写成 client.ip
+    fields: Vec<(String, Kind)>,
+}
+
+// Logstash 格式的 patterns 文件: 每行 "名字 正则", # 开头为注释
+fn parse_definitions(text: &str, definitions: &mut BTreeMap<String, String>) {
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+        if let Some((name, pattern)) = line.split_once(char::is_whitespace) {
+            definitions.insert(name.to_string(), pattern.trim_start().to_string());
+        }
+    }
+}
+
+fn field_name(raw: &str) -> String {
+    raw.trim_matches(['[', ']']).replace("][", ".")
+}
+
+fn expand(
+    pattern: &str,
+    definitions: &BTreeMap<String, String>,
+    fields: &mut Vec<(String, Kind)>,
+    depth: usize,
+) -> Result<String, String> {
+    if depth > MAX_DEPTH {
+        return Err(format!("grok pattern nests too deeply: {}", pattern));
+    }
+    let mut error = None;
+    let expanded = REFERENCE.replace_all(pattern, |caps: &Captures| {
+        let Some(definition) = definitions.get(&caps[1]) else {
+            error.get_or_insert_with(|| format!("unknown grok pattern: {}", &caps[1]));
+            return String::new();
+        };
+        // 带字段名的引用包成命名分组, 没有字段名的只是展开
+        let group = caps.get(2).map(|name| {
+            let kind = match caps.get(3).map(|k| k.as_str()) {
+                Some("int") => Kind::Int,
+                Some("float") => Kind::Float,
+                _ => Kind::Text,
+            };
+            fields.push((field_name(name.as_str()), kind));
+            fields.len() - 1
+        });
+        match expand(definition, definitions, fields, depth + 1) {
+            Ok(inner) => match group {
+                Some(i) => format!("(?P<g{}>{})", i, inner),
+                None => format!("(?:{})", inner),
+            },
+            Err(e) => {
+                error.get_or_insert(e);
+                String::new()
+            }
+        }
+    });
+    match error {
+        Some(e) => Err(e),
+        None => Ok(expanded.into_owned()),
+    }
+}
+
+impl Grok {
+    pub fn compile(pattern: &str, files: &[String], custom: &BTreeMap<String, String>) -> Result<Grok, String> {
+        let mut definitions = BTreeMap::new();
+        parse_definitions(BUILTIN, &mut definitions);
+        for path in files {
+            let path = crate::config::expand_tilde(path).map_err(|e| e.to_string())?;
+            let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            parse_definitions(&text, &mut definitions);
+        }
+        definitions.extend(custom.iter().map(|(name, pattern)| (name.clone(), pattern.clone())));
+
+        let mut fields = Vec::new();
+        let expanded = expand(pattern, &definitions, &mut fields, 0)?;
+        let regex = Regex::new(&expanded).map_err(|e| format!("Invalid grok pattern {}: {}", pattern, e))?;
+        Ok(Grok { regex, fields })
+    }
+
+    // 同名字段保留第一个匹配到的值, 没匹配上的行返回 None
+    pub fn parse(&self, line: &str) -> Option<Map<String, Value>> {
+        let caps = self.regex.captures(line)?;
+        let mut record = Map::new();
+        for (i, (name, kind)) in self.fields.iter().enumerate() {
+            let Some(value) = caps.name(&format!("g{}", i)).map(|m| m.as_str()) else {
+                continue;
+            };
+            if record.contains_key(name) {
+                continue;
+            }
+            let value = match kind {
+                Kind::Int => value.parse::<i64>().map(Value::from).unwrap_or_else(|_| value.into()),
+                Kind::Float => value
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(Number::from_f64)
+                    .map_or_else(|| value.into(), Value::Number),
+                Kind::Text => value.into(),
+            };
+            record.insert(name.clone(), value);
+        }
+        Some(record)
+    }
+}
@@ -0,0 +1,108 @@
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::archive::Archive;
+use crate::ssh::ConnectionStats;
+use crate::ui::LogWindow;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+const MIN_HISTORY: usize = 100;
+
+struct Buffer {
+    content: Arc<Mutex<Vec<String>>>,
+    stats: Arc<Mutex<ConnectionStats>>,
+    max_history: Arc<AtomicUsize>,
+    paused_at: Arc<Mutex<Option<usize>>>,
+    archive: Option<Arc<Archive>>,
+}
+
+impl Buffer {
+    fn bytes(&self) -> u64 {
+        self.stats.lock().unwrap().buffer_bytes
+    }
+
+    // 丢掉较早的一半, 并把上限降到剩余行数, 之后写入的行也按新上限淘汰
+    fn shrink(&self) -> u64 {
+        let mut content = self.content.lock().unwrap();
+        let keep = (content.len() / 2).max(MIN_HISTORY);
+        if content.len() <= keep {
+            return 0;
+        }
+        let removed = content.len() - keep;
+        let mut freed = 0;
+        for line in content.drain(..removed) {
+            freed += line.len() as u64;
+            if let Some(archive) = &self.archive {
+                archive.push(line);
+            }
+        }
+        content.shrink_to_fit();
+        self.max_history.store(keep, Ordering::Relaxed);
+
+        let mut paused_at = self.paused_at.lock().unwrap();
+        *paused_at = paused_at.map(|i| i.saturating_sub(removed));
+        {
+            let mut stats = self.stats.lock().unwrap();
+            stats.buffer_bytes = stats.buffer_bytes.saturating_sub(freed);
+        }
+        freed
+    }
+}
+
+fn rss_bytes() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let kb = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+// 定期检查自身 RSS, 超过上限时从最大的缓冲区开始缩减历史
+pub fn spawn(max_rss_mb: u64, windows: &[LogWindow]) {
+    let cap = max_rss_mb * 1024 * 1024;
+    let buffers: Vec<Buffer> = windows
+        .iter()
+        .map(|window| Buffer {
+            content: Arc::clone(&window.content),
+            stats: Arc::clone(&window.stats),
+            max_history: Arc::clone(&window.max_history),
+            paused_at: Arc::clone(&window.paused_at),
+            archive: window.archive.clone(),
+        })
+        .collect();
+
+    thread::spawn(move || {
+        // 分配器不一定把释放的内存还给系统, 缩减后要等缓冲区重新增长才再次缩减
+        let mut baseline = 0;
+        loop {
+            thread::sleep(CHECK_INTERVAL);
+            let Some(rss) = rss_bytes() else {
+                return;
+            };
+            let total: u64 = buffers.iter().map(Buffer::bytes).sum();
+            if rss <= cap || total <= baseline {
+                continue;
+            }
+
+            let mut to_free = (rss - cap) + cap / 10;
+            let mut by_size: Vec<&Buffer> = buffers.iter().collect();
+            by_size.sort_by_key(|b| std::cmp::Reverse(b.bytes()));
+            for buffer in by_size {
+                let freed = buffer.shrink();
+                to_free = to_free.saturating_sub(freed);
+                if to_free == 0 {
+                    break;
+                }
+            }
+            baseline = buffers.iter().map(Buffer::bytes).sum();
+        }
+    });
+}
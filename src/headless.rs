@@ -0,0 +1,46 @@
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+use once_cell::sync::OnceCell;
+
+use crate::ssh::ConnectionStatus;
+use crate::ui::LogWindow;
+
+static ENABLED: OnceCell<()> = OnceCell::new();
+
+// stdout 不是终端时(例如 rogger | tee)不启动 TUI, 每行加上 [窗口名] 前缀直接输出
+// 必须在启动数据源之前调用, 否则最先到达的行不会输出
+pub fn init() {
+    let _ = ENABLED.set(());
+}
+
+pub fn enabled() -> bool {
+    ENABLED.get().is_some()
+}
+
+pub fn emit(source: &str, line: &str) {
+    if !enabled() {
+        return;
+    }
+    let mut stdout = io::stdout().lock();
+    let line = line.trim_end_matches(['\n', '\r']);
+    // 管道另一端已经关闭, 没有必要再继续
+    if writeln!(stdout, "[{}] {}", source, line).and_then(|_| stdout.flush()).is_err() {
+        std::process::exit(0);
+    }
+}
+
+// 连接错误写到 stderr, 不混进输出的日志里
+pub fn status(source: &str, status: &ConnectionStatus) {
+    if let (true, ConnectionStatus::Error(e)) = (enabled(), status) {
+        eprintln!("[{}] {}", source, e);
+    }
+}
+
+// 数据源在各自的线程里输出; 保留窗口让请求通道保持打开, 直到进程被中断
+pub fn run(_windows: Vec<LogWindow>) -> io::Result<()> {
+    loop {
+        thread::sleep(Duration::from_secs(60));
+    }
+}
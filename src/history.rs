@@ -0,0 +1,38 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+const MAX_ENTRIES: usize = 100;
+
+fn history_path() -> io::Result<PathBuf> {
+    let home = std::env::var("HOME").map_err(|_| {
+        io::Error::new(io::ErrorKind::NotFound, "HOME environment variable not set")
+    })?;
+    Ok(PathBuf::from(home).join(".rogger/search_history"))
+}
+
+// 每行一条搜索词, 最新的在最后
+pub fn load() -> Vec<String> {
+    let Ok(content) = history_path().and_then(fs::read_to_string) else {
+        return Vec::new();
+    };
+    content.lines().filter(|l| !l.is_empty()).map(str::to_string).collect()
+}
+
+// 重复的搜索词移到最后, 只保留最近的 MAX_ENTRIES 条
+pub fn push(entries: &mut Vec<String>, query: &str) {
+    entries.retain(|entry| entry != query);
+    entries.push(query.to_string());
+    let excess = entries.len().saturating_sub(MAX_ENTRIES);
+    entries.drain(..excess);
+}
+
+pub fn save(entries: &[String]) -> io::Result<()> {
+    let path = history_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut content = entries.join("\n");
+    content.push('\n');
+    fs::write(path, content)
+}
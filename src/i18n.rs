@@ -0,0 +1,403 @@
+use once_cell::sync::OnceCell;
+
+static LOCALE: OnceCell<Locale> = OnceCell::new();
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Locale {
+    En,
+    Zh,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Msg {
+    Scroll,
+    ConnectErr,
+    HandshakeErr,
+    AuthErr,
+    HookErr,
+    AlgorithmErr,
+    ReadErr,
+    OffsetErr,
+    Disconnected,
+    NoAuthMethod,
+    Line,
+    NoRuleMatched,
+    Overlapped,
+    InspectorTitle,
+    MouseOff,
+    LinesPerSec,
+    Idle,
+    PickerTitle,
+    Loading,
+    PausedOnError,
+    GrepPrompt,
+    GrepHelp,
+    NotInBuffer,
+    DownloadErr,
+    DownloadMultiErr,
+    Saved,
+    TooSmall,
+    Lines,
+    CommandErr,
+    CommandExited,
+    NoCommand,
+    Unsupported,
+    ArchiveErr,
+    StdinClosed,
+    SaveLayoutPrompt,
+    UnknownLayout,
+    SaveErr,
+    RulePattern,
+    RuleSuggestTitle,
+    NoSuggestions,
+    ListenErr,
+    ForwardErr,
+    NoUrl,
+    HistoryShrunk,
+    NoLogGroup,
+    FieldFilterTitle,
+    NoJsonFields,
+    Sampled,
+    Recording,
+    Copied,
+    NoPosition,
+    Position,
+    NoRedisTarget,
+    NoDevice,
+    GelfErr,
+    NotOnPeer,
+    FluentErr,
+    DnsErr,
+    MissingFile,
+    NotInPath,
+    PreflightContinue,
+    PreflightOk,
+    NoContainer,
+    PodmanErr,
+    NoWorkspace,
+    AuthNotConfigured,
+    SearchPrompt,
+    NoMatch,
+    SearchWrapped,
+    Suspended,
+    SharedTitle,
+    NoSharedLines,
+    Ago,
+    SearchRegex,
+    SearchLiteral,
+    SearchMatchCase,
+    SearchIgnoreCase,
+    Silenced,
+    DryRunTarget,
+    DryRunCommand,
+    DryRunAuth,
+    DryRunHook,
+    DryRunForward,
+    DryRunWindows,
+    GlobalSearchPrompt,
+    GlobalSearchHelp,
+    FilterPrompt,
+    Filtered,
+    Hidden,
+    UnknownWindow,
+    ControlErr,
+    NoTimestamp,
+    LocalHost,
+    WindowsCount,
+    ExcludePrompt,
+    Excluded,
+    CheckPlaintext,
+    CheckPlaintextFix,
+    CheckWorldReadable,
+    CheckGroupReadable,
+    CheckHostKey,
+    CheckHostKeyFix,
+    CheckFix,
+    CheckOk,
+    CheckIssues,
+    SearchesTitle,
+    NoSavedSearches,
+    Marked,
+    Unmarked,
+    NoMarks,
+    Frozen,
+    NewLines,
+    Follow,
+    Manual,
+    NoWrap,
+    HostKeyErr,
+    HostKeyChanged,
+    HostKeyUnknown,
+}
+
+// 优先使用配置中的 locale, 其次是 LANG / LC_ALL 环境变量, 默认英文
+pub fn init(configured: Option<&str>) {
+    let locale = configured
+        .map(str::to_string)
+        .or_else(|| std::env::var("LC_ALL").ok())
+        .or_else(|| std::env::var("LANG").ok())
+        .map(|value| parse_locale(&value))
+        .unwrap_or(Locale::En);
+    let _ = LOCALE.set(locale);
+}
+
+fn parse_locale(value: &str) -> Locale {
+    if value.to_lowercase().starts_with("zh") {
+        Locale::Zh
+    } else {
+        Locale::En
+    }
+}
+
+pub fn tr(msg: Msg) -> &'static str {
+    match LOCALE.get().copied().unwrap_or(Locale::En) {
+        Locale::En => en(msg),
+        Locale::Zh => zh(msg),
+    }
+}
+
+fn en(msg: Msg) -> &'static str {
+    match msg {
+        Msg::Scroll => "Scroll",
+        Msg::ConnectErr => "Connect Err",
+        Msg::HandshakeErr => "Handshake Err",
+        Msg::AuthErr => "Auth Err",
+        Msg::HookErr => "Hook Err",
+        Msg::AlgorithmErr => "SSH Algorithm Err",
+        Msg::ReadErr => "Read Err",
+        Msg::OffsetErr => "Invalid tail offset",
+        Msg::Disconnected => "Disconnected, reconnecting",
+        Msg::NoAuthMethod => "No authentication method provided",
+        Msg::Line => "Line",
+        Msg::NoRuleMatched => "No rule matched",
+        Msg::Overlapped => "overlapped",
+        Msg::InspectorTitle => "Rule Inspector (i: close)",
+        Msg::MouseOff => "MOUSE OFF (c: capture)",
+        Msg::LinesPerSec => "lines/s",
+        Msg::Idle => "idle",
+        Msg::PickerTitle => "Open (Enter: open, Esc: cancel)",
+        Msg::Loading => "Loading...",
+        Msg::PausedOnError => "PAUSED ON ERROR (End: resume)",
+        Msg::GrepPrompt => "Remote grep",
+        Msg::GrepHelp => "Enter: jump, Esc: close",
+        Msg::NotInBuffer => "Line is no longer in the in-memory buffer",
+        Msg::DownloadErr => "Download Err",
+        Msg::DownloadMultiErr => "Download needs a single log_path",
+        Msg::Saved => "saved to",
+        Msg::TooSmall => "Terminal too small (Enter: maximize)",
+        Msg::Lines => "lines",
+        Msg::CommandErr => "Command Err",
+        Msg::CommandExited => "Command exited",
+        Msg::NoCommand => "no command configured",
+        Msg::Unsupported => "Not supported by this source",
+        Msg::ArchiveErr => "Archive Err",
+        Msg::StdinClosed => "stdin closed",
+        Msg::SaveLayoutPrompt => "Save layout as",
+        Msg::UnknownLayout => "Unknown layout",
+        Msg::SaveErr => "Save Err",
+        Msg::RulePattern => "Rule pattern",
+        Msg::RuleSuggestTitle => "Rule suggestions (e: edit, c: color, a: alert, s: save to config, Esc: close)",
+        Msg::NoSuggestions => "No WARN/ERROR lines in buffer",
+        Msg::ListenErr => "Listen Err",
+        Msg::ForwardErr => "Forward Err",
+        Msg::NoUrl => "no url configured",
+        Msg::HistoryShrunk => "memory cap, history shrunk to",
+        Msg::NoLogGroup => "no log_group configured",
+        Msg::FieldFilterTitle => "JSON fields",
+        Msg::NoJsonFields => "no JSON lines in buffer",
+        Msg::Sampled => "sampled",
+        Msg::Recording => "recording",
+        Msg::Copied => "copied",
+        Msg::NoPosition => "no remote position for this line",
+        Msg::Position => "Position",
+        Msg::NoRedisTarget => "redis source needs channel or stream",
+        Msg::NoDevice => "serial source needs device",
+        Msg::GelfErr => "Invalid GELF message",
+        Msg::NotOnPeer => "patterns not seen on",
+        Msg::FluentErr => "Forward protocol error",
+        Msg::DnsErr => "DNS lookup failed",
+        Msg::MissingFile => "Missing file",
+        Msg::NotInPath => "Not found in PATH",
+        Msg::PreflightContinue => "Continue with the working windows?",
+        Msg::PreflightOk => "All sources reachable",
+        Msg::NoContainer => "no container configured",
+        Msg::PodmanErr => "Podman Err",
+        Msg::NoWorkspace => "no workspace or query configured",
+        Msg::AuthNotConfigured => "not configured",
+        Msg::SearchPrompt => "Search",
+        Msg::NoMatch => "Pattern not found",
+        Msg::SearchWrapped => "Search wrapped",
+        Msg::Suspended => "idle, disconnected",
+        Msg::SharedTitle => "Lines seen in several windows within 30s (D: refresh)",
+        Msg::NoSharedLines => "No line has appeared in more than one window recently",
+        Msg::Ago => "ago",
+        Msg::SearchRegex => "regex",
+        Msg::SearchLiteral => "literal",
+        Msg::SearchMatchCase => "match case",
+        Msg::SearchIgnoreCase => "ignore case",
+        Msg::Silenced => "Alerts silenced for",
+        Msg::DryRunTarget => "target",
+        Msg::DryRunCommand => "command",
+        Msg::DryRunAuth => "auth",
+        Msg::DryRunHook => "pre_connect",
+        Msg::DryRunForward => "forward",
+        Msg::DryRunWindows => "windows would be opened",
+        Msg::GlobalSearchPrompt => "Search all windows",
+        Msg::GlobalSearchHelp => "Enter: jump  Esc: close",
+        Msg::FilterPrompt => "Show only lines matching",
+        Msg::Filtered => "filtered:",
+        Msg::Hidden => "hidden",
+        Msg::UnknownWindow => "unknown window",
+        Msg::ControlErr => "Control socket unavailable",
+        Msg::NoTimestamp => "no timestamp found in this window",
+        Msg::LocalHost => "local",
+        Msg::WindowsCount => "windows",
+        Msg::ExcludePrompt => "Hide lines matching",
+        Msg::Excluded => "excluded:",
+        Msg::CheckPlaintext => "plaintext secret in config",
+        Msg::CheckPlaintextFix => "use password_cmd, ${ENV_VAR} or ssh-agent instead",
+        Msg::CheckWorldReadable => "config file is readable by other users",
+        Msg::CheckGroupReadable => "config file is readable by its group",
+        Msg::CheckHostKey => "host key is not verified against known_hosts",
+        Msg::CheckHostKeyFix => "set host_key_check = \"strict\" (or \"accept-new\") to verify against ~/.ssh/known_hosts",
+        Msg::CheckFix => "fix:",
+        Msg::CheckOk => "no issues found",
+        Msg::CheckIssues => "issues",
+        Msg::SearchesTitle => "Saved searches — Enter or / search · & filter · ! exclude",
+        Msg::NoSavedSearches => "No [searches] in config",
+        Msg::Marked => "Marked",
+        Msg::Unmarked => "Unmarked",
+        Msg::NoMarks => "No marks in this window",
+        Msg::Frozen => "PAUSED",
+        Msg::NewLines => "new",
+        Msg::Follow => "▼ FOLLOW",
+        Msg::Manual => "∥ MANUAL",
+        Msg::NoWrap => "NO-WRAP",
+        Msg::HostKeyErr => "Host key check failed",
+        Msg::HostKeyChanged => "host key does not match known_hosts, possible MITM",
+        Msg::HostKeyUnknown => "host is not in known_hosts",
+    }
+}
+
+fn zh(msg: Msg) -> &'static str {
+    match msg {
+        Msg::Scroll => "滚动",
+        Msg::ConnectErr => "连接失败",
+        Msg::HandshakeErr => "握手失败",
+        Msg::AuthErr => "认证失败",
+        Msg::HookErr => "钩子执行失败",
+        Msg::AlgorithmErr => "SSH 算法配置错误",
+        Msg::ReadErr => "读取失败",
+        Msg::OffsetErr => "无效的读取偏移",
+        Msg::Disconnected => "连接断开, 正在重连",
+        Msg::NoAuthMethod => "未配置认证方式",
+        Msg::Line => "行",
+        Msg::NoRuleMatched => "没有匹配的规则",
+        Msg::Overlapped => "重叠",
+        Msg::InspectorTitle => "规则检查 (i: 关闭)",
+        Msg::MouseOff => "鼠标已释放 (c: 恢复)",
+        Msg::LinesPerSec => "行/秒",
+        Msg::Idle => "空闲",
+        Msg::PickerTitle => "打开 (Enter: 打开, Esc: 取消)",
+        Msg::Loading => "加载中...",
+        Msg::PausedOnError => "遇到错误已暂停 (End: 继续)",
+        Msg::GrepPrompt => "远程 grep",
+        Msg::GrepHelp => "Enter: 跳转, Esc: 关闭",
+        Msg::NotInBuffer => "该行已不在内存缓冲区中",
+        Msg::DownloadErr => "下载失败",
+        Msg::DownloadMultiErr => "下载仅支持单个 log_path",
+        Msg::Saved => "已保存到",
+        Msg::TooSmall => "终端太小 (Enter: 最大化)",
+        Msg::Lines => "行",
+        Msg::CommandErr => "命令启动失败",
+        Msg::CommandExited => "命令已退出",
+        Msg::NoCommand => "未配置 command",
+        Msg::Unsupported => "该数据源不支持此操作",
+        Msg::ArchiveErr => "归档失败",
+        Msg::StdinClosed => "标准输入已关闭",
+        Msg::SaveLayoutPrompt => "保存布局为",
+        Msg::UnknownLayout => "未知的布局",
+        Msg::SaveErr => "保存失败",
+        Msg::RulePattern => "规则正则",
+        Msg::RuleSuggestTitle => "规则建议 (e: 编辑, c: 颜色, a: 告警, s: 保存到配置, Esc: 关闭)",
+        Msg::NoSuggestions => "缓冲区中没有 WARN/ERROR 行",
+        Msg::ListenErr => "监听失败",
+        Msg::ForwardErr => "端口转发失败",
+        Msg::NoUrl => "未配置 url",
+        Msg::HistoryShrunk => "内存超限, 历史缩减至",
+        Msg::NoLogGroup => "未配置 log_group",
+        Msg::FieldFilterTitle => "JSON 字段",
+        Msg::NoJsonFields => "缓冲区中没有 JSON 行",
+        Msg::Sampled => "抽样",
+        Msg::Recording => "录制中",
+        Msg::Copied => "已复制",
+        Msg::NoPosition => "该行没有远端文件位置",
+        Msg::Position => "位置",
+        Msg::NoRedisTarget => "redis 数据源需要配置 channel 或 stream",
+        Msg::NoDevice => "serial 数据源需要配置 device",
+        Msg::GelfErr => "无法解析的 GELF 消息",
+        Msg::NotOnPeer => "个模式未出现在",
+        Msg::FluentErr => "Forward 协议错误",
+        Msg::DnsErr => "域名解析失败",
+        Msg::MissingFile => "文件不存在或不可读",
+        Msg::NotInPath => "PATH 中找不到",
+        Msg::PreflightContinue => "只打开可用的窗口继续?",
+        Msg::PreflightOk => "所有数据源均可用",
+        Msg::NoContainer => "未配置容器",
+        Msg::PodmanErr => "Podman 请求失败",
+        Msg::NoWorkspace => "未配置 workspace 或 query",
+        Msg::AuthNotConfigured => "未配置",
+        Msg::SearchPrompt => "搜索",
+        Msg::NoMatch => "未找到匹配",
+        Msg::SearchWrapped => "已从另一端继续搜索",
+        Msg::Suspended => "空闲已断开",
+        Msg::SharedTitle => "30 秒内出现在多个窗口的行 (D: 刷新)",
+        Msg::NoSharedLines => "最近没有同时出现在多个窗口的行",
+        Msg::Ago => "前",
+        Msg::SearchRegex => "正则",
+        Msg::SearchLiteral => "字面",
+        Msg::SearchMatchCase => "区分大小写",
+        Msg::SearchIgnoreCase => "忽略大小写",
+        Msg::Silenced => "已屏蔽告警",
+        Msg::DryRunTarget => "目标",
+        Msg::DryRunCommand => "命令",
+        Msg::DryRunAuth => "认证",
+        Msg::DryRunHook => "连接前",
+        Msg::DryRunForward => "端口转发",
+        Msg::DryRunWindows => "个窗口将会打开",
+        Msg::GlobalSearchPrompt => "搜索所有窗口",
+        Msg::GlobalSearchHelp => "Enter: 跳转  Esc: 关闭",
+        Msg::FilterPrompt => "只显示匹配的行",
+        Msg::Filtered => "过滤:",
+        Msg::Hidden => "行已隐藏",
+        Msg::UnknownWindow => "没有这个窗口",
+        Msg::ControlErr => "控制 socket 不可用",
+        Msg::NoTimestamp => "该窗口中没有可识别的时间戳",
+        Msg::LocalHost => "本机",
+        Msg::WindowsCount => "个窗口",
+        Msg::ExcludePrompt => "隐藏匹配的行",
+        Msg::Excluded => "排除:",
+        Msg::CheckPlaintext => "配置里有明文密钥",
+        Msg::CheckPlaintextFix => "改用 password_cmd、${环境变量} 或 ssh-agent",
+        Msg::CheckWorldReadable => "配置文件其他用户可读",
+        Msg::CheckGroupReadable => "配置文件同组用户可读",
+        Msg::CheckHostKey => "未按 known_hosts 校验主机密钥",
+        Msg::CheckHostKeyFix => "设置 host_key_check = \"strict\" (或 \"accept-new\") 按 ~/.ssh/known_hosts 校验",
+        Msg::CheckFix => "建议:",
+        Msg::CheckOk => "没有发现问题",
+        Msg::CheckIssues => "个问题",
+        Msg::SearchesTitle => "保存的搜索 — Enter 或 / 搜索 · & 过滤 · ! 排除",
+        Msg::NoSavedSearches => "配置里没有 [searches]",
+        Msg::Marked => "已标记",
+        Msg::Unmarked => "已取消标记",
+        Msg::NoMarks => "这个窗口没有标记",
+        Msg::Frozen => "已暂停",
+        Msg::NewLines => "新行",
+        Msg::Follow => "▼ 跟随",
+        Msg::Manual => "∥ 手动",
+        Msg::NoWrap => "不折行",
+        Msg::HostKeyErr => "主机密钥校验失败",
+        Msg::HostKeyChanged => "主机密钥与 known_hosts 不一致, 可能被中间人攻击",
+        Msg::HostKeyUnknown => "known_hosts 中没有该主机",
+    }
+}
@@ -0,0 +1,40 @@
+use std::io::Read;
+
+use flate2::read::{MultiGzDecoder, ZlibDecoder};
+
+pub fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&[0x1f, 0x8b])
+}
+
+// zlib 头的两个字节按大端合起来是 31 的倍数
+pub fn is_zlib(data: &[u8]) -> bool {
+    matches!(data, [cmf, flg, ..] if cmf & 0x0f == 8 && (*cmf as u16 * 256 + *flg as u16).is_multiple_of(31))
+}
+
+// 解压结果超过 limit 时报错, 避免压缩炸弹; 校验和不对时 flate2 会返回错误
+fn read_limited(decoder: impl Read, limit: usize) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    decoder
+        .take(limit as u64 + 1)
+        .read_to_end(&mut out)
+        .map_err(|e| e.to_string())?;
+    if out.len() > limit {
+        return Err("decompressed data too large".to_string());
+    }
+    Ok(out)
+}
+
+// 多个 gzip 成员首尾相接时依次解开
+pub fn gunzip(data: &[u8], limit: usize) -> Result<Vec<u8>, String> {
+    if !is_gzip(data) {
+        return Err("not gzip data".to_string());
+    }
+    read_limited(MultiGzDecoder::new(data), limit)
+}
+
+pub fn unzlib(data: &[u8], limit: usize) -> Result<Vec<u8>, String> {
+    if !is_zlib(data) {
+        return Err("unsupported zlib data".to_string());
+    }
+    read_limited(ZlibDecoder::new(data), limit)
+}
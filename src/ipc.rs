@@ -0,0 +1,75 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+// rogger-ctl 发来的命令, 由 UI 线程执行后回复
+pub struct Request {
+    pub args: Vec<String>,
+    pub reply: Sender<Result<String, String>>,
+}
+
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+// ROGGER_SOCKET 优先, 其次是配置的 control_socket, 默认 ~/.rogger/ctl.sock
+pub fn socket_path(configured: Option<&str>) -> io::Result<PathBuf> {
+    if let Some(path) = std::env::var_os("ROGGER_SOCKET") {
+        return Ok(PathBuf::from(path));
+    }
+    if let Some(path) = configured {
+        return Ok(PathBuf::from(path));
+    }
+    let home = std::env::var("HOME").map_err(|_| {
+        io::Error::new(io::ErrorKind::NotFound, "HOME environment variable not set")
+    })?;
+    Ok(PathBuf::from(home).join(".rogger/ctl.sock"))
+}
+
+// 另一个 rogger 正在监听同一路径时不抢占; 残留的 socket 文件直接删除
+pub fn listen(path: PathBuf) -> io::Result<Receiver<Request>> {
+    if UnixStream::connect(&path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("{} is in use", path.display()),
+        ));
+    }
+    let _ = fs::remove_file(&path);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let listener = UnixListener::bind(&path)?;
+    let (sender, requests) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let sender = sender.clone();
+            thread::spawn(move || serve(stream, sender));
+        }
+    });
+    Ok(requests)
+}
+
+// 每个连接一条命令: 一行 JSON 字符串数组, 回复一行 "ok ..." 或 "error ..."
+fn serve(stream: UnixStream, sender: Sender<Request>) {
+    let mut line = String::new();
+    if BufReader::new(&stream).read_line(&mut line).is_err() {
+        return;
+    }
+    let result = match serde_json::from_str::<Vec<String>>(&line) {
+        Ok(args) => {
+            let (reply, response) = mpsc::channel();
+            let _ = sender.send(Request { args, reply });
+            response
+                .recv_timeout(REPLY_TIMEOUT)
+                .unwrap_or_else(|_| Err("no response".to_string()))
+        }
+        Err(e) => Err(e.to_string()),
+    };
+    let mut stream = stream;
+    let _ = match result {
+        Ok(message) => writeln!(stream, "ok {}", message),
+        Err(message) => writeln!(stream, "error {}", message),
+    };
+}
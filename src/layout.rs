@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+// 显示哪些窗口、顺序、高度权重以及最大化的窗口, 都按窗口名保存
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LayoutConfig {
+    pub windows: Vec<String>,
+    pub weights: Option<Vec<u16>>,
+    pub maximized: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct LayoutsFile {
+    #[serde(default)]
+    layouts: BTreeMap<String, LayoutConfig>,
+}
+
+fn layouts_path() -> io::Result<PathBuf> {
+    let home = std::env::var("HOME").map_err(|_| {
+        io::Error::new(io::ErrorKind::NotFound, "HOME environment variable not set")
+    })?;
+    Ok(PathBuf::from(home).join(".rogger/layouts.toml"))
+}
+
+fn read_file() -> io::Result<LayoutsFile> {
+    match fs::read_to_string(layouts_path()?) {
+        Ok(content) => toml::from_str(&content).map_err(io::Error::other),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(LayoutsFile::default()),
+        Err(e) => Err(e),
+    }
+}
+
+// 配置文件中的 [layouts.NAME] 加上运行时保存的布局, 同名时以保存的为准
+pub fn load_layouts(configured: Option<&BTreeMap<String, LayoutConfig>>) -> BTreeMap<String, LayoutConfig> {
+    let mut layouts = configured.cloned().unwrap_or_default();
+    if let Ok(file) = read_file() {
+        layouts.extend(file.layouts);
+    }
+    layouts
+}
+
+pub fn save_layout(name: &str, layout: &LayoutConfig) -> io::Result<()> {
+    let mut file = read_file()?;
+    file.layouts.insert(name.to_string(), layout.clone());
+    let path = layouts_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, toml::to_string(&file).map_err(io::Error::other)?)
+}
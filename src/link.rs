@@ -0,0 +1,135 @@
+use std::fmt::Display;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::i18n::{tr, Msg};
+
+// TLS 交给 openssl s_client, 和其它数据源调用外部 CLI 的做法一致
+pub enum Link {
+    Tcp(TcpStream),
+    Tls(Arc<Mutex<Child>>),
+}
+
+impl Drop for Link {
+    fn drop(&mut self) {
+        match self {
+            Link::Tcp(stream) => {
+                let _ = stream.shutdown(Shutdown::Both);
+            }
+            Link::Tls(child) => {
+                let mut child = child.lock().unwrap();
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+}
+
+pub struct Connection {
+    pub link: Link,
+    pub reader: BufReader<Box<dyn Read + Send>>,
+    pub writer: Box<dyn Write + Send>,
+    // s_client 没有读超时: 握手完成前看门狗到时间就杀掉进程, 发送一次即解除
+    watchdog: Option<Sender<()>>,
+    stderr: Option<Receiver<Vec<u8>>>,
+}
+
+impl Connection {
+    // 握手阶段带超时, 之后读线程一直阻塞等数据
+    pub fn clear_timeout(&self) -> Result<(), String> {
+        if let Link::Tcp(stream) = &self.link {
+            stream.set_read_timeout(None).map_err(|e| e.to_string())?;
+        }
+        if let Some(watchdog) = &self.watchdog {
+            let _ = watchdog.send(());
+        }
+        Ok(())
+    }
+
+    // 握手失败时附上 s_client 的最后一行错误输出, 例如证书校验失败的原因
+    pub fn explain(&self, error: impl Display) -> String {
+        let mut last = None;
+        if let Some(stderr) = &self.stderr {
+            while let Ok(bytes) = stderr.recv_timeout(Duration::from_millis(200)) {
+                let line = String::from_utf8_lossy(&bytes).trim().to_string();
+                if !line.is_empty() {
+                    last = Some(line);
+                }
+            }
+        }
+        match last {
+            Some(line) => format!("{} ({})", error, line),
+            None => error.to_string(),
+        }
+    }
+}
+
+fn watch(child: Arc<Mutex<Child>>, timeout: Duration) -> Sender<()> {
+    let (disarm, disarmed) = mpsc::channel();
+    thread::spawn(move || {
+        if let Err(RecvTimeoutError::Timeout) = disarmed.recv_timeout(timeout) {
+            let _ = child.lock().unwrap().kill();
+        }
+    });
+    disarm
+}
+
+pub fn open(host: &str, port: u16, tls: bool, ca_file: Option<&str>, timeout: Duration) -> Result<Connection, String> {
+    let address = format!("{}:{}", host, port);
+    let connect_err = |e: io::Error| format!("{} ({}): {}", tr(Msg::ConnectErr), address, e);
+
+    if !tls {
+        let stream = TcpStream::connect(&address).map_err(connect_err)?;
+        stream.set_read_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+        let reader = stream.try_clone().map_err(|e| e.to_string())?;
+        let writer = stream.try_clone().map_err(|e| e.to_string())?;
+        return Ok(Connection {
+            link: Link::Tcp(stream),
+            reader: BufReader::new(Box::new(reader)),
+            writer: Box::new(writer),
+            watchdog: None,
+            stderr: None,
+        });
+    }
+
+    let mut command = Command::new("openssl");
+    command
+        .args(["s_client", "-quiet", "-verify_return_error", "-verify_hostname", host])
+        .args(["-servername", host, "-connect", &address]);
+    if let Some(ca_file) = ca_file {
+        command.args(["-CAfile", ca_file]);
+    }
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(connect_err)?;
+    let reader = child.stdout.take().unwrap();
+    let writer = child.stdin.take().unwrap();
+    // 握手之后没人收也要一直读, 否则 s_client 写 stderr 时会被 SIGPIPE 杀掉
+    let (error_sender, stderr) = mpsc::channel();
+    let mut errors = BufReader::new(child.stderr.take().unwrap());
+    thread::spawn(move || loop {
+        let mut line = Vec::new();
+        match errors.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                let _ = error_sender.send(line);
+            }
+        }
+    });
+    let child = Arc::new(Mutex::new(child));
+    Ok(Connection {
+        watchdog: Some(watch(Arc::clone(&child), timeout)),
+        link: Link::Tls(child),
+        reader: BufReader::new(Box::new(reader)),
+        writer: Box::new(writer),
+        stderr: Some(stderr),
+    })
+}
@@ -0,0 +1,75 @@
+use std::fs;
+use std::path::Path;
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::config::HighlightConfig;
+
+pub struct Imported {
+    pub highlights: Vec<HighlightConfig>,
+    // JSON 格式的日志, 导入时顺带打开 json_flatten
+    pub json: bool,
+}
+
+// lnav 的级别按严重程度对应颜色, error 及以上同时作为告警规则
+const LEVELS: [(&str, &str, bool); 5] = [
+    ("fatal", "red", true),
+    ("critical", "red", true),
+    ("error", "red", true),
+    ("warning", "yellow", false),
+    ("notice", "cyan", false),
+];
+
+// lnav 的级别正则匹配的是级别字段, 去掉锚点并加上单词边界后用于整行
+fn level_pattern(pattern: &str) -> String {
+    let pattern = pattern.strip_prefix('^').unwrap_or(pattern);
+    let pattern = pattern.strip_suffix('$').unwrap_or(pattern);
+    format!(r"\b(?:{})\b", pattern)
+}
+
+// lnav 的颜色是 #rrggbb 或颜色名; semantic() 等无法对应的颜色忽略, 使用默认颜色
+fn color(value: &Value) -> Option<String> {
+    let color = value.as_str()?;
+    (!color.contains('(')).then(|| color.to_lowercase().replace(' ', ""))
+}
+
+// 读取 lnav 格式定义文件 (https://docs.lnav.org/en/latest/formats.html),
+// 一个文件里可以有多个格式, 以 $ 开头的键是 schema 等元数据
+pub fn import(path: &Path) -> Result<Imported, String> {
+    let display = path.display();
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", display, e))?;
+    let value: Value = serde_json::from_str(&content).map_err(|e| format!("{}: {}", display, e))?;
+    let formats = value
+        .as_object()
+        .ok_or_else(|| format!("{}: not an lnav format file", display))?;
+
+    let mut imported = Imported {
+        highlights: Vec::new(),
+        json: false,
+    };
+    for (_, format) in formats.iter().filter(|(name, _)| !name.starts_with('$')) {
+        imported.json |= format["json"].as_bool().unwrap_or(false);
+        for (level, color, alert) in LEVELS {
+            if let Some(pattern) = format["level"][level].as_str() {
+                imported.highlights.push(HighlightConfig {
+                    pattern: level_pattern(pattern),
+                    color: Some(color.to_string()),
+                    alert: alert.then_some(true),
+                });
+            }
+        }
+        for highlight in format["highlights"].as_object().into_iter().flat_map(|h| h.values()) {
+            if let Some(pattern) = highlight["pattern"].as_str() {
+                imported.highlights.push(HighlightConfig {
+                    pattern: pattern.to_string(),
+                    color: color(&highlight["color"]),
+                    alert: None,
+                });
+            }
+        }
+    }
+    // lnav 用 PCRE, 环视等 regex crate 不支持的写法无法导入, 跳过这些规则
+    imported.highlights.retain(|rule| Regex::new(&rule.pattern).is_ok());
+    Ok(imported)
+}
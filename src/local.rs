@@ -0,0 +1,300 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::Receiver;
+use std::thread;
+use std::time::Duration;
+
+use crate::config;
+use crate::i18n::{tr, Msg};
+use crate::ssh::{ConnectionStatus, DownloadProgress, LogSink, SourceRequest};
+use crate::timestamp::{self, Format};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const BACKFILL_LINES: usize = 100;
+const BACKFILL_BYTES: u64 = 256 * 1024;
+
+// 跟踪单个本地文件, 通过 inode 和文件大小识别轮转与截断
+struct Follower {
+    path: String,
+    prefix: Option<String>,
+    reader: Option<BufReader<File>>,
+    inode: u64,
+    position: u64,
+    pending: Vec<u8>,
+    backfill: Option<(Duration, Option<Format>)>,
+}
+
+impl Follower {
+    fn new(path: String, multi: bool, log: &config::LogConfig) -> Self {
+        let prefix = multi.then(|| {
+            let name = path.rsplit('/').next().unwrap_or(&path);
+            format!("[{}] ", name)
+        });
+        Follower {
+            path,
+            prefix,
+            reader: None,
+            inode: 0,
+            position: 0,
+            pending: Vec::new(),
+            backfill: log
+                .backfill
+                .as_deref()
+                .and_then(timestamp::parse_duration)
+                .map(|window| (window, log.timestamp_format)),
+        }
+    }
+
+    fn open(&mut self, backfill: bool) -> io::Result<()> {
+        let mut file = File::open(&self.path)?;
+        let meta = file.metadata()?;
+        let start = match (backfill, self.backfill) {
+            (true, Some((window, format))) => match backfill_since(&mut file, meta.len(), window, format)? {
+                Some(start) => start,
+                None => backfill_offset(&mut file, meta.len())?,
+            },
+            (true, None) => backfill_offset(&mut file, meta.len())?,
+            (false, _) => 0,
+        };
+        file.seek(SeekFrom::Start(start))?;
+        self.reader = Some(BufReader::new(file));
+        self.inode = meta.ino();
+        self.position = start;
+        self.pending.clear();
+        Ok(())
+    }
+
+    // 读到文件末尾为止, 返回是否读到了新数据
+    fn poll(&mut self, sink: &LogSink) -> io::Result<bool> {
+        if self.reader.is_none() {
+            if fs::metadata(&self.path).is_err() {
+                return Ok(false);
+            }
+            self.open(false)?;
+        }
+
+        let mut read_any = false;
+        if let Some(reader) = self.reader.as_mut() {
+            loop {
+                let n = reader.read_until(b'\n', &mut self.pending)?;
+                if n == 0 {
+                    break;
+                }
+                read_any = true;
+                self.position += n as u64;
+                sink.stats.lock().unwrap().bytes_received += n as u64;
+                // 末尾没有换行说明写入方还没写完这一行
+                if self.pending.last() != Some(&b'\n') {
+                    break;
+                }
+                let line = sink.decode(&self.pending);
+                self.pending.clear();
+                match &self.prefix {
+                    Some(prefix) => sink.push_line(format!("{}{}", prefix, line)),
+                    None => sink.push_line(line),
+                }
+            }
+        }
+
+        // 旧文件读完后再检查是否被轮转或截断
+        if let Ok(meta) = fs::metadata(&self.path) {
+            if meta.ino() != self.inode || meta.len() < self.position {
+                self.open(false)?;
+                read_any = true;
+            }
+        }
+        Ok(read_any)
+    }
+}
+
+// 从文件末尾往前找最后 BACKFILL_LINES 行的起始位置
+fn backfill_offset(file: &mut File, len: u64) -> io::Result<u64> {
+    let start = len.saturating_sub(BACKFILL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+
+    let mut newlines = 0;
+    let search_end = tail.len().saturating_sub(1);
+    for (i, byte) in tail[..search_end].iter().enumerate().rev() {
+        if *byte == b'\n' {
+            newlines += 1;
+            if newlines == BACKFILL_LINES {
+                return Ok(start + i as u64 + 1);
+            }
+        }
+    }
+    Ok(start)
+}
+
+// 从 start 开始的第一行的起始位置
+fn next_line_start(reader: &mut BufReader<&mut File>, start: u64) -> io::Result<u64> {
+    if start == 0 {
+        reader.seek(SeekFrom::Start(0))?;
+        return Ok(0);
+    }
+    reader.seek(SeekFrom::Start(start - 1))?;
+    let skipped = reader.read_until(b'\n', &mut Vec::new())?;
+    Ok(start - 1 + skipped as u64)
+}
+
+// 从 start 所在的下一行起第一个能解析出时间戳的行
+fn first_timestamp(reader: &mut BufReader<&mut File>, start: u64, format: Format) -> io::Result<Option<i64>> {
+    next_line_start(reader, start)?;
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(None);
+        }
+        if let Some(millis) = format.parse(&String::from_utf8_lossy(&line)) {
+            return Ok(Some(millis));
+        }
+    }
+}
+
+// backfill = "30m": 以文件末尾最后一个时间戳为准往前推, 不受日志时区影响
+// 日志按时间追加, 二分查找第一行不早于该时间的位置; 末尾没有可识别的时间戳时返回 None
+fn backfill_since(file: &mut File, len: u64, window: Duration, format: Option<Format>) -> io::Result<Option<u64>> {
+    let start = len.saturating_sub(BACKFILL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+    let mut tail = String::new();
+    file.take(BACKFILL_BYTES).read_to_string(&mut tail).ok();
+    let lines: Vec<String> = tail.lines().map(str::to_string).collect();
+    let format = format.unwrap_or_else(|| timestamp::detect(&lines));
+    let Some(last) = lines.iter().rev().find_map(|line| format.parse(line)) else {
+        return Ok(None);
+    };
+    let cutoff = last - window.as_millis() as i64;
+
+    let mut reader = BufReader::new(file);
+    let (mut low, mut high) = (0, len);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        match first_timestamp(&mut reader, mid, format)? {
+            Some(millis) if millis < cutoff => low = mid + 1,
+            _ => high = mid,
+        }
+    }
+    next_line_start(&mut reader, low).map(Some)
+}
+
+// 通配符交给本地 shell 展开, 与远端的行为保持一致
+fn expand_paths(log_path: &config::LogPath) -> Vec<String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(format!("for f in {}; do printf '%s\\n' \"$f\"; done", log_path.shell_args()))
+        .stdin(Stdio::null())
+        .output();
+    match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect(),
+        _ => log_path.paths().iter().map(|p| p.to_string()).collect(),
+    }
+}
+
+pub fn run_local(command: &str) -> Result<String, String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| e.to_string())?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn copy_local(log: &config::LogConfig, local_path: PathBuf) -> DownloadProgress {
+    let mut progress = DownloadProgress {
+        local_path: local_path.clone(),
+        total: 0,
+        done: 0,
+        finished: false,
+        error: None,
+    };
+    if log.log_path.is_multi() {
+        progress.error = Some(tr(Msg::DownloadMultiErr).to_string());
+        return progress;
+    }
+
+    let result = local_path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::copy(log.log_path.paths()[0], &local_path));
+    match result {
+        Ok(n) => {
+            progress.total = n;
+            progress.done = n;
+            progress.finished = true;
+        }
+        Err(e) => progress.error = Some(e.to_string()),
+    }
+    progress
+}
+
+pub fn tail_local(log: &config::LogConfig, sink: LogSink, requests: Receiver<SourceRequest>) {
+    let mut log = log.clone();
+
+    'source: loop {
+        let multi = log.log_path.is_multi();
+        let mut followers: Vec<Follower> = expand_paths(&log.log_path)
+            .into_iter()
+            .map(|path| Follower::new(path, multi, &log))
+            .collect();
+
+        for follower in followers.iter_mut() {
+            if let Err(e) = follower.open(true) {
+                sink.set_status(ConnectionStatus::Error(format!(
+                    "{} ({}): {}",
+                    tr(Msg::ReadErr),
+                    follower.path,
+                    e
+                )));
+            }
+        }
+
+        loop {
+            while let Ok(request) = requests.try_recv() {
+                match request {
+                    SourceRequest::Exec { command, reply } => {
+                        let _ = reply.send(run_local(&command));
+                    }
+                    SourceRequest::SwitchPath(path) => {
+                        log.log_path = config::LogPath::Single(path);
+                        sink.clear();
+                        sink.set_status(ConnectionStatus::Connected);
+                        continue 'source;
+                    }
+                    SourceRequest::Refresh => {
+                        sink.clear();
+                        continue 'source;
+                    }
+                    SourceRequest::Suspend | SourceRequest::Resume => {}
+                    SourceRequest::Download(local_path) => {
+                        *sink.download.lock().unwrap() = Some(copy_local(&log, local_path));
+                    }
+                }
+            }
+
+            let mut read_any = false;
+            for follower in followers.iter_mut() {
+                match follower.poll(&sink) {
+                    Ok(read) => read_any |= read,
+                    Err(e) => sink.set_status(ConnectionStatus::Error(format!(
+                        "{} ({}): {}",
+                        tr(Msg::ReadErr),
+                        follower.path,
+                        e
+                    ))),
+                }
+            }
+            if !read_any {
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use std::collections::hash_map::RandomState;
+use std::fs::File;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufRead, Read, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+
+use crate::codec::{base64, percent_encode};
+use crate::command::{handle_request, idle};
+use crate::config;
+use crate::i18n::{tr, Msg};
+use crate::link::{self, Connection};
+use crate::ssh::{ConnectionStatus, LogSink, SourceRequest};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_FRAME: u64 = 16 * 1024 * 1024;
+
+struct Endpoint {
+    tls: bool,
+    host: String,
+    port: u16,
+    path: String,
+}
+
+// http:// 走 ws:// 升级, https:// 走 wss://
+fn parse_url(url: &str) -> Result<Endpoint, String> {
+    let (tls, rest) = [("http://", false), ("ws://", false), ("https://", true), ("wss://", true)]
+        .iter()
+        .find_map(|(scheme, tls)| Some((*tls, url.strip_prefix(scheme)?)))
+        .ok_or_else(|| format!("Loki url must start with http:// or https://: {}", url))?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], rest[i..].trim_end_matches('/')),
+        None => (rest, ""),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (
+            host,
+            port.parse()
+                .map_err(|_| format!("Invalid Loki url port: {}", url))?,
+        ),
+        None => (authority, if tls { 443 } else { 80 }),
+    };
+    Ok(Endpoint {
+        tls,
+        host: host.to_string(),
+        port,
+        path: path.to_string(),
+    })
+}
+
+// Sec-WebSocket-Key 和每一帧的掩码都要不可预测; 读不了 /dev/urandom 时退回 RandomState 的随机种子
+fn random<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    let read = File::open("/dev/urandom").and_then(|mut urandom| urandom.read_exact(&mut bytes));
+    if read.is_err() {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        for chunk in bytes.chunks_mut(8) {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u128(now);
+            chunk.copy_from_slice(&hasher.finish().to_be_bytes()[..chunk.len()]);
+        }
+    }
+    bytes
+}
+
+fn connect(log: &config::LogConfig, query: &str, start: Option<u128>) -> Result<Connection, String> {
+    let url = log.url.as_deref().ok_or_else(|| tr(Msg::NoUrl).to_string())?;
+    let endpoint = parse_url(url)?;
+    let mut target = format!(
+        "{}/loki/api/v1/tail?query={}",
+        endpoint.path,
+        percent_encode(query)
+    );
+    // 重连时从最后一条之后继续, 避免重复
+    if let Some(start) = start {
+        target.push_str(&format!("&start={}", start + 1));
+    }
+
+    let address = format!("{}:{}", endpoint.host, endpoint.port);
+    let mut connection = link::open(
+        &endpoint.host,
+        endpoint.port,
+        endpoint.tls,
+        log.ca_file.as_deref(),
+        CONNECT_TIMEOUT,
+    )?;
+
+    let mut request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n",
+        target,
+        address,
+        base64(&random::<16>())
+    );
+    if let (Some(username), Some(password)) = (&log.username, &log.password) {
+        let credentials = base64(format!("{}:{}", username, password).as_bytes());
+        request.push_str(&format!("Authorization: Basic {}\r\n", credentials));
+    } else if let Some(token) = &log.token {
+        request.push_str(&format!("Authorization: Bearer {}\r\n", token));
+    }
+    // 多租户时通过 headers 传 X-Scope-OrgID
+    for (name, value) in log.headers.iter().flatten() {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    upgrade(&mut connection, &request).map_err(|e| connection.explain(e))?;
+    connection.clear_timeout()?;
+    Ok(connection)
+}
+
+fn upgrade(connection: &mut Connection, request: &str) -> Result<(), String> {
+    connection.writer.write_all(request.as_bytes()).map_err(|e| e.to_string())?;
+
+    // 响应头之后可能紧跟着帧, 之后继续用同一个 reader 读, 缓冲的字节才不会丢
+    let reader = &mut connection.reader;
+    let mut status = String::new();
+    reader.read_line(&mut status).map_err(|e| e.to_string())?;
+    if status.is_empty() {
+        return Err(tr(Msg::Disconnected).to_string());
+    }
+    if status.split_whitespace().nth(1) != Some("101") {
+        return Err(format!("{}: {}", tr(Msg::ConnectErr), status.trim()));
+    }
+    loop {
+        let mut header = String::new();
+        match reader.read_line(&mut header) {
+            Ok(0) => return Err(tr(Msg::Disconnected).to_string()),
+            Ok(_) if header.trim().is_empty() => return Ok(()),
+            Ok(_) => {}
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+}
+
+// 客户端发出的帧必须加掩码, 每帧用新的随机掩码
+fn write_frame(stream: &mut impl Write, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mask: [u8; 4] = random();
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        n if n < 126 => frame.push(0x80 | n as u8),
+        n if n <= u16::MAX as usize => {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(n as u16).to_be_bytes());
+        }
+        n => {
+            frame.push(0x80 | 127);
+            frame.extend_from_slice(&(n as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(&mask);
+    frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+    stream.write_all(&frame)
+}
+
+// 读出完整的文本消息, 顺带回应 ping; 连接关闭时返回 Ok(None)
+fn read_message(reader: &mut impl Read, writer: &mut impl Write) -> io::Result<Option<Vec<u8>>> {
+    let mut message = Vec::new();
+    loop {
+        let mut head = [0u8; 2];
+        reader.read_exact(&mut head)?;
+        let fin = head[0] & 0x80 != 0;
+        let opcode = head[0] & 0x0f;
+        let masked = head[1] & 0x80 != 0;
+        let length = match head[1] & 0x7f {
+            126 => {
+                let mut buf = [0u8; 2];
+                reader.read_exact(&mut buf)?;
+                u16::from_be_bytes(buf) as u64
+            }
+            127 => {
+                let mut buf = [0u8; 8];
+                reader.read_exact(&mut buf)?;
+                u64::from_be_bytes(buf)
+            }
+            n => n as u64,
+        };
+        if length > MAX_FRAME {
+            return Err(io::Error::other("websocket frame too large"));
+        }
+        let mut mask = [0u8; 4];
+        if masked {
+            reader.read_exact(&mut mask)?;
+        }
+        let mut payload = vec![0u8; length as usize];
+        reader.read_exact(&mut payload)?;
+        if masked {
+            payload.iter_mut().enumerate().for_each(|(i, b)| *b ^= mask[i % 4]);
+        }
+
+        match opcode {
+            0..=2 => {
+                message.extend_from_slice(&payload);
+                if fin {
+                    return Ok(Some(message));
+                }
+            }
+            8 => {
+                let _ = write_frame(writer, 8, &payload);
+                return Ok(None);
+            }
+            9 => write_frame(writer, 10, &payload)?,
+            _ => {}
+        }
+    }
+}
+
+// 连接归读线程所有, 读完时一起关闭
+fn read_messages(connection: Connection, messages: Sender<Vec<u8>>) {
+    let Connection { link, mut reader, mut writer, .. } = connection;
+    while let Ok(Some(message)) = read_message(&mut reader, &mut writer) {
+        if messages.send(message).is_err() {
+            break;
+        }
+    }
+    drop(link);
+}
+
+fn format_labels(labels: &Value) -> String {
+    let labels: Vec<String> = labels
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(name, value)| format!("{}={}", name, value.as_str().unwrap_or_default()))
+        .collect();
+    format!("{{{}}}", labels.join(", "))
+}
+
+// labels = "title" 时标签只显示在标题上; 查询匹配到多个流时标题说明不了每一行, 改回加在行首
+enum TitleLabels {
+    Off,
+    One(Option<String>),
+    Mixed,
+}
+
+impl TitleLabels {
+    fn in_title(&mut self, labels: &str, sink: &LogSink) -> bool {
+        match self {
+            TitleLabels::One(None) => {
+                *sink.subtitle.lock().unwrap() = Some(labels.to_string());
+                *self = TitleLabels::One(Some(labels.to_string()));
+            }
+            TitleLabels::One(Some(title)) if title != labels => {
+                *sink.subtitle.lock().unwrap() = None;
+                *self = TitleLabels::Mixed;
+            }
+            _ => {}
+        }
+        matches!(self, TitleLabels::One(_))
+    }
+}
+
+// {"streams":[{"stream":{标签},"values":[["纳秒时间戳","行"]]}]}
+fn push_streams(message: &[u8], sink: &LogSink, title: &mut TitleLabels, last: &mut Option<u128>) {
+    let Ok(value) = serde_json::from_slice::<Value>(message) else {
+        return;
+    };
+    for stream in value["streams"].as_array().into_iter().flatten() {
+        let labels = format_labels(&stream["stream"]);
+        let in_title = title.in_title(&labels, sink);
+        for entry in stream["values"].as_array().into_iter().flatten() {
+            if let Some(ns) = entry[0].as_str().and_then(|ns| ns.parse::<u128>().ok()) {
+                *last = Some(last.map_or(ns, |last| last.max(ns)));
+            }
+            let line = entry[1].as_str().unwrap_or_default().trim_end_matches(['\r', '\n']);
+            sink.push_line(if in_title {
+                format!("{}\n", line)
+            } else {
+                format!("{} {}\n", labels, line)
+            });
+        }
+    }
+}
+
+pub fn tail(log: &config::LogConfig, sink: LogSink, requests: Receiver<SourceRequest>) {
+    let query = log.query.clone().unwrap_or_else(|| "{job=~\".+\"}".to_string());
+    let mut last = None;
+    let mut title = if log.labels.as_deref() == Some("title") {
+        TitleLabels::One(None)
+    } else {
+        TitleLabels::Off
+    };
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        let started = Instant::now();
+        match connect(log, &query, last) {
+            Ok(connection) => {
+                sink.set_status(ConnectionStatus::Connected);
+                let (message_sender, messages) = mpsc::channel();
+                thread::spawn(move || read_messages(connection, message_sender));
+                loop {
+                    match messages.recv_timeout(POLL_INTERVAL) {
+                        Ok(message) => {
+                            sink.stats.lock().unwrap().bytes_received += message.len() as u64;
+                            push_streams(&message, &sink, &mut title, &mut last);
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                    while let Ok(request) = requests.try_recv() {
+                        handle_request(request, &sink);
+                    }
+                }
+                sink.set_status(ConnectionStatus::Error(format!(
+                    "{} ({})",
+                    tr(Msg::Disconnected),
+                    log.url.as_deref().unwrap_or_default()
+                )));
+            }
+            Err(e) => sink.set_status(ConnectionStatus::Error(e)),
+        }
+
+        if started.elapsed() > Duration::from_secs(60) {
+            backoff = Duration::from_secs(1);
+        }
+        idle(backoff, &sink, &requests);
+        backoff = (backoff * 2).min(Duration::from_secs(30));
+    }
+}
@@ -1,60 +1,407 @@
+mod archive;
+mod auth;
+mod azure;
+mod check;
+mod cli;
+mod cloudwatch;
+mod codec;
+mod command;
+mod compare;
 mod config;
+mod dedup;
+mod dryrun;
+mod elasticsearch;
+mod fields;
+mod fluent;
+mod forward;
+mod gcp;
+mod gelf;
+mod grok;
+mod guard;
+mod headless;
+mod history;
+mod i18n;
+mod inflate;
+mod ipc;
+mod layout;
+mod link;
+mod lnav;
+mod local;
+mod loki;
+mod msgpack;
+mod otlp;
+mod podman;
+mod preflight;
+mod reassemble;
+mod redis;
+mod replay;
+mod serial;
+mod silence;
 mod ssh;
+mod sse;
+mod suggest;
+mod syslog;
+mod timestamp;
+mod transform;
 mod ui;
+mod winevent;
 
-use std::io;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{self, IsTerminal};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::atomic::AtomicUsize;
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Instant;
 
-use ssh::{connect_and_tail, ConnectionStatus};
-use ui::{create_log_formatter, AppState, LogWindow, run_ui};
+use ssh::{tail_with_retry, ConnectionStats, ConnectionStatus, LogSink, SourceRequest};
+use ui::{create_log_formatter, AppState, LogWindow, run_ui, WrapOptions};
 
-fn log_window(log_config: config::LogConfig) -> LogWindow {
+// 创建窗口和写入端共享的状态, 由调用方决定用哪种数据源写入 sink
+fn log_window(log_config: &config::LogConfig) -> (LogWindow, LogSink, Receiver<SourceRequest>) {
     let content = Arc::new(Mutex::new(Vec::new()));
-    let formatter = Arc::new(create_log_formatter());
-    let max_history = log_config.max_history.unwrap_or(10000);
+    let highlights = log_config.highlights.as_deref().unwrap_or_default();
+    let formatter = Arc::new(create_log_formatter(highlights));
+    let history_limit = log_config.max_history.unwrap_or(10000);
+    let max_history = Arc::new(AtomicUsize::new(history_limit));
     let scroll_position = Arc::new(Mutex::new(0));
     let connection_status = Arc::new(Mutex::new(ConnectionStatus::Connected));
+    let stats = Arc::new(Mutex::new(ConnectionStats::new()));
+    let (requests, request_receiver) = mpsc::channel();
+    let paused_at = Arc::new(Mutex::new(None));
+    let download = Arc::new(Mutex::new(None));
+    let subtitle = Arc::new(Mutex::new(None));
+    let positions = Arc::new(Mutex::new(VecDeque::new()));
+    let timestamps = Arc::new(timestamp::Detector::new(log_config.timestamp_format));
+    let recent = log_config
+        .highlight_new
+        .as_deref()
+        .and_then(timestamp::parse_duration)
+        .map(|window| Arc::new(ssh::Recent::new(window)));
+    let dropped = Arc::new(Mutex::new(Vec::new()));
+    let archive = log_config
+        .archive
+        .as_ref()
+        .map(|config| Arc::new(archive::Archive::new(archive::open_store(config), &log_config.name)));
 
     let log_window = LogWindow {
         name: log_config.name.clone(),
+        host: log_config.host.clone(),
         content: Arc::clone(&content),
         formatter: Arc::clone(&formatter),
-        scroll_position: Arc::clone(&scroll_position),
+        scroll_position,
+        view_anchor: Cell::new(None),
         connection_status: Arc::clone(&connection_status),
+        stats: Arc::clone(&stats),
+        wrap_options: WrapOptions {
+            marker: log_config.wrap_marker.clone().unwrap_or_else(|| "↪ ".to_string()),
+            hanging_indent: log_config.hanging_indent.unwrap_or(0),
+            words: log_config.word_wrap.unwrap_or(false),
+        },
+        requests,
+        browse_dir: log_config
+            .browse_dir
+            .clone()
+            .unwrap_or_else(|| log_config.log_path.parent_dir()),
+        paused_at: Arc::clone(&paused_at),
+        source_paths: log_config.log_path.shell_args(),
+        download: Arc::clone(&download),
+        archive: archive.clone(),
+        max_history: Arc::clone(&max_history),
+        history_limit,
+        json_filter: Vec::new(),
+        filters: Vec::new(),
+        marks: Vec::new(),
+        follow: true,
+        wrap: true,
+        h_scroll: 0,
+        follow_errors: false,
+        frozen: None,
+        severity: Default::default(),
+        dropped: log_config.drop_excluded.unwrap_or(false).then(|| Arc::clone(&dropped)),
+        filter_context: log_config.context_lines.unwrap_or(0),
+        sample: log_config.sample.unwrap_or(1).max(1),
+        subtitle: Arc::clone(&subtitle),
+        positions: Arc::clone(&positions),
+        divergence: Arc::new(Mutex::new(None)),
+        timestamps: Arc::clone(&timestamps),
+        idle_disconnect: log_config
+            .idle_disconnect
+            .as_deref()
+            .and_then(timestamp::parse_duration),
+        recent: recent.clone(),
+        last_viewed: Instant::now(),
+        suspended: false,
     };
 
-    let is_maximized = Arc::new(Mutex::new(false));
-    thread::spawn(move || {
-        connect_and_tail(
-            &log_config,
-            content,
-            max_history,
-            scroll_position,
-            is_maximized,
-            connection_status,
-        )
-    });
+    let sink = LogSink {
+        name: log_config.name.clone(),
+        host: log_config.host.clone(),
+        content,
+        max_history,
+        connection_status,
+        stats,
+        auto_pause: log_config.auto_pause_on_error.unwrap_or(false),
+        paused_at,
+        download,
+        archive,
+        encoding: log_config
+            .encoding
+            .as_deref()
+            .and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes()))
+            .unwrap_or(encoding_rs::UTF_8),
+        // 配置在读取时已经校验过
+        alerts: highlights
+            .iter()
+            .filter(|rule| rule.alert == Some(true))
+            .filter_map(|rule| regex::Regex::new(&rule.pattern).ok())
+            .collect(),
+        transforms: transform::Pipeline::new(log_config.transforms.as_deref().unwrap_or_default())
+            .unwrap_or_default(),
+        retention: log_config
+            .max_age
+            .as_deref()
+            .and_then(timestamp::parse_duration)
+            .map(ssh::Retention::new),
+        recent,
+        dropped,
+        reassembly: log_config
+            .json_multiline
+            .unwrap_or(false)
+            .then(|| Mutex::new(reassemble::JsonAssembler::default())),
+        subtitle,
+        positions,
+        timestamps,
+    };
+
+    (log_window, sink, request_receiver)
+}
 
+// 数据源在后台线程里写入 sink, 窗口留给 UI
+fn spawn_window(
+    log_config: config::LogConfig,
+    tail: fn(&config::LogConfig, LogSink, Receiver<SourceRequest>),
+) -> LogWindow {
+    let (log_window, sink, request_receiver) = log_window(&log_config);
+    thread::spawn(move || tail(&log_config, sink, request_receiver));
     log_window
 }
 
-fn main() -> io::Result<()> {
-    // TODO: File Err Handle
-    // TODO: Input File Path
-    let config = config::read_config("~/.rogger/config.toml").expect("File Not Found Err: ~/.rogger/config.toml");
-    
-    let log_windows: Vec<LogWindow> = config.logs
+fn stdin_window(name: &str) -> LogWindow {
+    let log_config = config::LogConfig {
+        name: name.to_string(),
+        ..Default::default()
+    };
+    let (log_window, sink, request_receiver) = log_window(&log_config);
+    thread::spawn(move || command::read_stdin(sink, request_receiver));
+    log_window
+}
+
+fn compose_windows(log_config: config::LogConfig) -> Vec<LogWindow> {
+    match command::compose_services(&log_config) {
+        Ok(services) => services
+            .into_iter()
+            .map(|service| spawn_window(service, command::run_command))
+            .collect(),
+        // 查询失败时保留一个窗口显示错误
+        Err(e) => {
+            let (log_window, sink, _) = log_window(&log_config);
+            sink.set_status(ConnectionStatus::Error(e));
+            vec![log_window]
+        }
+    }
+}
+
+fn source_windows(log_config: config::LogConfig) -> Vec<LogWindow> {
+    let tail: fn(&config::LogConfig, LogSink, Receiver<SourceRequest>) = match log_config.source {
+        config::SourceType::Ssh | config::SourceType::Journal => tail_with_retry,
+        config::SourceType::Local => local::tail_local,
+        config::SourceType::Command => command::run_command,
+        config::SourceType::Compose => return compose_windows(log_config),
+        config::SourceType::Syslog => syslog::listen,
+        config::SourceType::Sse => sse::stream,
+        config::SourceType::Cloudwatch => cloudwatch::tail,
+        config::SourceType::Gcp => gcp::tail,
+        config::SourceType::Loki => loki::tail,
+        config::SourceType::Elasticsearch => elasticsearch::tail,
+        config::SourceType::Redis => redis::tail,
+        config::SourceType::Serial => serial::tail,
+        config::SourceType::Gelf => gelf::listen,
+        config::SourceType::Forward => fluent::listen,
+        config::SourceType::Podman if !log_config.host.is_empty() => tail_with_retry,
+        config::SourceType::Podman => podman::tail,
+        config::SourceType::Azure => azure::tail,
+        config::SourceType::Replay => replay::tail,
+        config::SourceType::Winevent => winevent::tail,
+    };
+    vec![spawn_window(log_config, tail)]
+}
+
+fn replay_windows(files: &[String], speed: f64) -> io::Result<Vec<LogWindow>> {
+    let recordings = replay::load_recordings(files)?;
+    let clock = Arc::new(replay::ReplayClock::new(&recordings, speed));
+
+    Ok(recordings
         .into_iter()
-        .map(log_window)
+        .map(|recording| {
+            let log_config = config::LogConfig {
+                name: recording.name.clone(),
+                log_path: config::LogPath::Single(recording.path.clone()),
+                ..Default::default()
+            };
+            let (log_window, sink, _) = log_window(&log_config);
+            let clock = Arc::clone(&clock);
+            thread::spawn(move || replay::replay(recording, sink, &clock));
+            log_window
+        })
+        .collect())
+}
+
+fn main() -> io::Result<()> {
+    let args = cli::parse_args().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    if args.check {
+        if !check::run(&args.config_path) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // 回放和 --stdin 模式下配置文件可选
+    let mut config = if args.replay.is_empty() && !args.stdin {
+        config::read_config(&args.config_path)
+            .unwrap_or_else(|e| panic!("Config Err: {}: {}", args.config_path, e))
+    } else {
+        config::read_config(&args.config_path).unwrap_or_default()
+    };
+
+    i18n::init(config.global.as_ref().and_then(|g| g.locale.as_deref()));
+    if let Some(otlp) = config.global.as_ref().and_then(|g| g.otlp.as_ref()) {
+        otlp::init(otlp).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    }
+
+    silence::init(config.silences.as_deref().unwrap_or_default());
+
+    if args.dry_run {
+        dryrun::run(config.logs);
+        return Ok(());
+    }
+
+    if args.preflight && args.replay.is_empty() {
+        match preflight::run(std::mem::take(&mut config.logs)) {
+            Some(logs) => config.logs = logs,
+            None => return Ok(()),
+        }
+    }
+
+    if !io::stdout().is_terminal() {
+        headless::init();
+    }
+
+    let pairs: Vec<(String, String)> = config
+        .logs
+        .iter()
+        .filter_map(|log| Some((log.name.clone(), log.compare_with.clone()?)))
         .collect();
+    let mut log_windows: Vec<LogWindow> = if args.replay.is_empty() {
+        config.logs.into_iter().flat_map(source_windows).collect()
+    } else {
+        replay_windows(&args.replay, args.speed)?
+    };
+    if args.stdin {
+        log_windows.push(stdin_window(&args.name));
+    }
+    if config.global.as_ref().and_then(|g| g.auto_wrapping) == Some(false) {
+        for window in log_windows.iter_mut() {
+            window.wrap = false;
+        }
+    }
+    compare::spawn(&pairs, &log_windows);
+    if log_windows.len() > 1 {
+        dedup::enable();
+    }
+    if let Some(max_rss_mb) = config.global.as_ref().and_then(|g| g.max_rss_mb) {
+        guard::spawn(max_rss_mb, &log_windows);
+    }
 
+    if headless::enabled() {
+        return headless::run(log_windows);
+    }
+
+    // 没有 HOME 又没有配置路径时不提供 rogger-ctl
+    let socket = ipc::socket_path(config.global.as_ref().and_then(|g| g.control_socket.as_deref())).ok();
+    let (control, control_err) = match socket.clone().map(ipc::listen) {
+        Some(Ok(requests)) => (Some(requests), None),
+        Some(Err(e)) => (None, Some(e)),
+        None => (None, None),
+    };
+    let save_search_history = config
+        .global
+        .as_ref()
+        .and_then(|global| global.save_search_history)
+        .unwrap_or(false);
+    let layouts = layout::load_layouts(config.layouts.as_ref());
+    let visible: Vec<usize> = (0..log_windows.len()).collect();
     let mut app_state = AppState {
+        weights: vec![1; visible.len()],
+        visible,
+        group_by_host: config
+            .global
+            .as_ref()
+            .and_then(|global| global.group_by_host)
+            .unwrap_or(false),
+        collapsed: HashSet::new(),
+        layouts,
+        layout_label: None,
+        rule_popup: None,
+        field_popup: None,
+        shared_popup: None,
+        saved_searches: config.searches.clone().unwrap_or_default().into_iter().collect(),
+        searches_popup: None,
+        macros: HashMap::new(),
+        recording: None,
+        macro_pending: None,
+        config_path: args.config_path.clone(),
+        filter_presets: config.filters.clone().unwrap_or_default(),
         log_windows,
         selected_window: 0,
         is_maximized: false,
-        has_scrolled: false,
+        show_inspector: false,
+        mouse_captured: true,
+        show_invisibles: false,
+        hyperlinks: config
+            .global
+            .as_ref()
+            .and_then(|global| global.hyperlinks)
+            .unwrap_or_else(ui::supports_hyperlinks),
+        file_picker: None,
+        prompt: None,
+        grep: None,
+        search: None,
+        search_options: Default::default(),
+        global_search: None,
+        control,
+        search_history: if save_search_history { history::load() } else { Vec::new() },
+        save_search_history,
+        jump_to_line: Cell::new(None),
+        view_top: Cell::new(None),
+        notice: None,
+        time_cursor: None,
     };
 
-    run_ui(&mut app_state)
+    if let Some(name) = &args.layout {
+        ui::apply_layout(&mut app_state, name)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    }
+
+    if let Some(e) = control_err {
+        app_state.notice = Some((format!("{}: {}", i18n::tr(i18n::Msg::ControlErr), e), Instant::now()));
+    }
+
+    let result = run_ui(&mut app_state);
+    if let (Some(_), Some(path)) = (&app_state.control, socket) {
+        let _ = std::fs::remove_file(path);
+    }
+    result
 }
\ No newline at end of file
@@ -0,0 +1,225 @@
+use std::io::{self, Read};
+
+use serde_json::{Map, Number, Value};
+
+// 只实现 Fluentd forward 协议需要的 MessagePack 编解码
+#[derive(Debug)]
+pub enum Pack {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bin(Vec<u8>),
+    Array(Vec<Pack>),
+    Map(Vec<(Pack, Pack)>),
+    Ext(i8, Vec<u8>),
+}
+
+const MAX_LENGTH: usize = 64 * 1024 * 1024;
+const MAX_DEPTH: usize = 64;
+
+impl Pack {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Pack::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    // forward 协议里 str 和 bin 都可能用来传字节串
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Pack::Str(s) => Some(s.as_bytes()),
+            Pack::Bin(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Pack]> {
+        match self {
+            Pack::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Pack> {
+        match self {
+            Pack::Map(entries) => entries
+                .iter()
+                .find(|(k, _)| k.as_bytes() == Some(key.as_bytes()))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        match self {
+            Pack::Nil => Value::Null,
+            Pack::Bool(b) => Value::Bool(*b),
+            Pack::Int(n) => Value::from(*n),
+            Pack::Float(f) => Number::from_f64(*f).map_or(Value::Null, Value::Number),
+            Pack::Str(s) => Value::String(s.clone()),
+            Pack::Bin(b) | Pack::Ext(_, b) => Value::String(String::from_utf8_lossy(b).into_owned()),
+            Pack::Array(items) => Value::Array(items.iter().map(Pack::to_json).collect()),
+            Pack::Map(entries) => Value::Object(
+                entries
+                    .iter()
+                    .map(|(k, v)| {
+                        let key = match k.as_bytes() {
+                            Some(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+                            None => k.to_json().to_string(),
+                        };
+                        (key, v.to_json())
+                    })
+                    .collect::<Map<String, Value>>(),
+            ),
+        }
+    }
+}
+
+fn read_bytes(reader: &mut impl Read, length: usize) -> io::Result<Vec<u8>> {
+    if length > MAX_LENGTH {
+        return Err(io::Error::other("msgpack value too large"));
+    }
+    let mut bytes = vec![0u8; length];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_uint(reader: &mut impl Read, size: usize) -> io::Result<u64> {
+    let bytes = read_bytes(reader, size)?;
+    Ok(bytes.iter().fold(0u64, |n, &b| n << 8 | b as u64))
+}
+
+fn read_str(reader: &mut impl Read, length: usize) -> io::Result<Pack> {
+    let bytes = read_bytes(reader, length)?;
+    Ok(Pack::Str(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+fn read_array(reader: &mut impl Read, length: usize, depth: usize) -> io::Result<Pack> {
+    (0..length)
+        .map(|_| decode_nested(reader, depth + 1))
+        .collect::<io::Result<Vec<_>>>()
+        .map(Pack::Array)
+}
+
+fn read_map(reader: &mut impl Read, length: usize, depth: usize) -> io::Result<Pack> {
+    (0..length)
+        .map(|_| Ok((decode_nested(reader, depth + 1)?, decode_nested(reader, depth + 1)?)))
+        .collect::<io::Result<Vec<_>>>()
+        .map(Pack::Map)
+}
+
+fn read_ext(reader: &mut impl Read, length: usize) -> io::Result<Pack> {
+    let kind = read_uint(reader, 1)? as i8;
+    Ok(Pack::Ext(kind, read_bytes(reader, length)?))
+}
+
+pub fn decode(reader: &mut impl Read) -> io::Result<Pack> {
+    decode_nested(reader, 0)
+}
+
+// 数据来自任意客户端, 限制嵌套层数, 避免 0x91 0x91 ... 这样的输入把线程栈撑爆
+fn decode_nested(reader: &mut impl Read, depth: usize) -> io::Result<Pack> {
+    if depth > MAX_DEPTH {
+        return Err(io::Error::other("msgpack nested too deeply"));
+    }
+    let mut marker = [0u8; 1];
+    reader.read_exact(&mut marker)?;
+    let marker = marker[0];
+    match marker {
+        0x00..=0x7f => Ok(Pack::Int(marker as i64)),
+        0x80..=0x8f => read_map(reader, (marker & 0x0f) as usize, depth),
+        0x90..=0x9f => read_array(reader, (marker & 0x0f) as usize, depth),
+        0xa0..=0xbf => read_str(reader, (marker & 0x1f) as usize),
+        0xc0 => Ok(Pack::Nil),
+        0xc2 => Ok(Pack::Bool(false)),
+        0xc3 => Ok(Pack::Bool(true)),
+        0xc4..=0xc6 => {
+            let length = read_uint(reader, 1 << (marker - 0xc4))? as usize;
+            read_bytes(reader, length).map(Pack::Bin)
+        }
+        0xc7..=0xc9 => {
+            let length = read_uint(reader, 1 << (marker - 0xc7))? as usize;
+            read_ext(reader, length)
+        }
+        0xca => Ok(Pack::Float(f32::from_bits(read_uint(reader, 4)? as u32) as f64)),
+        0xcb => Ok(Pack::Float(f64::from_bits(read_uint(reader, 8)?))),
+        0xcc..=0xcf => Ok(Pack::Int(read_uint(reader, 1 << (marker - 0xcc))? as i64)),
+        0xd0 => Ok(Pack::Int(read_uint(reader, 1)? as i8 as i64)),
+        0xd1 => Ok(Pack::Int(read_uint(reader, 2)? as i16 as i64)),
+        0xd2 => Ok(Pack::Int(read_uint(reader, 4)? as i32 as i64)),
+        0xd3 => Ok(Pack::Int(read_uint(reader, 8)? as i64)),
+        0xd4..=0xd8 => read_ext(reader, 1 << (marker - 0xd4)),
+        0xd9..=0xdb => {
+            let length = read_uint(reader, 1 << (marker - 0xd9))? as usize;
+            read_str(reader, length)
+        }
+        0xdc | 0xdd => {
+            let length = read_uint(reader, 2 << (marker - 0xdc))? as usize;
+            read_array(reader, length, depth)
+        }
+        0xde | 0xdf => {
+            let length = read_uint(reader, 2 << (marker - 0xde))? as usize;
+            read_map(reader, length, depth)
+        }
+        0xe0..=0xff => Ok(Pack::Int(marker as i8 as i64)),
+        _ => Err(io::Error::other(format!("invalid msgpack marker 0x{:02x}", marker))),
+    }
+}
+
+fn encode_length(out: &mut Vec<u8>, length: usize, fix: Option<(u8, usize)>, markers: [u8; 3]) {
+    match (fix, length) {
+        (Some((base, max)), n) if n <= max => out.push(base | n as u8),
+        (_, n) if n <= u8::MAX as usize && markers[0] != 0 => out.extend([markers[0], n as u8]),
+        (_, n) if n <= u16::MAX as usize => {
+            out.push(markers[1]);
+            out.extend_from_slice(&(n as u16).to_be_bytes());
+        }
+        (_, n) => {
+            out.push(markers[2]);
+            out.extend_from_slice(&(n as u32).to_be_bytes());
+        }
+    }
+}
+
+pub fn encode(pack: &Pack, out: &mut Vec<u8>) {
+    match pack {
+        Pack::Nil => out.push(0xc0),
+        Pack::Bool(b) => out.push(if *b { 0xc3 } else { 0xc2 }),
+        Pack::Int(n) if (0..=0x7f).contains(n) => out.push(*n as u8),
+        Pack::Int(n) => {
+            out.push(0xd3);
+            out.extend_from_slice(&n.to_be_bytes());
+        }
+        Pack::Float(f) => {
+            out.push(0xcb);
+            out.extend_from_slice(&f.to_bits().to_be_bytes());
+        }
+        Pack::Str(s) => {
+            encode_length(out, s.len(), Some((0xa0, 31)), [0xd9, 0xda, 0xdb]);
+            out.extend_from_slice(s.as_bytes());
+        }
+        Pack::Bin(b) => {
+            encode_length(out, b.len(), None, [0xc4, 0xc5, 0xc6]);
+            out.extend_from_slice(b);
+        }
+        Pack::Array(items) => {
+            encode_length(out, items.len(), Some((0x90, 15)), [0, 0xdc, 0xdd]);
+            items.iter().for_each(|item| encode(item, out));
+        }
+        Pack::Map(entries) => {
+            encode_length(out, entries.len(), Some((0x80, 15)), [0, 0xde, 0xdf]);
+            for (key, value) in entries {
+                encode(key, out);
+                encode(value, out);
+            }
+        }
+        Pack::Ext(kind, data) => {
+            encode_length(out, data.len(), None, [0xc7, 0xc8, 0xc9]);
+            out.push(*kind as u8);
+            out.extend_from_slice(data);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::{Lazy, OnceCell};
+use regex::Regex;
+
+use crate::config::OtlpConfig;
+
+static EXPORTER: OnceCell<SyncSender<Record>> = OnceCell::new();
+
+const QUEUE_SIZE: usize = 10000;
+const BATCH_SIZE: usize = 512;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Record {
+    time_nanos: u128,
+    source: String,
+    host: String,
+    body: String,
+}
+
+struct Endpoint {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+// 只支持 http://host:port[/path], 默认路径为 OTLP/HTTP 的 /v1/logs
+fn parse_endpoint(endpoint: &str) -> Result<Endpoint, String> {
+    let rest = endpoint
+        .strip_prefix("http://")
+        .ok_or_else(|| format!("OTLP endpoint must start with http://: {}", endpoint))?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, ""),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (
+            host,
+            port.parse()
+                .map_err(|_| format!("Invalid OTLP endpoint port: {}", endpoint))?,
+        ),
+        None => (authority, 4318),
+    };
+    let path = if path.is_empty() || path == "/" {
+        "/v1/logs".to_string()
+    } else {
+        path.to_string()
+    };
+    Ok(Endpoint {
+        host: host.to_string(),
+        port,
+        path,
+    })
+}
+
+pub fn init(config: &OtlpConfig) -> Result<(), String> {
+    let endpoint = parse_endpoint(&config.endpoint)?;
+    let service = config
+        .service_name
+        .clone()
+        .unwrap_or_else(|| "rogger".to_string());
+    let (sender, records) = mpsc::sync_channel(QUEUE_SIZE);
+    thread::spawn(move || run_exporter(endpoint, service, records));
+    let _ = EXPORTER.set(sender);
+    Ok(())
+}
+
+// 未配置导出时什么也不做; 队列满时丢弃, 不阻塞 tail
+pub fn export(source: &str, host: &str, line: &str) {
+    let Some(sender) = EXPORTER.get() else {
+        return;
+    };
+    let time_nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let _ = sender.try_send(Record {
+        time_nanos,
+        source: source.to_string(),
+        host: host.to_string(),
+        body: line.trim_end_matches(['\r', '\n']).to_string(),
+    });
+}
+
+fn run_exporter(endpoint: Endpoint, service: String, records: Receiver<Record>) {
+    let mut batch = Vec::new();
+    let mut last_flush = Instant::now();
+    loop {
+        let closed = match records.recv_timeout(FLUSH_INTERVAL) {
+            Ok(record) => {
+                batch.push(record);
+                false
+            }
+            Err(RecvTimeoutError::Timeout) => false,
+            Err(RecvTimeoutError::Disconnected) => true,
+        };
+
+        if !batch.is_empty()
+            && (batch.len() >= BATCH_SIZE || last_flush.elapsed() >= FLUSH_INTERVAL || closed)
+        {
+            // 收集器不可用时丢弃这一批, 避免内存无限增长
+            let _ = post(&endpoint, &encode_batch(&service, &batch));
+            batch.clear();
+            last_flush = Instant::now();
+        }
+        if closed {
+            return;
+        }
+    }
+}
+
+fn post(endpoint: &Endpoint, body: &str) -> io::Result<()> {
+    let addr = (endpoint.host.as_str(), endpoint.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::other("OTLP endpoint did not resolve"))?;
+    let mut stream = TcpStream::connect_timeout(&addr, IO_TIMEOUT)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        endpoint.path,
+        endpoint.host,
+        endpoint.port,
+        body.len(),
+        body
+    )?;
+
+    let mut response = String::new();
+    let _ = stream.read_to_string(&mut response);
+    let status = response.split_whitespace().nth(1).unwrap_or_default();
+    if status.starts_with('2') {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("OTLP export failed: {}", status)))
+    }
+}
+
+static SEVERITY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b(TRACE|DEBUG|INFO|WARN|WARNING|ERROR|FATAL)\b").unwrap());
+
+// 按 OTLP 的 SeverityNumber 取各级别的起始值
+fn severity(line: &str) -> Option<(&'static str, u8)> {
+    let level = SEVERITY.captures(line)?.get(1)?.as_str();
+    Some(match level {
+        "TRACE" => ("TRACE", 1),
+        "DEBUG" => ("DEBUG", 5),
+        "INFO" => ("INFO", 9),
+        "WARN" | "WARNING" => ("WARN", 13),
+        "ERROR" => ("ERROR", 17),
+        _ => ("FATAL", 21),
+    })
+}
+
+fn encode_batch(service: &str, batch: &[Record]) -> String {
+    let records: Vec<String> = batch
+        .iter()
+        .map(|record| {
+            let severity = severity(&record.body)
+                .map(|(text, number)| {
+                    format!(r#""severityText":"{}","severityNumber":{},"#, text, number)
+                })
+                .unwrap_or_default();
+            format!(
+                r#"{{"timeUnixNano":"{}",{}"body":{{"stringValue":{}}},"attributes":[{},{}]}}"#,
+                record.time_nanos,
+                severity,
+                json_string(&record.body),
+                attribute("log.source", &record.source),
+                attribute("host.name", &record.host)
+            )
+        })
+        .collect();
+
+    format!(
+        r#"{{"resourceLogs":[{{"resource":{{"attributes":[{}]}},"scopeLogs":[{{"scope":{{"name":"rogger"}},"logRecords":[{}]}}]}}]}}"#,
+        attribute("service.name", service),
+        records.join(",")
+    )
+}
+
+fn attribute(key: &str, value: &str) -> String {
+    format!(
+        r#"{{"key":{},"value":{{"stringValue":{}}}}}"#,
+        json_string(key),
+        json_string(value)
+    )
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,256 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+
+use crate::codec::percent_encode;
+use crate::command::{forward_lines, handle_request, idle};
+use crate::config;
+use crate::i18n::{tr, Msg};
+use crate::ssh::{ConnectionStatus, LogSink, SourceRequest};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const API: &str = "/v4.0.0/libpod";
+
+// socket 未配置时依次尝试 rootless 和 rootful 的默认位置
+fn socket_path(log: &config::LogConfig) -> String {
+    if let Some(socket) = &log.socket {
+        return socket.trim_start_matches("unix://").to_string();
+    }
+    std::env::var("XDG_RUNTIME_DIR")
+        .map(|dir| format!("{}/podman/podman.sock", dir))
+        .ok()
+        .filter(|path| Path::new(path).exists())
+        .unwrap_or_else(|| "/run/podman/podman.sock".to_string())
+}
+
+// HTTP/1.1 的分块响应体
+struct Chunked<R> {
+    inner: R,
+    remaining: usize,
+    done: bool,
+}
+
+impl<R: BufRead> Read for Chunked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.remaining == 0 {
+            if self.done {
+                return Ok(0);
+            }
+            let mut size = String::new();
+            if self.inner.read_line(&mut size)? == 0 {
+                self.done = true;
+                continue;
+            }
+            // 上一块数据后面的空行
+            let size = size.split(';').next().unwrap_or_default().trim();
+            if size.is_empty() {
+                continue;
+            }
+            self.remaining = usize::from_str_radix(size, 16)
+                .map_err(|_| io::Error::other(format!("invalid chunk size: {}", size)))?;
+            self.done = self.remaining == 0;
+        }
+        let n = buf.len().min(self.remaining);
+        let n = self.inner.read(&mut buf[..n])?;
+        if n == 0 {
+            self.done = true;
+        }
+        self.remaining -= n;
+        Ok(n)
+    }
+}
+
+// 非 tty 容器的输出按帧复用: 1 字节流类型, 3 字节保留, 4 字节大端长度
+struct Demux<R> {
+    inner: R,
+    remaining: usize,
+}
+
+impl<R: Read> Read for Demux<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.remaining == 0 {
+            let mut header = [0u8; 8];
+            match self.inner.read_exact(&mut header) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(0),
+                Err(e) => return Err(e),
+            }
+            self.remaining = u32::from_be_bytes(header[4..].try_into().unwrap()) as usize;
+        }
+        let n = buf.len().min(self.remaining);
+        let n = self.inner.read(&mut buf[..n])?;
+        self.remaining -= n;
+        Ok(n)
+    }
+}
+
+fn request(socket: &str, path: &str) -> Result<(UnixStream, Box<dyn Read + Send>), String> {
+    let stream = UnixStream::connect(socket).map_err(|e| format!("{}: {}", socket, e))?;
+    let mut writer = stream.try_clone().map_err(|e| e.to_string())?;
+    write!(writer, "GET {} HTTP/1.1\r\nHost: d\r\nConnection: close\r\n\r\n", path)
+        .map_err(|e| e.to_string())?;
+
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    let mut status = String::new();
+    reader.read_line(&mut status).map_err(|e| e.to_string())?;
+    let code = status.split_whitespace().nth(1).unwrap_or_default().to_string();
+    let mut chunked = false;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).map_err(|e| e.to_string())? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            chunked |= name.eq_ignore_ascii_case("transfer-encoding")
+                && value.trim().eq_ignore_ascii_case("chunked");
+        }
+    }
+    let mut body: Box<dyn Read + Send> = if chunked {
+        Box::new(Chunked {
+            inner: reader,
+            remaining: 0,
+            done: false,
+        })
+    } else {
+        Box::new(reader)
+    };
+
+    if code != "200" {
+        let mut text = String::new();
+        let _ = body.read_to_string(&mut text);
+        let message = serde_json::from_str::<Value>(&text)
+            .ok()
+            .and_then(|reply| reply["message"].as_str().map(str::to_string))
+            .unwrap_or_else(|| text.trim().to_string());
+        return Err(format!("HTTP {}: {}", code, message));
+    }
+    Ok((stream, body))
+}
+
+fn is_tty(socket: &str, container: &str) -> Result<bool, String> {
+    let path = format!("{}/containers/{}/json", API, percent_encode(container));
+    let (_, mut body) = request(socket, &path)?;
+    let mut text = String::new();
+    body.read_to_string(&mut text).map_err(|e| e.to_string())?;
+    let inspect: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    Ok(inspect["Config"]["Tty"].as_bool().unwrap_or(false))
+}
+
+// --preflight: socket 可以连接且容器存在
+pub fn check(log: &config::LogConfig, container: &str) -> Result<String, String> {
+    let socket = socket_path(log);
+    is_tty(&socket, container)
+        .map(|_| socket)
+        .map_err(|e| format!("{}: {}", tr(Msg::PodmanErr), e))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// 首次连接回填最后 100 行或从 since 开始; 重连时从断开的时间继续
+fn logs_path(log: &config::LogConfig, container: &str, resume_from: Option<u64>) -> String {
+    let start = match (resume_from, &log.since) {
+        (Some(secs), _) => format!("since={}", secs),
+        (None, Some(since)) => format!("since={}", percent_encode(since)),
+        (None, None) => "tail=100".to_string(),
+    };
+    format!(
+        "{}/containers/{}/logs?follow=true&stdout=true&stderr=true&{}",
+        API,
+        percent_encode(container),
+        start
+    )
+}
+
+fn open_logs(
+    log: &config::LogConfig,
+    socket: &str,
+    container: &str,
+    resume_from: Option<u64>,
+) -> Result<(UnixStream, Box<dyn Read + Send>), String> {
+    let tty = is_tty(socket, container)?;
+    let (stream, body) = request(socket, &logs_path(log, container, resume_from))?;
+    if tty {
+        return Ok((stream, body));
+    }
+    Ok((stream, Box::new(Demux { inner: body, remaining: 0 })))
+}
+
+// 本机通过 Podman API socket 跟随容器日志; 远端 (配置了 host) 由 ssh 模块执行 podman logs -f
+pub fn tail(log: &config::LogConfig, sink: LogSink, requests: Receiver<SourceRequest>) {
+    let Some(container) = log.container.as_deref() else {
+        sink.set_status(ConnectionStatus::Error(tr(Msg::NoContainer).to_string()));
+        loop {
+            idle(Duration::from_secs(1), &sink, &requests);
+        }
+    };
+    let socket = socket_path(log);
+    let mut resume_from = None;
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        let started = Instant::now();
+        let mut refresh = false;
+        match open_logs(log, &socket, container, resume_from) {
+            Ok((stream, body)) => {
+                sink.set_status(ConnectionStatus::Connected);
+                let (line_sender, lines) = mpsc::channel();
+                forward_lines(body, line_sender);
+                loop {
+                    match lines.recv_timeout(POLL_INTERVAL) {
+                        Ok(bytes) => {
+                            sink.stats.lock().unwrap().bytes_received += bytes.len() as u64;
+                            sink.push_line(sink.decode(&bytes));
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                    while let Ok(request) = requests.try_recv() {
+                        match request {
+                            SourceRequest::Refresh => refresh = true,
+                            request => handle_request(request, &sink),
+                        }
+                    }
+                    if refresh {
+                        break;
+                    }
+                }
+                // 让读线程从阻塞的读中返回
+                let _ = stream.shutdown(Shutdown::Both);
+                resume_from = Some(now_secs());
+                if !refresh {
+                    sink.set_status(ConnectionStatus::Error(format!(
+                        "{} ({})",
+                        tr(Msg::Disconnected),
+                        container
+                    )));
+                }
+            }
+            Err(e) => sink.set_status(ConnectionStatus::Error(format!(
+                "{}: {}",
+                tr(Msg::PodmanErr),
+                e
+            ))),
+        }
+
+        if refresh {
+            resume_from = None;
+            sink.clear();
+            continue;
+        }
+        // 容器停止后 API 会立刻关闭连接, 退避避免频繁重试
+        if started.elapsed() > Duration::from_secs(60) {
+            backoff = Duration::from_secs(1);
+        }
+        idle(backoff, &sink, &requests);
+        backoff = (backoff * 2).min(Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,181 @@
+use std::io::{self, BufRead, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use crate::config::{self, SourceType};
+use crate::i18n::{tr, Msg};
+use crate::podman;
+use crate::ssh;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn in_path(program: &str) -> Result<String, String> {
+    if program.contains('/') {
+        if Path::new(program).exists() {
+            return Ok(program.to_string());
+        }
+        return Err(format!("{}: {}", tr(Msg::MissingFile), program));
+    }
+    std::env::var_os("PATH")
+        .and_then(|paths| {
+            std::env::split_paths(&paths)
+                .map(|dir| dir.join(program))
+                .find(|candidate| candidate.is_file())
+        })
+        .map(|found| found.display().to_string())
+        .ok_or_else(|| format!("{}: {}", tr(Msg::NotInPath), program))
+}
+
+fn reach(host: &str, port: u16) -> Result<String, String> {
+    let address = (host, port)
+        .to_socket_addrs()
+        .map_err(|e| e.to_string())
+        .and_then(|mut addresses| addresses.next().ok_or_else(|| host.to_string()))
+        .map_err(|e| format!("{}: {}", tr(Msg::DnsErr), e))?;
+    TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)
+        .map_err(|e| format!("{}: {}", tr(Msg::ConnectErr), e))?;
+    Ok(address.to_string())
+}
+
+// 只检查 host 和端口能否连通, 不发请求
+fn reach_url(url: Option<&str>) -> Result<String, String> {
+    let url = url.ok_or_else(|| tr(Msg::NoUrl).to_string())?;
+    let (rest, default_port) = match url.split_once("://") {
+        Some(("https", rest)) => (rest, 443),
+        Some((_, rest)) => (rest, 80),
+        None => (url, 80),
+    };
+    let authority = rest.split(['/', '?']).next().unwrap_or_default();
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (
+            host,
+            port.parse()
+                .map_err(|_| format!("Invalid url port: {}", url))?,
+        ),
+        None => (authority, default_port),
+    };
+    reach(host, port)
+}
+
+fn local_files(log_path: &config::LogPath) -> Result<String, String> {
+    let mut missing = Vec::new();
+    for path in log_path.paths() {
+        let is_glob = path.contains(['*', '?', '[']);
+        let target = match (is_glob, path.rsplit_once('/')) {
+            (true, Some((dir, _))) => if dir.is_empty() { "/" } else { dir }.to_string(),
+            (true, None) => ".".to_string(),
+            (false, _) => path.to_string(),
+        };
+        // 通配符只检查所在目录, 文件可能稍后才出现
+        let readable = if is_glob {
+            Path::new(&target).is_dir()
+        } else {
+            std::fs::File::open(&target).is_ok()
+        };
+        if !readable {
+            missing.push(target);
+        }
+    }
+    if !missing.is_empty() {
+        return Err(format!("{}: {}", tr(Msg::MissingFile), missing.join(", ")));
+    }
+    Ok(log_path.paths().join(", "))
+}
+
+fn bind(address: &str, udp: bool) -> Result<String, String> {
+    let bound = if udp {
+        UdpSocket::bind(address).map(drop)
+    } else {
+        TcpListener::bind(address).map(drop)
+    };
+    bound
+        .map(|_| address.to_string())
+        .map_err(|e| format!("{} ({}): {}", tr(Msg::ListenErr), address, e))
+}
+
+fn listen_address(log: &config::LogConfig, default: &str) -> String {
+    log.listen.clone().unwrap_or_else(|| default.to_string())
+}
+
+fn check(log: &config::LogConfig) -> Result<String, String> {
+    match log.source {
+        SourceType::Ssh | SourceType::Journal => ssh::check(log),
+        SourceType::Local | SourceType::Replay => local_files(&log.log_path),
+        SourceType::Command => match log.command.as_deref() {
+            Some([program, ..]) => in_path(program),
+            _ => Err(tr(Msg::NoCommand).to_string()),
+        },
+        SourceType::Compose => in_path("docker"),
+        SourceType::Cloudwatch => in_path("aws"),
+        SourceType::Gcp => in_path("gcloud"),
+        SourceType::Azure => in_path("az"),
+        SourceType::Winevent => in_path("pwsh"),
+        SourceType::Sse | SourceType::Loki | SourceType::Elasticsearch => reach_url(log.url.as_deref()),
+        SourceType::Redis => reach(&log.host, if log.port == 0 { 6379 } else { log.port }),
+        SourceType::Syslog => {
+            let address = listen_address(log, "0.0.0.0:5514");
+            match log.protocol.as_deref().unwrap_or("udp") {
+                "udp" => bind(&address, true),
+                "tcp" => bind(&address, false),
+                _ => bind(&address, true).and_then(|_| bind(&address, false)),
+            }
+        }
+        SourceType::Gelf => bind(&listen_address(log, "0.0.0.0:12201"), true),
+        SourceType::Forward => bind(&listen_address(log, "0.0.0.0:24224"), false),
+        SourceType::Podman if !log.host.is_empty() => ssh::check(log),
+        SourceType::Podman => match log.container.as_deref() {
+            Some(container) => podman::check(log, container),
+            None => Err(tr(Msg::NoContainer).to_string()),
+        },
+        SourceType::Serial => match log.device.as_deref() {
+            Some(device) if Path::new(device).exists() => Ok(device.to_string()),
+            Some(device) => Err(format!("{}: {}", tr(Msg::MissingFile), device)),
+            None => Err(tr(Msg::NoDevice).to_string()),
+        },
+    }
+}
+
+// --preflight: 并发检查所有数据源, 打印结果后询问是否只打开可用的窗口
+// 返回 None 表示用户选择退出
+pub fn run(logs: Vec<config::LogConfig>) -> Option<Vec<config::LogConfig>> {
+    let results: Vec<Result<String, String>> = thread::scope(|scope| {
+        let handles: Vec<_> = logs.iter().map(|log| scope.spawn(move || check(log))).collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or_else(|_| Err("panic".to_string())))
+            .collect()
+    });
+
+    let name_width = logs.iter().map(|log| log.name.chars().count()).max().unwrap_or(0);
+    for (log, result) in logs.iter().zip(&results) {
+        let (mark, detail) = match result {
+            Ok(detail) => ("\x1b[32m✔\x1b[0m", detail),
+            Err(reason) => ("\x1b[31m✘\x1b[0m", reason),
+        };
+        let source = format!("{:?}", log.source).to_lowercase();
+        println!("{} {:<name_width$}  {:<13} {}", mark, log.name, source, detail);
+    }
+
+    let ok = results.iter().filter(|result| result.is_ok()).count();
+    if ok == logs.len() {
+        println!("{}", tr(Msg::PreflightOk));
+        return Some(logs);
+    }
+    print!("{} {}/{} [Y/n] ", tr(Msg::PreflightContinue), ok, logs.len());
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    let _ = io::stdin().lock().read_line(&mut answer);
+    if answer.trim().eq_ignore_ascii_case("n") || ok == 0 {
+        return None;
+    }
+    Some(
+        logs.into_iter()
+            .zip(results)
+            .filter(|(_, result)| result.is_ok())
+            .map(|(log, _)| log)
+            .collect(),
+    )
+}
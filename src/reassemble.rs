@@ -0,0 +1,93 @@
+use crate::ssh::Position;
+
+// 一直配不平括号时放弃合并, 避免吞掉后面所有的行
+const MAX_LINES: usize = 500;
+
+// 缩进输出的多行 JSON: 以 { 结尾的行开始收集, 括号配平后压缩成一行
+#[derive(Default)]
+pub struct JsonAssembler {
+    lines: Vec<String>,
+    position: Option<Position>,
+    depth: usize,
+}
+
+impl JsonAssembler {
+    // 返回可以存入缓冲区的行, 记录还没结束时为空
+    pub fn feed(&mut self, line: String, position: Option<Position>) -> Vec<(String, Option<Position>)> {
+        if self.lines.is_empty() {
+            if !line.trim_end().ends_with(['{', '[']) {
+                return vec![(line, position)];
+            }
+            self.position = position;
+        }
+        self.depth = depth_after(&line, self.depth);
+        self.lines.push(line);
+
+        if self.depth == 0 {
+            let lines = std::mem::take(&mut self.lines);
+            return vec![(compact(&lines), self.position.take())];
+        }
+        if self.lines.len() >= MAX_LINES {
+            return self.flush();
+        }
+        Vec::new()
+    }
+
+    // 原样放出收集到一半的行
+    pub fn flush(&mut self) -> Vec<(String, Option<Position>)> {
+        self.depth = 0;
+        let position = self.position.take();
+        self.lines
+            .drain(..)
+            .enumerate()
+            .map(|(i, line)| (line, if i == 0 { position.clone() } else { None }))
+            .collect()
+    }
+}
+
+// 不计字符串里的括号; 字符串不会跨行, 每行重新开始
+fn depth_after(line: &str, mut depth: usize) -> usize {
+    let (mut in_string, mut escaped) = (false, false);
+    for c in line.chars() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    depth
+}
+
+// 第一个括号之前的前缀(时间戳、级别等)保留, 之后去掉字符串外的空白, 字段顺序不变
+fn compact(lines: &[String]) -> String {
+    let joined = lines.join("\n");
+    let start = joined.find(['{', '[']).unwrap_or(0);
+    let mut record = joined[..start].to_string();
+    let (mut in_string, mut escaped) = (false, false);
+    for c in joined[start..].chars() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c.is_whitespace() {
+            continue;
+        }
+        record.push(c);
+    }
+    record
+}
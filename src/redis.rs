@@ -0,0 +1,281 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{Map, Value};
+
+use crate::command::{handle_request, idle};
+use crate::config;
+use crate::i18n::{tr, Msg};
+use crate::link::{self, Connection};
+use crate::ssh::{ConnectionStatus, LogSink, SourceRequest};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_PORT: u16 = 6379;
+// XREAD 每次最多阻塞这么久, 订阅模式下空闲这么久发一次 PING
+const BLOCK_MS: u64 = 5000;
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+const MAX_SILENCE: Duration = Duration::from_secs(60);
+const MAX_BULK: i64 = 64 * 1024 * 1024;
+
+enum Reply {
+    Status(String),
+    Error(String),
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+    Array(Option<Vec<Reply>>),
+}
+
+impl Reply {
+    fn text(&self) -> Option<String> {
+        match self {
+            Reply::Status(s) => Some(s.clone()),
+            Reply::Bulk(Some(bytes)) => Some(String::from_utf8_lossy(bytes).into_owned()),
+            Reply::Integer(n) => Some(n.to_string()),
+            _ => None,
+        }
+    }
+
+    fn items(&self) -> &[Reply] {
+        match self {
+            Reply::Array(Some(items)) => items,
+            _ => &[],
+        }
+    }
+}
+
+fn read_reply(reader: &mut impl BufRead) -> io::Result<Reply> {
+    let mut header = Vec::new();
+    if reader.read_until(b'\n', &mut header)? == 0 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, tr(Msg::Disconnected)));
+    }
+    let line = String::from_utf8_lossy(&header);
+    let line = line.trim_end_matches(['\r', '\n']);
+    let (kind, rest) = line.split_at(line.len().min(1));
+    let number = || {
+        rest.parse::<i64>()
+            .map_err(|_| io::Error::other(format!("invalid RESP header: {}", line)))
+    };
+    match kind {
+        "+" => Ok(Reply::Status(rest.to_string())),
+        "-" => Ok(Reply::Error(rest.to_string())),
+        ":" => Ok(Reply::Integer(number()?)),
+        "$" => match number()? {
+            n if n < 0 => Ok(Reply::Bulk(None)),
+            n if n > MAX_BULK => Err(io::Error::other("RESP bulk string too large")),
+            n => {
+                // 内容后面还有 \r\n
+                let mut bytes = vec![0u8; n as usize + 2];
+                reader.read_exact(&mut bytes)?;
+                bytes.truncate(n as usize);
+                Ok(Reply::Bulk(Some(bytes)))
+            }
+        },
+        "*" => match number()? {
+            n if n < 0 => Ok(Reply::Array(None)),
+            n => (0..n)
+                .map(|_| read_reply(reader))
+                .collect::<io::Result<Vec<_>>>()
+                .map(|items| Reply::Array(Some(items))),
+        },
+        _ => Err(io::Error::other(format!("invalid RESP header: {}", line))),
+    }
+}
+
+fn write_command(writer: &mut impl Write, args: &[&str]) -> io::Result<()> {
+    let mut command = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        command.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        command.extend_from_slice(arg.as_bytes());
+        command.extend_from_slice(b"\r\n");
+    }
+    writer.write_all(&command)?;
+    writer.flush()
+}
+
+fn open_link(log: &config::LogConfig) -> Result<Connection, String> {
+    let port = if log.port == 0 { DEFAULT_PORT } else { log.port };
+    link::open(&log.host, port, log.tls.unwrap_or(false), log.ca_file.as_deref(), CONNECT_TIMEOUT)
+}
+
+// 握手阶段同步收发, 命令出错时直接把 Redis 的错误信息显示出来
+fn call(connection: &mut Connection, args: &[&str]) -> Result<Reply, String> {
+    write_command(&mut connection.writer, args).map_err(|e| e.to_string())?;
+    match read_reply(&mut connection.reader).map_err(|e| e.to_string())? {
+        Reply::Error(e) => Err(format!("{}: {}", args[0], e)),
+        reply => Ok(reply),
+    }
+}
+
+fn connect(log: &config::LogConfig) -> Result<Connection, String> {
+    let mut connection = open_link(log)?;
+    login(&mut connection, log).map_err(|e| connection.explain(e))?;
+    connection.clear_timeout()?;
+    Ok(connection)
+}
+
+fn login(connection: &mut Connection, log: &config::LogConfig) -> Result<(), String> {
+    match (&log.username, &log.password) {
+        (Some(username), Some(password)) => {
+            call(connection, &["AUTH", username, password])?;
+        }
+        (None, Some(password)) => {
+            call(connection, &["AUTH", password])?;
+        }
+        _ => {}
+    }
+    if let Some(database) = log.database {
+        call(connection, &["SELECT", &database.to_string()])?;
+    }
+    Ok(())
+}
+
+fn read_replies(mut reader: BufReader<Box<dyn Read + Send>>, replies: Sender<Reply>) {
+    while let Ok(reply) = read_reply(&mut reader) {
+        if replies.send(reply).is_err() {
+            break;
+        }
+    }
+}
+
+fn push_text(sink: &LogSink, bytes: &[u8]) {
+    sink.stats.lock().unwrap().bytes_received += bytes.len() as u64;
+    let text = sink.decode(bytes);
+    sink.push_line(format!("{}\n", text.trim_end_matches(['\r', '\n'])));
+}
+
+// message: [message, 频道, 内容]; 按模式订阅时为 [pmessage, 模式, 频道, 内容], 行前加上频道
+fn push_message(sink: &LogSink, reply: &Reply) {
+    let items = reply.items();
+    match (items.first().and_then(Reply::text).as_deref(), items) {
+        (Some("message"), [_, _, Reply::Bulk(Some(payload))]) => push_text(sink, payload),
+        (Some("pmessage"), [_, _, channel, Reply::Bulk(Some(payload))]) => {
+            let mut bytes = format!("[{}] ", channel.text().unwrap_or_default()).into_bytes();
+            bytes.extend_from_slice(payload);
+            push_text(sink, &bytes);
+        }
+        _ => {}
+    }
+}
+
+// [[流, [[id, [字段, 值, ...]], ...]]]; 只有一个字段时直接显示它的值, 否则转成 JSON 对象
+fn push_entries(sink: &LogSink, reply: &Reply, last_id: &mut String) {
+    for stream in reply.items() {
+        for entry in stream.items().get(1).map(Reply::items).unwrap_or_default() {
+            let [id, fields] = entry.items() else {
+                continue;
+            };
+            if let Some(id) = id.text() {
+                *last_id = id;
+            }
+            let fields = fields.items();
+            match fields {
+                [_, Reply::Bulk(Some(value))] => push_text(sink, value),
+                _ => {
+                    let object: Map<String, Value> = fields
+                        .chunks(2)
+                        .filter_map(|pair| match pair {
+                            [name, value] => Some((name.text()?, Value::String(value.text()?))),
+                            _ => None,
+                        })
+                        .collect();
+                    push_text(sink, Value::Object(object).to_string().as_bytes());
+                }
+            }
+        }
+    }
+}
+
+fn is_pattern(channel: &str) -> bool {
+    channel.contains(['*', '?', '['])
+}
+
+// 订阅模式断线期间的消息会丢失; stream 模式从最后读到的 id 之后继续
+fn follow(
+    log: &config::LogConfig,
+    sink: &LogSink,
+    requests: &Receiver<SourceRequest>,
+    last_id: &mut String,
+) -> Result<(), String> {
+    let mut connection = connect(log)?;
+    let xread = |writer: &mut Box<dyn Write + Send>, key: &str, id: &str| {
+        write_command(
+            writer,
+            &["XREAD", "COUNT", "100", "BLOCK", &BLOCK_MS.to_string(), "STREAMS", key, id],
+        )
+    };
+    match (&log.channel, &log.stream) {
+        (_, Some(key)) => xread(&mut connection.writer, key, last_id),
+        (Some(channel), None) if is_pattern(channel) => {
+            write_command(&mut connection.writer, &["PSUBSCRIBE", channel])
+        }
+        (Some(channel), None) => write_command(&mut connection.writer, &["SUBSCRIBE", channel]),
+        (None, None) => return Err(tr(Msg::NoRedisTarget).to_string()),
+    }
+    .map_err(|e| e.to_string())?;
+    sink.set_status(ConnectionStatus::Connected);
+
+    let Connection { link, reader, mut writer, .. } = connection;
+    let (reply_sender, replies) = mpsc::channel();
+    thread::spawn(move || read_replies(reader, reply_sender));
+    let mut last_reply = Instant::now();
+    let mut pinged = false;
+    loop {
+        match replies.recv_timeout(POLL_INTERVAL) {
+            Ok(Reply::Error(e)) => return Err(e),
+            Ok(reply) => {
+                last_reply = Instant::now();
+                pinged = false;
+                match &log.stream {
+                    Some(key) => {
+                        push_entries(sink, &reply, last_id);
+                        xread(&mut writer, key, last_id).map_err(|e| e.to_string())?;
+                    }
+                    None => push_message(sink, &reply),
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        while let Ok(request) = requests.try_recv() {
+            handle_request(request, sink);
+        }
+
+        // 网络中断时读线程可能一直阻塞, 长时间没有回复就主动重连
+        let silence = last_reply.elapsed();
+        if silence > MAX_SILENCE {
+            break;
+        }
+        if silence > PING_INTERVAL && !pinged {
+            write_command(&mut writer, &["PING"]).map_err(|e| e.to_string())?;
+            pinged = true;
+        }
+    }
+    drop(link);
+    Err(format!(
+        "{} ({}:{})",
+        tr(Msg::Disconnected),
+        log.host,
+        if log.port == 0 { DEFAULT_PORT } else { log.port }
+    ))
+}
+
+pub fn tail(log: &config::LogConfig, sink: LogSink, requests: Receiver<SourceRequest>) {
+    // $ 表示只读新写入的条目
+    let mut last_id = "$".to_string();
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        let started = Instant::now();
+        if let Err(e) = follow(log, &sink, &requests, &mut last_id) {
+            sink.set_status(ConnectionStatus::Error(e));
+        }
+
+        if started.elapsed() > Duration::from_secs(60) {
+            backoff = Duration::from_secs(1);
+        }
+        idle(backoff, &sink, &requests);
+        backoff = (backoff * 2).min(Duration::from_secs(30));
+    }
+}
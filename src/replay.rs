@@ -0,0 +1,139 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::command::handle_request;
+use crate::config;
+use crate::ssh::{ConnectionStatus, LogSink, SourceRequest};
+use crate::timestamp;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+pub struct Recording {
+    pub name: String,
+    pub path: String,
+    lines: Vec<String>,
+    format: timestamp::Format,
+}
+
+// 所有窗口共用一个虚拟时钟, 起点是全部录制中最早的时间戳
+pub struct ReplayClock {
+    started: Instant,
+    origin_millis: i64,
+    speed: f64,
+}
+
+impl ReplayClock {
+    pub fn new(recordings: &[Recording], speed: f64) -> Self {
+        let origin_millis = recordings
+            .iter()
+            .filter_map(|r| r.lines.iter().find_map(|l| r.format.parse(l)))
+            .min()
+            .unwrap_or(0);
+        ReplayClock {
+            started: Instant::now(),
+            origin_millis,
+            speed,
+        }
+    }
+
+    fn now_millis(&self) -> i64 {
+        self.origin_millis + (self.started.elapsed().as_millis() as f64 * self.speed) as i64
+    }
+
+    // 距离该时间戳还要等待的真实时间
+    fn ahead(&self, millis: i64) -> Option<Duration> {
+        let ahead = millis - self.now_millis();
+        (ahead > 0).then(|| Duration::from_millis((ahead as f64 / self.speed) as u64))
+    }
+
+    fn wait_until(&self, millis: i64) {
+        if let Some(ahead) = self.ahead(millis) {
+            thread::sleep(ahead);
+        }
+    }
+}
+
+pub fn load_recordings(files: &[String]) -> io::Result<Vec<Recording>> {
+    files
+        .iter()
+        .map(|file| {
+            let content = fs::read_to_string(file)?;
+            let name = Path::new(file)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| file.clone());
+            let lines: Vec<String> = content.lines().map(|l| format!("{}\n", l)).collect();
+            Ok(Recording {
+                name,
+                path: file.clone(),
+                format: timestamp::detect(&lines),
+                lines,
+            })
+        })
+        .collect()
+}
+
+// 没有时间戳的行(如堆栈)沿用上一行的时间, 紧跟着输出
+pub fn replay(recording: Recording, sink: LogSink, clock: &ReplayClock) {
+    for line in recording.lines {
+        if let Some(millis) = recording.format.parse(&line) {
+            clock.wait_until(millis);
+        }
+        sink.push_line(line);
+    }
+}
+
+// 等待时处理 UI 请求, 收到 R 时返回 false 从头回放
+fn wait(clock: &ReplayClock, millis: i64, sink: &LogSink, requests: &Receiver<SourceRequest>) -> bool {
+    while let Some(ahead) = clock.ahead(millis) {
+        match requests.recv_timeout(ahead.min(POLL_INTERVAL)) {
+            Ok(SourceRequest::Refresh) => return false,
+            Ok(request) => handle_request(request, sink),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => thread::sleep(POLL_INTERVAL),
+        }
+    }
+    true
+}
+
+// type = "replay": 回放本地文件, 配置了 speed 时按行内时间戳的间隔输出, 用于演示和调试格式化规则
+pub fn tail(log: &config::LogConfig, sink: LogSink, requests: Receiver<SourceRequest>) {
+    let path = log.log_path.paths().first().map(|p| p.to_string()).unwrap_or_default();
+    let speed = log.speed.filter(|speed| *speed > 0.0);
+    'source: loop {
+        match load_recordings(std::slice::from_ref(&path)) {
+            Ok(mut recordings) => {
+                let recording = recordings.remove(0);
+                sink.set_status(ConnectionStatus::Connected);
+                let clock = speed.map(|speed| ReplayClock::new(std::slice::from_ref(&recording), speed));
+                let format = log.timestamp_format.unwrap_or(recording.format);
+                for line in recording.lines {
+                    if let (Some(clock), Some(millis)) = (&clock, format.parse(&line)) {
+                        if !wait(clock, millis, &sink, &requests) {
+                            sink.clear();
+                            continue 'source;
+                        }
+                    }
+                    sink.push_line(line);
+                }
+            }
+            Err(e) => sink.set_status(ConnectionStatus::Error(format!("{}: {}", path, e))),
+        }
+
+        // 回放结束后保持窗口, 按 R 重新读取文件
+        loop {
+            match requests.recv() {
+                Ok(SourceRequest::Refresh) => {
+                    sink.clear();
+                    continue 'source;
+                }
+                Ok(request) => handle_request(request, &sink),
+                Err(_) => return,
+            }
+        }
+    }
+}
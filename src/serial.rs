@@ -0,0 +1,135 @@
+use std::fs::File;
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::command::{handle_request, idle};
+use crate::config;
+use crate::i18n::{tr, Msg};
+use crate::ssh::{ConnectionStatus, LogSink, SourceRequest};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const DEFAULT_BAUD: u32 = 115_200;
+// 设备输出提示符等不带换行的内容时, 空闲这么久就先显示出来
+const FLUSH_AFTER: Duration = Duration::from_millis(500);
+
+// 没有串口库, 用 stty 设置波特率和 raw 模式后当普通文件读
+fn open(device: &str, baud: u32) -> Result<File, String> {
+    let flag = if cfg!(target_os = "macos") { "-f" } else { "-F" };
+    let output = Command::new("stty")
+        .args([flag, device, &baud.to_string(), "raw", "-echo"])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("stty: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "stty ({}): {}",
+            device,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    File::open(device).map_err(|e| format!("{} ({}): {}", tr(Msg::ReadErr), device, e))
+}
+
+fn read_chunks(mut file: File, chunks: Sender<Vec<u8>>) {
+    let mut buf = [0u8; 4096];
+    loop {
+        match file.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                if chunks.send(buf[..n].to_vec()).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+// line_ending: "lf" (默认), "crlf" 或 "cr"
+struct Splitter {
+    delimiter: u8,
+    strip_cr: bool,
+    pending: Vec<u8>,
+}
+
+impl Splitter {
+    fn new(line_ending: Option<&str>) -> Self {
+        Splitter {
+            delimiter: if line_ending == Some("cr") { b'\r' } else { b'\n' },
+            strip_cr: line_ending == Some("crlf"),
+            pending: Vec::new(),
+        }
+    }
+
+    fn feed(&mut self, chunk: &[u8], sink: &LogSink) {
+        self.pending.extend_from_slice(chunk);
+        while let Some(end) = self.pending.iter().position(|&b| b == self.delimiter) {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            self.push(&line[..end], sink);
+        }
+    }
+
+    fn flush(&mut self, sink: &LogSink) {
+        if !self.pending.is_empty() {
+            let line = std::mem::take(&mut self.pending);
+            self.push(&line, sink);
+        }
+    }
+
+    fn push(&self, line: &[u8], sink: &LogSink) {
+        let line = if self.strip_cr { line.strip_suffix(b"\r").unwrap_or(line) } else { line };
+        sink.push_line(format!("{}\n", sink.decode(line)));
+    }
+}
+
+// 设备拔出后读取出错, 按退避重新打开
+pub fn tail(log: &config::LogConfig, sink: LogSink, requests: Receiver<SourceRequest>) {
+    let Some(device) = log.device.as_deref() else {
+        sink.set_status(ConnectionStatus::Error(tr(Msg::NoDevice).to_string()));
+        loop {
+            idle(Duration::from_secs(1), &sink, &requests);
+        }
+    };
+    let baud = log.baud.unwrap_or(DEFAULT_BAUD);
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        match open(device, baud) {
+            Ok(file) => {
+                backoff = Duration::from_secs(1);
+                sink.set_status(ConnectionStatus::Connected);
+                let (chunk_sender, chunks) = mpsc::channel();
+                thread::spawn(move || read_chunks(file, chunk_sender));
+                let mut splitter = Splitter::new(log.line_ending.as_deref());
+                let mut last_chunk = Instant::now();
+                loop {
+                    match chunks.recv_timeout(POLL_INTERVAL) {
+                        Ok(chunk) => {
+                            sink.stats.lock().unwrap().bytes_received += chunk.len() as u64;
+                            splitter.feed(&chunk, &sink);
+                            last_chunk = Instant::now();
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                    if last_chunk.elapsed() > FLUSH_AFTER {
+                        splitter.flush(&sink);
+                    }
+                    while let Ok(request) = requests.try_recv() {
+                        handle_request(request, &sink);
+                    }
+                }
+                splitter.flush(&sink);
+                sink.set_status(ConnectionStatus::Error(format!(
+                    "{} ({})",
+                    tr(Msg::Disconnected),
+                    device
+                )));
+            }
+            Err(e) => sink.set_status(ConnectionStatus::Error(e)),
+        }
+        idle(backoff, &sink, &requests);
+        backoff = (backoff * 2).min(Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,32 @@
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::config::SilenceConfig;
+use crate::suggest;
+
+// 已知的噪音: 匹配的行照常显示, 但不触发告警和 auto_pause_on_error
+static SILENCES: Lazy<RwLock<Vec<Regex>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+// 配置在读取时已经校验过
+pub fn init(silences: &[SilenceConfig]) {
+    let mut regexes = SILENCES.write().unwrap();
+    regexes.extend(silences.iter().filter_map(|s| Regex::new(&s.pattern).ok()));
+}
+
+pub fn is_silenced(line: &str) -> bool {
+    SILENCES.read().unwrap().iter().any(|re| re.is_match(line))
+}
+
+// 取能匹配同类日志的模板, 没有级别可以归纳时匹配整行
+pub fn pattern_for(line: &str) -> String {
+    let line = line.trim_end_matches(['\r', '\n']);
+    suggest::template(line).unwrap_or_else(|| regex::escape(line.trim()))
+}
+
+pub fn add(pattern: &str) -> Result<(), regex::Error> {
+    let regex = Regex::new(pattern)?;
+    SILENCES.write().unwrap().push(regex);
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use crate::command::{forward_lines, handle_request, idle};
+use crate::config;
+use crate::i18n::{tr, Msg};
+use crate::ssh::{ConnectionStatus, LogSink, SourceRequest};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const DEFAULT_RETRY: Duration = Duration::from_secs(3);
+
+// 按 SSE 规范逐行组装事件, 空行表示一个事件结束
+#[derive(Default)]
+struct EventParser {
+    event: String,
+    data: Vec<String>,
+    last_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl EventParser {
+    fn feed(&mut self, line: &str) -> Option<String> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => self.data.push(value.to_string()),
+            "id" if !value.contains('\0') => self.last_id = Some(value.to_string()),
+            "retry" => {
+                if let Ok(millis) = value.parse() {
+                    self.retry = Some(Duration::from_millis(millis));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    // 多行 data 合并成一行, 非默认的事件类型放在行首
+    fn dispatch(&mut self) -> Option<String> {
+        let event = std::mem::take(&mut self.event);
+        if self.data.is_empty() {
+            return None;
+        }
+        let data = std::mem::take(&mut self.data).join(" ");
+        Some(if event.is_empty() || event == "message" {
+            format!("{}\n", data)
+        } else {
+            format!("[{}] {}\n", event, data)
+        })
+    }
+}
+
+fn curl_args(log: &config::LogConfig, url: &str, last_id: Option<&str>) -> Vec<String> {
+    let mut args: Vec<String> = ["-sS", "-N", "--fail", "-H", "Accept: text/event-stream"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+    let mut header = |value: String| {
+        args.push("-H".to_string());
+        args.push(value);
+    };
+    for (name, value) in log.headers.iter().flatten() {
+        header(format!("{}: {}", name, value));
+    }
+    if let Some(token) = &log.token {
+        header(format!("Authorization: Bearer {}", token));
+    }
+    // 重连时带上最后收到的事件 id, 服务端据此补发断线期间的事件
+    if let Some(id) = last_id {
+        header(format!("Last-Event-ID: {}", id));
+    }
+    args.push(url.to_string());
+    args
+}
+
+fn spawn_curl(log: &config::LogConfig, url: &str, last_id: Option<&str>) -> Result<Child, String> {
+    Command::new("curl")
+        .args(curl_args(log, url, last_id))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("{}: {}", tr(Msg::CommandErr), e))
+}
+
+pub fn stream(log: &config::LogConfig, sink: LogSink, requests: Receiver<SourceRequest>) {
+    let Some(url) = log.url.clone() else {
+        sink.set_status(ConnectionStatus::Error(tr(Msg::NoUrl).to_string()));
+        loop {
+            idle(POLL_INTERVAL, &sink, &requests);
+        }
+    };
+
+    let mut parser = EventParser::default();
+    loop {
+        let mut child = match spawn_curl(log, &url, parser.last_id.as_deref()) {
+            Ok(child) => child,
+            Err(e) => {
+                sink.set_status(ConnectionStatus::Error(e));
+                idle(parser.retry.unwrap_or(DEFAULT_RETRY), &sink, &requests);
+                continue;
+            }
+        };
+        sink.set_status(ConnectionStatus::Connected);
+
+        let (line_sender, lines) = mpsc::channel();
+        if let Some(stdout) = child.stdout.take() {
+            forward_lines(stdout, line_sender);
+        }
+        loop {
+            match lines.recv_timeout(POLL_INTERVAL) {
+                Ok(bytes) => {
+                    sink.stats.lock().unwrap().bytes_received += bytes.len() as u64;
+                    if let Some(line) = parser.feed(&sink.decode(&bytes)) {
+                        sink.push_line(line);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            while let Ok(request) = requests.try_recv() {
+                handle_request(request, &sink);
+            }
+        }
+
+        // 连接结束时丢弃未完成的事件, 重连后由 Last-Event-ID 补发
+        parser.event.clear();
+        parser.data.clear();
+        let mut stderr = String::new();
+        if let Some(mut pipe) = child.stderr.take() {
+            let _ = pipe.read_to_string(&mut stderr);
+        }
+        let status = child.wait();
+        let reason = match stderr.trim() {
+            "" => status.map(|s| s.to_string()).unwrap_or_default(),
+            message => message.to_string(),
+        };
+        sink.set_status(ConnectionStatus::Error(format!(
+            "{} ({}): {}",
+            tr(Msg::Disconnected),
+            url,
+            reason
+        )));
+        idle(parser.retry.unwrap_or(DEFAULT_RETRY), &sink, &requests);
+    }
+}
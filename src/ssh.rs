@@ -1,148 +1,1299 @@
-use ssh2::Session;
-use std::io::{self, BufRead, BufReader};
-use std::net::TcpStream;
-use std::path::Path;
+use ssh2::{CheckResult, KnownHostFileKind, MethodType, Session, Sftp};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::thread;
+use std::time::{Duration, Instant};
 
+use encoding_rs::Encoding;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::archive::Archive;
+use crate::auth::{authenticate, AuthMethod};
 use crate::config;
+use crate::forward::Forwarder;
+use crate::i18n::{tr, Msg};
+use crate::reassemble::JsonAssembler;
+use crate::timestamp;
+use crate::transform::Pipeline;
 
 pub enum ConnectionStatus {
     Connected,
+    Authenticated(AuthMethod),
     Error(String),
+    Suspended,
+}
+
+pub struct ConnectionStats {
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    pub lines_received: u64,
+    // 当前缓冲区中所有行的字节数, 随写入和淘汰增量维护
+    pub buffer_bytes: u64,
+    last_line_at: Option<Instant>,
+    rate_window_start: Instant,
+    rate_window_lines: u64,
+    lines_per_sec: f64,
+}
+
+impl ConnectionStats {
+    pub fn new() -> Self {
+        ConnectionStats {
+            bytes_received: 0,
+            bytes_sent: 0,
+            lines_received: 0,
+            buffer_bytes: 0,
+            last_line_at: None,
+            rate_window_start: Instant::now(),
+            rate_window_lines: 0,
+            lines_per_sec: 0.0,
+        }
+    }
+
+    fn record_line(&mut self) {
+        let now = Instant::now();
+        self.lines_received += 1;
+        self.last_line_at = Some(now);
+        self.rate_window_lines += 1;
+
+        let elapsed = now.duration_since(self.rate_window_start);
+        if elapsed >= Duration::from_secs(1) {
+            self.lines_per_sec = self.rate_window_lines as f64 / elapsed.as_secs_f64();
+            self.rate_window_start = now;
+            self.rate_window_lines = 0;
+        }
+    }
+
+    // 超过两个统计周期没有新行时视为速率为 0
+    pub fn lines_per_sec(&self) -> f64 {
+        if self.rate_window_start.elapsed() > Duration::from_secs(2) {
+            0.0
+        } else {
+            self.lines_per_sec
+        }
+    }
+
+    pub fn since_last_line(&self) -> Option<Duration> {
+        self.last_line_at.map(|at| at.elapsed())
+    }
+}
+
+pub struct LogSink {
+    pub name: String,
+    pub host: String,
+    pub content: Arc<Mutex<Vec<String>>>,
+    pub max_history: Arc<AtomicUsize>,
+    pub connection_status: Arc<Mutex<ConnectionStatus>>,
+    pub stats: Arc<Mutex<ConnectionStats>>,
+    pub auto_pause: bool,
+    pub paused_at: Arc<Mutex<Option<usize>>>,
+    pub download: Arc<Mutex<Option<DownloadProgress>>>,
+    pub archive: Option<Arc<Archive>>,
+    pub encoding: &'static Encoding,
+    pub transforms: Pipeline,
+    pub alerts: Vec<Regex>,
+    pub retention: Option<Retention>,
+    pub recent: Option<Arc<Recent>>,
+    // drop_excluded 时由 UI 设置, 匹配的行直接丢弃
+    pub dropped: Arc<Mutex<Vec<Regex>>>,
+    pub reassembly: Option<Mutex<JsonAssembler>>,
+    // 数据源提供的附加标题, 例如 Loki 的流标签
+    pub subtitle: Arc<Mutex<Option<String>>>,
+    // 与 content 逐行对应的远端文件位置, 只有单文件 tail 会记录
+    pub positions: Arc<Mutex<VecDeque<Option<Position>>>>,
+    pub timestamps: Arc<timestamp::Detector>,
+}
+
+// 行在远端文件中的位置: 起始字节偏移和大致行号(从 1 开始)
+#[derive(Clone)]
+pub struct Position {
+    pub host: Arc<str>,
+    pub path: Arc<str>,
+    pub offset: u64,
+    pub line: Option<u64>,
+}
+
+impl Position {
+    // host:path:行号 (offset 字节偏移)
+    pub fn permalink(&self) -> String {
+        match self.line {
+            Some(line) => format!("{}:{}:{} (offset {})", self.host, self.path, line, self.offset),
+            None => format!("{}:{} (offset {})", self.host, self.path, self.offset),
+        }
+    }
+}
+
+// 缓冲区只会从头部被清空或缩减, 按末尾对齐取行的位置
+pub fn position_of(
+    content: &[String],
+    positions: &VecDeque<Option<Position>>,
+    line: usize,
+) -> Option<Position> {
+    let from_end = content.len().checked_sub(line)?;
+    let index = positions.len().checked_sub(from_end)?;
+    positions.get(index).cloned().flatten()
+}
+
+// highlight_new: 最近到达的行的时间, 与 content 的末尾对齐
+pub struct Recent {
+    window: Duration,
+    arrivals: Mutex<VecDeque<Instant>>,
+}
+
+const MAX_RECENT: usize = 1024;
+
+impl Recent {
+    pub fn new(window: Duration) -> Self {
+        Recent {
+            window,
+            arrivals: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    fn push(&self) {
+        let now = Instant::now();
+        let mut arrivals = self.arrivals.lock().unwrap();
+        arrivals.push_back(now);
+        while arrivals.len() > MAX_RECENT
+            || arrivals.front().is_some_and(|arrival| now - *arrival > self.window)
+        {
+            arrivals.pop_front();
+        }
+    }
+
+    // 倒数第 from_end 行剩余的高亮程度, 1.0 为刚到达, 过了 window 返回 None
+    pub fn freshness(&self, from_end: usize) -> Option<f32> {
+        let arrivals = self.arrivals.lock().unwrap();
+        let arrival = arrivals.get(arrivals.len().checked_sub(from_end + 1)?)?;
+        let age = arrival.elapsed().as_secs_f32() / self.window.as_secs_f32();
+        (age < 1.0).then_some(1.0 - age)
+    }
 }
 
-pub fn connect_and_tail(
+// max_age 按行时间淘汰: 带时间戳的行和最新的时间戳比较, 其余按到达时间
+pub struct Retention {
+    max_age: Duration,
+    state: Mutex<RetentionState>,
+}
+
+#[derive(Default)]
+struct RetentionState {
+    arrivals: VecDeque<Instant>,
+    latest: Option<i64>,
+}
+
+impl Retention {
+    pub fn new(max_age: Duration) -> Self {
+        Retention {
+            max_age,
+            state: Mutex::new(RetentionState::default()),
+        }
+    }
+}
+
+impl RetentionState {
+    fn expired(&self, max_age: Duration, line: &str, format: timestamp::Format) -> bool {
+        match (format.parse(line), self.latest) {
+            (Some(millis), Some(latest)) => latest - millis > max_age.as_millis() as i64,
+            _ => self
+                .arrivals
+                .front()
+                .is_some_and(|arrival| arrival.elapsed() > max_age),
+        }
+    }
+}
+
+pub static SEVERE_LINE: Lazy<Regex> = Lazy::new(|| Regex::new(r"ERROR|FATAL").unwrap());
+
+impl LogSink {
+    // 按配置的编码解码, 无法解码的字节替换为 U+FFFD
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        self.encoding.decode_without_bom_handling(bytes).0.into_owned()
+    }
+
+    pub fn push_line(&self, line: String) {
+        self.push_line_at(line, None)
+    }
+
+    pub fn push_line_at(&self, line: String, position: Option<Position>) {
+        match &self.reassembly {
+            Some(assembler) => {
+                let records = assembler.lock().unwrap().feed(line, position);
+                for (line, position) in records {
+                    self.store_line(line, position);
+                }
+            }
+            None => self.store_line(line, position),
+        }
+    }
+
+    fn store_line(&self, line: String, position: Option<Position>) {
+        let line = self.transforms.apply(line);
+        if self.dropped.lock().unwrap().iter().any(|re| re.is_match(&line)) {
+            return;
+        }
+        self.timestamps.observe(&line);
+        self.stats.lock().unwrap().record_line();
+        crate::otlp::export(&self.name, &self.host, &line);
+        crate::headless::emit(&self.name, &line);
+        crate::dedup::record(&self.name, &line);
+
+        let severe = self.auto_pause
+            && (SEVERE_LINE.is_match(&line) || self.alerts.iter().any(|re| re.is_match(&line)))
+            && !crate::silence::is_silenced(&line);
+        let mut content = self.content.lock().unwrap();
+        let mut retention = self.retention.as_ref().map(|r| (r.max_age, r.state.lock().unwrap()));
+        if let Some((_, state)) = retention.as_mut() {
+            // 缓冲区可能被清空或从头部缩减过, 先让到达时间和内容对齐
+            let excess = state.arrivals.len().saturating_sub(content.len());
+            state.arrivals.drain(..excess);
+            state.arrivals.push_back(Instant::now());
+            if let Some(millis) = self.timestamps.format().parse(&line) {
+                state.latest = Some(state.latest.map_or(millis, |latest| latest.max(millis)));
+            }
+        }
+        let mut buffer_bytes = line.len() as u64;
+        content.push(line);
+        if let Some(recent) = &self.recent {
+            recent.push();
+        }
+        let mut positions = self.positions.lock().unwrap();
+        if position.is_some() || !positions.is_empty() {
+            let earlier = content.len() - 1;
+            let excess = positions.len().saturating_sub(earlier);
+            positions.drain(..excess);
+            while positions.len() < earlier {
+                positions.push_front(None);
+            }
+            positions.push_back(position);
+        }
+
+        let mut paused_at = self.paused_at.lock().unwrap();
+        if severe && paused_at.is_none() {
+            *paused_at = Some(content.len() - 1);
+        }
+
+        let max_history = self.max_history.load(Ordering::Relaxed);
+        while content.len() > max_history
+            || retention.as_ref().is_some_and(|(max_age, state)| {
+                content.len() > 1 && state.expired(*max_age, &content[0], self.timestamps.format())
+            })
+        {
+            let removed = content.remove(0);
+            if let Some((_, state)) = retention.as_mut() {
+                state.arrivals.pop_front();
+            }
+            positions.pop_front();
+            buffer_bytes = buffer_bytes.wrapping_sub(removed.len() as u64);
+            if let Some(archive) = &self.archive {
+                archive.push(removed);
+            }
+            *paused_at = paused_at.map(|i| i.saturating_sub(1));
+        }
+        {
+            let mut stats = self.stats.lock().unwrap();
+            stats.buffer_bytes = stats.buffer_bytes.wrapping_add(buffer_bytes);
+        }
+    }
+
+    pub fn clear(&self) {
+        self.content.lock().unwrap().clear();
+        if let Some(assembler) = &self.reassembly {
+            assembler.lock().unwrap().flush();
+        }
+        if let Some(recent) = &self.recent {
+            recent.arrivals.lock().unwrap().clear();
+        }
+        self.stats.lock().unwrap().buffer_bytes = 0;
+        *self.paused_at.lock().unwrap() = None;
+    }
+
+    pub fn set_status(&self, status: ConnectionStatus) {
+        crate::headless::status(&self.name, &status);
+        if let Ok(mut status_lock) = self.connection_status.lock() {
+            *status_lock = status;
+        }
+    }
+}
+
+// UI 线程发给 tail 线程的请求, 在同一个 SSH 会话上执行
+pub enum SourceRequest {
+    Exec {
+        command: String,
+        reply: Sender<Result<String, String>>,
+    },
+    SwitchPath(String),
+    Download(PathBuf),
+    // 清空后重新回填最近的行, 不断开连接
+    Refresh,
+    // 窗口长时间没有显示时断开连接, 再次显示时 Resume 重连
+    Suspend,
+    Resume,
+}
+
+pub struct DownloadProgress {
+    pub local_path: PathBuf,
+    pub total: u64,
+    pub done: u64,
+    pub finished: bool,
+    pub error: Option<String>,
+}
+
+struct ActiveDownload {
+    _sftp: Sftp,
+    remote: ssh2::File,
+    local: fs::File,
+    progress: Arc<Mutex<Option<DownloadProgress>>>,
+}
+
+// 连接中断或切换文件时下载随之结束, 在进度里标记出来
+impl Drop for ActiveDownload {
+    fn drop(&mut self) {
+        if let Some(progress) = self.progress.lock().unwrap().as_mut() {
+            if !progress.finished && progress.error.is_none() {
+                progress.error = Some(tr(Msg::Disconnected).to_string());
+            }
+        }
+    }
+}
+
+const DOWNLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
+impl ActiveDownload {
+    // 返回 false 表示下载已结束(完成或失败)
+    fn copy_chunk(&mut self) -> bool {
+        let mut buf = vec![0u8; DOWNLOAD_CHUNK_SIZE];
+        let result = self
+            .remote
+            .read(&mut buf)
+            .and_then(|n| self.local.write_all(&buf[..n]).map(|_| n));
+
+        let mut progress = self.progress.lock().unwrap();
+        let Some(progress) = progress.as_mut() else {
+            return false;
+        };
+        match result {
+            Ok(0) => {
+                progress.finished = true;
+                false
+            }
+            Ok(n) => {
+                progress.done += n as u64;
+                true
+            }
+            Err(e) => {
+                progress.error = Some(e.to_string());
+                false
+            }
+        }
+    }
+}
+
+fn start_download(
+    sess: &Session,
     log: &config::LogConfig,
-    content: Arc<Mutex<Vec<String>>>,
-    max_history: usize,
-    scroll_position: Arc<Mutex<usize>>,
-    is_maximized: Arc<Mutex<bool>>,
-    connection_status: Arc<Mutex<ConnectionStatus>>,
-) -> io::Result<()> {
+    local_path: PathBuf,
+    progress: &Arc<Mutex<Option<DownloadProgress>>>,
+) -> Option<ActiveDownload> {
+    let mut state = DownloadProgress {
+        local_path: local_path.clone(),
+        total: 0,
+        done: 0,
+        finished: false,
+        error: None,
+    };
+
+    sess.set_timeout(0);
+    let result = (|| -> Result<ActiveDownload, String> {
+        if log.log_path.is_multi() {
+            return Err(tr(Msg::DownloadMultiErr).to_string());
+        }
+        let remote_path = Path::new(log.log_path.paths()[0]);
+        let sftp = sess.sftp().map_err(|e| e.to_string())?;
+        let remote = sftp.open(remote_path).map_err(|e| e.to_string())?;
+        state.total = sftp.stat(remote_path).ok().and_then(|s| s.size).unwrap_or(0);
+
+        if let Some(dir) = local_path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let local = fs::File::create(&local_path).map_err(|e| e.to_string())?;
+        Ok(ActiveDownload {
+            _sftp: sftp,
+            remote,
+            local,
+            progress: Arc::clone(progress),
+        })
+    })();
+    sess.set_timeout(REQUEST_POLL_MS);
+
+    if let Err(e) = &result {
+        state.error = Some(e.clone());
+    }
+    *progress.lock().unwrap() = Some(state);
+    result.ok()
+}
+
+// 单文件 tail 读到的字节偏移和已经读过的完整行数, 用于断线续传和行位置
+#[derive(Default)]
+struct TailCursor {
+    offset: Option<u64>,
+    lines: Option<u64>,
+}
+
+enum StreamEnd {
+    Closed,
+    SwitchPath(String),
+    Refresh,
+    Suspend,
+}
+
+// 断线后按指数退避重连, 并从上次读到的字节偏移继续, 保证缓冲区内容连续
+pub fn tail_with_retry(log: &config::LogConfig, sink: LogSink, requests: Receiver<SourceRequest>) {
+    let mut log = log.clone();
+    let mut cursor = TailCursor::default();
+    let mut backoff = Duration::from_secs(1);
+
+    loop {
+        let started = Instant::now();
+        match connect_and_tail(&mut log, &sink, &requests, &mut cursor) {
+            Ok(StreamEnd::Suspend) => {
+                if !wait_for_resume(&mut log, &sink, &requests, &mut cursor) {
+                    return;
+                }
+                backoff = Duration::from_secs(1);
+                continue;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                let has_error = matches!(
+                    *sink.connection_status.lock().unwrap(),
+                    ConnectionStatus::Error(_)
+                );
+                if !has_error {
+                    sink.set_status(ConnectionStatus::Error(e.to_string()));
+                }
+            }
+        }
+
+        if started.elapsed() > Duration::from_secs(60) {
+            backoff = Duration::from_secs(1);
+        }
+        thread::sleep(backoff);
+        backoff = (backoff * 2).min(Duration::from_secs(30));
+    }
+}
+
+// 断开期间保留读取位置, 重连后从断开处续传, 补上这段时间的日志
+// 返回 false 表示 UI 已经退出
+fn wait_for_resume(
+    log: &mut config::LogConfig,
+    sink: &LogSink,
+    requests: &Receiver<SourceRequest>,
+    cursor: &mut TailCursor,
+) -> bool {
+    sink.set_status(ConnectionStatus::Suspended);
+    loop {
+        match requests.recv() {
+            Ok(SourceRequest::Resume) => return true,
+            Ok(SourceRequest::SwitchPath(path)) => {
+                log.log_path = config::LogPath::Single(path);
+                *cursor = TailCursor::default();
+                sink.clear();
+                return true;
+            }
+            Ok(SourceRequest::Refresh) => {
+                *cursor = TailCursor::default();
+                sink.clear();
+                return true;
+            }
+            Ok(SourceRequest::Exec { reply, .. }) => {
+                let _ = reply.send(Err(tr(Msg::Suspended).to_string()));
+            }
+            Ok(SourceRequest::Download(_) | SourceRequest::Suspend) => {}
+            Err(_) => return false,
+        }
+    }
+}
+
+fn connect_and_tail(
+    log: &mut config::LogConfig,
+    sink: &LogSink,
+    requests: &Receiver<SourceRequest>,
+    cursor: &mut TailCursor,
+) -> io::Result<StreamEnd> {
+    if let Some(hook) = &log.pre_connect {
+        run_hook("pre_connect", hook, log).map_err(|e| {
+            sink.set_status(ConnectionStatus::Error(e.clone()));
+            io::Error::other(e)
+        })?;
+    }
+
     let tcp = TcpStream::connect(format!("{}:{}", log.host, log.port)).map_err(|e| {
-        let _ = update_connection_status(
-            &connection_status,
-            ConnectionStatus::Error(format!("Connect Err: {}", e)),
-        );
+        sink.set_status(ConnectionStatus::Error(format!(
+            "{}: {}",
+            tr(Msg::ConnectErr),
+            e
+        )));
         e
     })?;
 
-    tcp.set_read_timeout(Some(Duration::from_secs(30)))?;
+    let stream = counting_relay(tcp, Arc::clone(&sink.stats))?;
+    stream.set_read_timeout(Some(Duration::from_secs(30)))?;
 
-    let mut sess = Session::new().map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-    sess.set_tcp_stream(tcp);
+    let mut sess = Session::new().map_err(io::Error::other)?;
+    sess.set_compress(log.compression.unwrap_or(false));
+    if let Some(algorithms) = &log.ssh_algorithms {
+        set_algorithms(&sess, algorithms).map_err(|e| {
+            sink.set_status(ConnectionStatus::Error(format!(
+                "{}: {}",
+                tr(Msg::AlgorithmErr),
+                e
+            )));
+            io::Error::other(e)
+        })?;
+    }
+    sess.set_tcp_stream(stream);
 
     sess.handshake().map_err(|e| {
-        let _ = update_connection_status(
-            &connection_status,
-            ConnectionStatus::Error(format!("Handshake Err: {}", e)),
-        );
-        io::Error::new(io::ErrorKind::Other, e)
+        sink.set_status(ConnectionStatus::Error(format!(
+            "{}: {}",
+            tr(Msg::HandshakeErr),
+            e
+        )));
+        io::Error::other(e)
+    })?;
+    verify_host_key(&sess, log).map_err(|e| {
+        sink.set_status(ConnectionStatus::Error(e.clone()));
+        io::Error::other(e)
     })?;
 
-    authenticate(&sess, log)?;
+    let auth_method = authenticate(&sess, log).map_err(|e| {
+        sink.set_status(ConnectionStatus::Error(format!("{}: {}", tr(Msg::AuthErr), e)));
+        e
+    })?;
+
+    sink.set_status(ConnectionStatus::Authenticated(auth_method));
+    if let Some(hook) = &log.post_connect {
+        // post_connect 失败不中断 tail, 只在状态中提示
+        if let Err(e) = run_hook("post_connect", hook, log) {
+            sink.set_status(ConnectionStatus::Error(e));
+        }
+    }
+    let mut forwarder = match &log.forward {
+        Some(forward) => match Forwarder::bind(forward) {
+            Ok(forwarder) => Some(forwarder),
+            Err(e) => {
+                // 端口被占用时只提示, 日志照常 tail
+                sink.set_status(ConnectionStatus::Error(format!(
+                    "{} ({} -> {}): {}",
+                    tr(Msg::ForwardErr),
+                    forward.local,
+                    forward.remote,
+                    e
+                )));
+                None
+            }
+        },
+        None => None,
+    };
+    // 让阻塞读定期返回, 以便处理 UI 发来的请求
+    sess.set_timeout(REQUEST_POLL_MS);
+
+    loop {
+        match tail_channel(&sess, log, sink, requests, cursor, &mut forwarder)? {
+            end @ (StreamEnd::Closed | StreamEnd::Suspend) => return Ok(end),
+            StreamEnd::SwitchPath(path) => {
+                log.log_path = config::LogPath::Single(path);
+                *cursor = TailCursor::default();
+                sink.clear();
+            }
+            // 在同一会话上重开 channel, 按首次连接的方式回填
+            StreamEnd::Refresh => {
+                *cursor = TailCursor::default();
+                sink.clear();
+            }
+        }
+    }
+}
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+// 和 OpenSSH 一样, 非 22 端口的主机在 known_hosts 里写作 [host]:port
+fn verify_host_key(sess: &Session, log: &config::LogConfig) -> Result<(), String> {
+    let mode = log.host_key_check.unwrap_or_default();
+    if mode == config::HostKeyCheck::Off {
+        return Ok(());
+    }
+    let fail = |e: &dyn std::fmt::Display| format!("{}: {}", tr(Msg::HostKeyErr), e);
+    let (key, key_type) = sess.host_key().ok_or_else(|| fail(&"no host key"))?;
+    let path = config::expand_tilde("~/.ssh/known_hosts").map_err(|e| fail(&e))?;
+    let mut known_hosts = sess.known_hosts().map_err(|e| fail(&e))?;
+    if path.exists() {
+        known_hosts
+            .read_file(&path, KnownHostFileKind::OpenSSH)
+            .map_err(|e| fail(&e))?;
+    }
+    match known_hosts.check_port(&log.host, log.port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::Mismatch => Err(fail(&tr(Msg::HostKeyChanged))),
+        CheckResult::NotFound if mode == config::HostKeyCheck::AcceptNew => {
+            let host = if log.port == 22 {
+                log.host.clone()
+            } else {
+                format!("[{}]:{}", log.host, log.port)
+            };
+            known_hosts
+                .add(&host, key, "added by rogger", key_type.into())
+                .and_then(|_| known_hosts.write_file(&path, KnownHostFileKind::OpenSSH))
+                .map_err(|e| fail(&e))
+        }
+        CheckResult::NotFound => Err(fail(&tr(Msg::HostKeyUnknown))),
+        CheckResult::Failure => Err(fail(&"known_hosts check failed")),
+    }
+}
+
+// --preflight: 解析地址、连接并认证, 再确认日志文件可读, 不开始 tail
+pub fn check(log: &config::LogConfig) -> Result<String, String> {
+    if let Some(hook) = &log.pre_connect {
+        run_hook("pre_connect", hook, log)?;
+    }
+    let address = (log.host.as_str(), log.port)
+        .to_socket_addrs()
+        .map_err(|e| e.to_string())
+        .and_then(|mut addresses| addresses.next().ok_or_else(|| log.host.clone()))
+        .map_err(|e| format!("{}: {}", tr(Msg::DnsErr), e))?;
+    let tcp = TcpStream::connect_timeout(&address, CHECK_TIMEOUT)
+        .map_err(|e| format!("{}: {}", tr(Msg::ConnectErr), e))?;
+
+    let mut sess = Session::new().map_err(|e| e.to_string())?;
+    sess.set_timeout(CHECK_TIMEOUT.as_millis() as u32);
+    if let Some(algorithms) = &log.ssh_algorithms {
+        set_algorithms(&sess, algorithms).map_err(|e| format!("{}: {}", tr(Msg::AlgorithmErr), e))?;
+    }
+    sess.set_tcp_stream(tcp);
+    sess.handshake()
+        .map_err(|e| format!("{}: {}", tr(Msg::HandshakeErr), e))?;
+    verify_host_key(&sess, log)?;
+    let method = authenticate(&sess, log).map_err(|e| format!("{}: {}", tr(Msg::AuthErr), e))?;
+
+    // 输出不可读的路径; 通配符展开后至少要有一个可读文件
+    let command = match log.source {
+        config::SourceType::Journal => "command -v journalctl >/dev/null || echo journalctl".to_string(),
+        config::SourceType::Podman => "command -v podman >/dev/null || echo podman".to_string(),
+        _ => log.log_path
+            .paths()
+            .iter()
+            .map(|path| {
+                format!(
+                    "set -- {}; [ -r \"$1\" ] || echo {};",
+                    shell_glob_quote(path),
+                    shell_quote(path)
+                )
+            })
+            .collect(),
+    };
+    let missing = run_remote(&sess, &command)?;
+    let missing: Vec<&str> = missing.lines().filter(|l| !l.is_empty()).collect();
+    if !missing.is_empty() {
+        return Err(format!("{}: {}", tr(Msg::MissingFile), missing.join(", ")));
+    }
+    Ok(method.as_str().to_string())
+}
+
+// 在本地执行连接钩子, 例如刷新 VPN token 或用 vault 签发证书
+fn run_hook(name: &str, command: &str, log: &config::LogConfig) -> Result<(), String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("ROGGER_NAME", &log.name)
+        .env("ROGGER_HOST", &log.host)
+        .env("ROGGER_PORT", log.port.to_string())
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("{} ({}): {}", tr(Msg::HookErr), name, e))?;
+
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(format!(
+        "{} ({}): {} {}",
+        tr(Msg::HookErr),
+        name,
+        output.status,
+        stderr.trim()
+    ))
+}
 
-    let mut channel = sess.channel_session()?;
-    channel.exec(&format!("tail {} -n 100 -f", log.log_path))?;
+const REQUEST_POLL_MS: u32 = 200;
+const FORWARD_POLL_MS: u32 = 10;
+const LIBSSH2_ERROR_TIMEOUT: i32 = -9;
+
+fn remote_command(log: &config::LogConfig, cursor: &TailCursor) -> String {
+    let journal = log.source == config::SourceType::Journal;
+    let podman = log.source == config::SourceType::Podman;
+    if journal {
+        journal_command(log, cursor.offset.is_some())
+    } else if podman {
+        podman_command(log, cursor.offset.is_some())
+    } else if is_compressed(log) {
+        compressed_command(log, cursor.offset.is_some())
+    } else if log.log_path.is_multi() {
+        multi_tail_command(&log.log_path.paths(), cursor.offset.is_some())
+    } else {
+        tail_command(
+            log.log_path.paths()[0],
+            cursor.offset,
+            backfill_secs(log),
+            log.line_numbers.unwrap_or(true),
+        )
+    }
+}
+
+// --dry-run: 首次连接时在远端执行的命令
+pub fn initial_command(log: &config::LogConfig) -> String {
+    remote_command(log, &TailCursor::default())
+}
+
+fn tail_channel(
+    sess: &Session,
+    log: &config::LogConfig,
+    sink: &LogSink,
+    requests: &Receiver<SourceRequest>,
+    cursor: &mut TailCursor,
+    forwarder: &mut Option<Forwarder>,
+) -> io::Result<StreamEnd> {
+    let mut channel = retry_on_timeout(|| sess.channel_session())?;
+    let journal = log.source == config::SourceType::Journal;
+    let podman = log.source == config::SourceType::Podman;
+    let multi = !journal && !podman && log.log_path.is_multi();
+    let compressed = !journal && !podman && is_compressed(log);
+    let command = remote_command(log, cursor);
+    retry_on_timeout(|| channel.exec(&command))?;
 
     let mut reader = BufReader::new(channel);
 
-    let _ = update_connection_status(&connection_status, ConnectionStatus::Connected);
+    if multi || journal || podman || compressed {
+        // 多文件、journal、容器日志和压缩文件无法按偏移续传, 只用 offset 标记是否已经回填过
+        cursor.offset = Some(0);
+    } else {
+        // 远端脚本先输出本次 tail 的起始偏移和偏移之前的行数
+        let mut first_line = String::new();
+        while let Err(e) = reader.read_line(&mut first_line) {
+            if !is_timeout(&e) {
+                return Err(e);
+            }
+        }
+        let mut fields = first_line.split_whitespace();
+        let start = fields.next().and_then(|f| f.parse::<u64>().ok()).ok_or_else(|| {
+            io::Error::other(format!("{}: {}", tr(Msg::OffsetErr), first_line.trim()))
+        })?;
+        // 重连时只输出偏移: 从断开处续传就沿用行数, 文件被截断后从 0 行开始
+        let counted = fields.next().and_then(|f| f.parse::<u64>().ok());
+        cursor.lines = match counted {
+            Some(lines) => Some(lines),
+            None if cursor.offset == Some(start) => cursor.lines,
+            None if start == 0 && log.line_numbers.unwrap_or(true) => Some(0),
+            None => None,
+        };
+        cursor.offset = Some(start);
+    }
+
+    process_log_stream(&mut reader, sess, sink, requests, log, cursor, forwarder)
+}
+
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock)
+}
+
+fn retry_on_timeout<T>(mut f: impl FnMut() -> Result<T, ssh2::Error>) -> io::Result<T> {
+    loop {
+        match f() {
+            Ok(value) => return Ok(value),
+            Err(e) if e.code() == ssh2::ErrorCode::Session(LIBSSH2_ERROR_TIMEOUT) => continue,
+            Err(e) => return Err(io::Error::other(e)),
+        }
+    }
+}
+
+// 在独立的 channel 上执行一次性命令, 期间关闭会话超时
+fn run_remote(sess: &Session, command: &str) -> Result<String, String> {
+    sess.set_timeout(0);
+    let result = (|| -> Result<String, ssh2::Error> {
+        let mut channel = sess.channel_session()?;
+        channel.exec(command)?;
+        let mut output = String::new();
+        let _ = channel.read_to_string(&mut output);
+        channel.wait_close()?;
+        Ok(output)
+    })();
+    sess.set_timeout(REQUEST_POLL_MS);
+    result.map_err(|e| e.to_string())
+}
+
+fn backfill_secs(log: &config::LogConfig) -> Option<u64> {
+    log.backfill
+        .as_deref()
+        .and_then(timestamp::parse_duration)
+        .map(|duration| duration.as_secs())
+}
+
+// 重连时不再回填, 避免重复; 多个文件交错输出, backfill 不适用
+fn multi_tail_command(patterns: &[&str], resumed: bool) -> String {
+    let patterns: Vec<String> = patterns.iter().map(|p| shell_glob_quote(p)).collect();
+    format!(
+        "exec tail -n {} -F {}",
+        if resumed { 0 } else { 100 },
+        patterns.join(" ")
+    )
+}
+
+// 重连时不再回填, 避免重复; since 只在首次连接时使用
+fn journal_command(log: &config::LogConfig, resumed: bool) -> String {
+    let output = match log.output.as_deref() {
+        Some("json") => "json",
+        _ => "short-iso",
+    };
+    let backfill = backfill_secs(log).filter(|_| !resumed && log.since.is_none());
+    let lines = match (resumed, backfill) {
+        (true, _) => "0".to_string(),
+        (false, Some(_)) => "all".to_string(),
+        (false, None) => "100".to_string(),
+    };
+    let mut command = format!("exec journalctl --no-pager -f -n {} -o {}", lines, output);
+    if let Some(unit) = &log.unit {
+        command.push_str(&format!(" -u {}", shell_quote(unit)));
+    }
+    if let Some(priority) = &log.priority {
+        command.push_str(&format!(" -p {}", shell_quote(priority)));
+    }
+    if let (Some(since), false) = (&log.since, resumed) {
+        command.push_str(&format!(" --since {}", shell_quote(since)));
+    } else if let Some(secs) = backfill {
+        command.push_str(&format!(" --since -{}s", secs));
+    }
+    command
+}
+
+// rootless 的远端机器没有 API socket 可用, 直接跟随 podman logs; 同样只在首次连接时回填
+fn podman_command(log: &config::LogConfig, resumed: bool) -> String {
+    let backfill = backfill_secs(log).filter(|_| !resumed && log.since.is_none());
+    let lines = match (resumed, backfill) {
+        (true, _) => 0,
+        (false, Some(_)) => -1,
+        (false, None) => 100,
+    };
+    let mut command = format!("exec podman logs -f --tail {}", lines);
+    if let (Some(since), false) = (&log.since, resumed) {
+        command.push_str(&format!(" --since {}", shell_quote(since)));
+    } else if let Some(secs) = backfill {
+        command.push_str(&format!(" --since {}s", secs));
+    }
+    command.push_str(&format!(
+        " {} 2>&1",
+        shell_quote(log.container.as_deref().unwrap_or_default())
+    ));
+    command
+}
 
-    {
-        let mut scroll_pos = scroll_position.lock().unwrap();
-        let content = content.lock().unwrap();
-        *scroll_pos = content.len().saturating_sub(1);
+// 只支持单个文件; 没有显式配置 decompress 时按扩展名判断
+fn is_compressed(log: &config::LogConfig) -> bool {
+    !log.log_path.is_multi()
+        && log.decompress.unwrap_or_else(|| {
+            let path = log.log_path.paths()[0];
+            path.ends_with(".gz") || path.ends_with(".zst")
+        })
+}
+
+// 压缩的历史日志整个解压输出, 之后跟随 live_path 指定的当前文件; 没有 live_path 时保持 channel 打开
+// 重连时只继续跟随, 不再重复解压
+fn compressed_command(log: &config::LogConfig, resumed: bool) -> String {
+    let path = log.log_path.paths()[0];
+    let follow = match &log.live_path {
+        Some(live) => format!(
+            "exec tail -n {} -F {}",
+            if resumed { "0" } else { "+1" },
+            shell_quote(live)
+        ),
+        None => "exec tail -f /dev/null".to_string(),
+    };
+    if resumed {
+        return follow;
     }
+    let decompress = if path.ends_with(".zst") { "zstd -dcq" } else { "gzip -dcf" };
+    format!("{} -- {}; {}", decompress, shell_quote(path), follow)
+}
+
+enum LineFormat {
+    Plain,
+    Files(FileDemux),
+    JournalJson,
+}
 
-    process_log_stream(
-        &mut reader,
-        content,
-        max_history,
-        scroll_position,
-        is_maximized,
-        connection_status,
-        &log.host,
+// journalctl -o json 的一条记录转换成 "时间 标识[PID]: 级别 消息"
+fn journal_line(line: &str) -> String {
+    let Ok(serde_json::Value::Object(entry)) = serde_json::from_str(line) else {
+        return line.to_string();
+    };
+    let field = |name: &str| match entry.get(name) {
+        Some(serde_json::Value::String(s)) => Some(s.clone()),
+        Some(serde_json::Value::Null) | None => None,
+        Some(other) => Some(other.to_string()),
+    };
+
+    let time = field("__REALTIME_TIMESTAMP")
+        .and_then(|micros| micros.parse::<i64>().ok())
+        .map(|micros| crate::timestamp::format_date_time(micros / 1000))
+        .unwrap_or_default();
+    let identifier = field("SYSLOG_IDENTIFIER")
+        .or_else(|| field("_SYSTEMD_UNIT"))
+        .unwrap_or_default();
+    let pid = field("_PID").map(|pid| format!("[{}]", pid)).unwrap_or_default();
+    let level = crate::syslog::severity_name(
+        field("PRIORITY").and_then(|p| p.parse().ok()).unwrap_or(6),
+    );
+    format!(
+        "{} {}{}: {} {}\n",
+        time,
+        identifier,
+        pid,
+        level,
+        field("MESSAGE").unwrap_or_default()
     )
 }
 
-fn authenticate(sess: &Session, log: &config::LogConfig) -> io::Result<()> {
-    let username = log.username.as_deref().unwrap_or("");
-    let result = if let Some(password) = &log.password {
-        sess.userauth_password(username, password)
-    } else if let Some(ssh_key) = &log.ssh_key {
-        let key_path = Path::new(ssh_key);
-        sess.userauth_pubkey_file(username, None, key_path, None)
+// tail 多文件时用 "==> path <==" 分隔, 把它转换成每行的文件名前缀
+struct FileDemux {
+    current_file: Option<String>,
+    pending_blank: Option<String>,
+}
+
+impl FileDemux {
+    fn new() -> Self {
+        FileDemux {
+            current_file: None,
+            pending_blank: None,
+        }
+    }
+
+    fn feed(&mut self, line: String) -> Vec<String> {
+        let trimmed = line.trim_end_matches(['\r', '\n']);
+        if let Some(path) = trimmed
+            .strip_prefix("==> ")
+            .and_then(|rest| rest.strip_suffix(" <=="))
+        {
+            let name = path.rsplit('/').next().unwrap_or(path);
+            self.current_file = Some(name.to_string());
+            self.pending_blank = None;
+            return Vec::new();
+        }
+
+        let mut lines: Vec<String> = self.pending_blank.take().into_iter().collect();
+        if trimmed.is_empty() {
+            // 文件头前面的空行由 tail 插入, 先暂存等下一行再决定
+            self.pending_blank = Some(self.prefixed(line));
+        } else {
+            lines.push(self.prefixed(line));
+        }
+        lines
+    }
+
+    fn prefixed(&self, line: String) -> String {
+        match &self.current_file {
+            Some(name) => format!("[{}] {}", name, line),
+            None => line,
+        }
+    }
+}
+
+// backfill: 以文件末尾最后一个 ISO 时间戳为准往前推, 和日志用同一个时区; 用 awk 找到第一行不早于该时间的偏移
+// 找不到 ISO 时间戳或远端 date 不支持 -d 时退回最后 100 行
+fn backfill_offset(secs: u64) -> String {
+    format!(
+        concat!(
+            "last=$(tail -c 262144 \"$p\" | grep -oE '[0-9]{{4}}-[0-9]{{2}}-[0-9]{{2}}[T ][0-9]{{2}}:[0-9]{{2}}:[0-9]{{2}}' | tail -n 1 | tr T ' '); ",
+            "cut=$(date -d \"@$(($(date -d \"$last\" +%s) - {}))\" '+%Y-%m-%d %H:%M:%S' 2>/dev/null); ",
+            "off=$(LC_ALL=C awk -v cut=\"$cut\" 'cut != \"\" && match($0, /[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9][T ][0-9][0-9]:[0-9][0-9]:[0-9][0-9]/) ",
+            "{{ t = substr($0, RSTART, 19); sub(/T/, \" \", t); if (t >= cut) {{ print n; found = 1; exit }} }} ",
+            "{{ n += length($0) + 1 }} END {{ if (!found) print -1 }}' \"$p\"); ",
+            "[ \"$off\" -ge 0 ] 2>/dev/null || off=$((size - $(tail -n 100 \"$p\" | wc -c)))"
+        ),
+        secs
+    )
+}
+
+// 首次连接回填最后 100 行(或 backfill 时间范围); 重连时从 offset 继续, 文件被截断/轮转则从头开始
+// 首次连接时起始偏移之前的行数用 head | wc -l 统计, 大文件上要读很久, 所以重连时沿用已知的行数
+fn tail_command(log_path: &str, offset: Option<u64>, backfill: Option<u64>, count_lines: bool) -> String {
+    let start = match (offset, backfill) {
+        (Some(offset), _) => format!(
+            "off={}; [ \"$size\" -lt \"$off\" ] && off=0",
+            offset
+        ),
+        (None, Some(secs)) => backfill_offset(secs),
+        (None, None) => "off=$((size - $(tail -n 100 \"$p\" | wc -c)))".to_string(),
+    };
+    let report = if count_lines && offset.is_none() {
+        "echo \"$off $(head -c \"$off\" \"$p\" | wc -l)\""
     } else {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            "No authentication method provided",
-        ));
+        "echo \"$off\""
     };
+    format!(
+        "p={}; size=$(wc -c < \"$p\"); {}; {}; exec tail -c +$((off + 1)) -f \"$p\"",
+        shell_quote(log_path),
+        start,
+        report
+    )
+}
 
-    result.map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
 }
 
-fn process_log_stream(
-    reader: &mut BufReader<ssh2::Channel>,
-    content: Arc<Mutex<Vec<String>>>,
-    max_history: usize,
-    scroll_position: Arc<Mutex<usize>>,
-    is_maximized: Arc<Mutex<bool>>,
-    connection_status: Arc<Mutex<ConnectionStatus>>,
-    host: &str,
-) -> io::Result<()> {
+// 通配符保持在引号外以便远端 shell 展开, 其余部分照常加引号
+pub fn shell_glob_quote(pattern: &str) -> String {
+    let mut quoted = String::new();
+    let mut literal = String::new();
+    for c in pattern.chars() {
+        if matches!(c, '*' | '?') {
+            if !literal.is_empty() {
+                quoted.push_str(&shell_quote(&literal));
+                literal.clear();
+            }
+            quoted.push(c);
+        } else {
+            literal.push(c);
+        }
+    }
+    if !literal.is_empty() {
+        quoted.push_str(&shell_quote(&literal));
+    }
+    quoted
+}
+
+// libssh2 直接读写 fd, 无法包装 TcpStream 计数, 所以经由 socketpair 中转来统计实际传输的字节数
+fn counting_relay(tcp: TcpStream, stats: Arc<Mutex<ConnectionStats>>) -> io::Result<UnixStream> {
+    let (local, remote) = UnixStream::pair()?;
+
+    let tcp_reader = tcp.try_clone()?;
+    let remote_writer = remote.try_clone()?;
+    let rx_stats = Arc::clone(&stats);
+    thread::spawn(move || {
+        copy_counting(tcp_reader, remote_writer, |n| {
+            rx_stats.lock().unwrap().bytes_received += n
+        })
+    });
+    thread::spawn(move || copy_counting(remote, tcp, |n| stats.lock().unwrap().bytes_sent += n));
+
+    Ok(local)
+}
+
+fn copy_counting<R, W, F>(mut reader: R, mut writer: W, count: F)
+where
+    R: Read + Shutdownable,
+    W: Write + Shutdownable,
+    F: Fn(u64),
+{
+    let mut buf = [0u8; 16 * 1024];
     loop {
-        let mut line = String::new();
-        match reader.read_line(&mut line) {
-            Ok(0) => break,
-            Ok(_) => update_content(&content, max_history, &scroll_position, &is_maximized, line),
-            Err(e) => {
-                let _ = update_connection_status(
-                    &connection_status,
-                    ConnectionStatus::Error(format!("Read Err ({}): {}", host, e)),
-                );
-                break;
+        match reader.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                if writer.write_all(&buf[..n]).is_err() {
+                    break;
+                }
+                count(n as u64);
             }
         }
     }
-    Ok(())
+    reader.shutdown_both();
+    writer.shutdown_both();
 }
 
-fn update_content(
-    content: &Arc<Mutex<Vec<String>>>,
-    max_history: usize,
-    scroll_position: &Arc<Mutex<usize>>,
-    is_maximized: &Arc<Mutex<bool>>,
-    line: String,
-) {
-    let mut content = content.lock().unwrap();
-    content.push(line);
+trait Shutdownable {
+    fn shutdown_both(&self);
+}
 
-    while content.len() > max_history {
-        content.remove(0);
+impl Shutdownable for TcpStream {
+    fn shutdown_both(&self) {
+        let _ = self.shutdown(Shutdown::Both);
     }
+}
 
-    let mut scroll_pos = scroll_position.lock().unwrap();
-    let is_max = *is_maximized.lock().unwrap();
+impl Shutdownable for UnixStream {
+    fn shutdown_both(&self) {
+        let _ = self.shutdown(Shutdown::Both);
+    }
+}
 
-    if !is_max {
-        *scroll_pos = content.len().saturating_sub(1);
-    } else {
-        if *scroll_pos == content.len().saturating_sub(2) {
-            *scroll_pos = content.len().saturating_sub(1);
+fn set_algorithms(sess: &Session, algorithms: &config::SshAlgorithms) -> Result<(), ssh2::Error> {
+    let prefs = [
+        (&algorithms.kex, &[MethodType::Kex][..]),
+        (&algorithms.host_key, &[MethodType::HostKey][..]),
+        (&algorithms.ciphers, &[MethodType::CryptCs, MethodType::CryptSc][..]),
+        (&algorithms.macs, &[MethodType::MacCs, MethodType::MacSc][..]),
+    ];
+
+    let legacy = algorithms.legacy.unwrap_or(false);
+    for (names, method_types) in prefs {
+        for method_type in method_types {
+            let prefs = match names {
+                Some(names) => names.join(","),
+                None if legacy => sess.supported_algs(*method_type)?.join(","),
+                None => continue,
+            };
+            sess.method_pref(*method_type, &prefs)?;
         }
     }
+    Ok(())
 }
 
-fn update_connection_status(
-    connection_status: &Mutex<ConnectionStatus>,
-    status: ConnectionStatus,
-) -> io::Result<()> {
-    let mut status_lock = connection_status
-        .lock()
-        .map_err(|_| io::Error::new(io::ErrorKind::Other, "Failed to lock connection status"))?;
-    *status_lock = status;
-    Ok(())
+// 按读取的字节数限速, 读得太快时 sleep, 让 SSH 窗口反压到远端
+struct Throttle {
+    bytes_per_sec: u64,
+    started: Instant,
+    bytes: u64,
+}
+
+impl Throttle {
+    fn new(max_kbps: u64) -> Self {
+        Throttle {
+            bytes_per_sec: max_kbps.max(1) * 1024,
+            started: Instant::now(),
+            bytes: 0,
+        }
+    }
+
+    fn consume(&mut self, len: usize) {
+        self.bytes += len as u64;
+        let due = Duration::from_millis(self.bytes * 1000 / self.bytes_per_sec);
+        let elapsed = self.started.elapsed();
+        if due > elapsed {
+            thread::sleep(due - elapsed);
+        }
+        // 每秒重置一次, 避免空闲一段时间后积攒的额度造成突发
+        if self.started.elapsed() >= Duration::from_secs(1) {
+            self.started = Instant::now();
+            self.bytes = 0;
+        }
+    }
+}
+
+fn process_log_stream(
+    reader: &mut BufReader<ssh2::Channel>,
+    sess: &Session,
+    sink: &LogSink,
+    requests: &Receiver<SourceRequest>,
+    log: &config::LogConfig,
+    cursor: &mut TailCursor,
+    forwarder: &mut Option<Forwarder>,
+) -> io::Result<StreamEnd> {
+    let host = &log.host;
+    let journal = log.source == config::SourceType::Journal;
+    let files = log.source == config::SourceType::Ssh && !is_compressed(log);
+    let mut format = if journal && log.output.as_deref() == Some("json") {
+        LineFormat::JournalJson
+    } else if files && log.log_path.is_multi() {
+        LineFormat::Files(FileDemux::new())
+    } else {
+        LineFormat::Plain
+    };
+    let host_name: Arc<str> = Arc::from(host.as_str());
+    let path: Option<Arc<str>> =
+        (files && matches!(format, LineFormat::Plain)).then(|| Arc::from(log.log_path.paths()[0]));
+    let mut line = Vec::new();
+    let mut download: Option<ActiveDownload> = None;
+    let mut throttle = log.max_kbps.map(Throttle::new);
+    loop {
+        while let Ok(request) = requests.try_recv() {
+            match request {
+                SourceRequest::Exec { command, reply } => {
+                    let _ = reply.send(run_remote(sess, &command));
+                }
+                SourceRequest::SwitchPath(path) => return Ok(StreamEnd::SwitchPath(path)),
+                SourceRequest::Refresh => return Ok(StreamEnd::Refresh),
+                SourceRequest::Suspend => return Ok(StreamEnd::Suspend),
+                SourceRequest::Resume => {}
+                SourceRequest::Download(local_path) => {
+                    // 先结束旧的下载, 避免它的 Drop 改写新下载的进度
+                    drop(download.take());
+                    download = start_download(sess, log, local_path, &sink.download);
+                }
+            }
+        }
+
+        // 每轮只拷贝一个分块, 下载期间 tail 照常进行
+        if let Some(active) = download.as_mut() {
+            sess.set_timeout(0);
+            let more = active.copy_chunk();
+            sess.set_timeout(REQUEST_POLL_MS);
+            if !more {
+                download = None;
+            }
+        }
+
+        // 有转发连接时缩短读超时, 避免转发的数据要等待一整个轮询周期
+        if let Some(forwarder) = forwarder.as_mut() {
+            forwarder.poll(sess);
+            sess.set_timeout(if forwarder.is_active() {
+                FORWARD_POLL_MS
+            } else {
+                REQUEST_POLL_MS
+            });
+        }
+
+        // 超时返回时已读到的半行会保留在 line 中, 下次继续追加
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => {
+                sink.set_status(ConnectionStatus::Error(format!(
+                    "{} ({})",
+                    tr(Msg::Disconnected),
+                    host
+                )));
+                break;
+            }
+            Ok(_) => {
+                let bytes = std::mem::take(&mut line);
+                let position = path.as_ref().zip(cursor.offset).map(|(path, offset)| Position {
+                    host: Arc::clone(&host_name),
+                    path: Arc::clone(path),
+                    offset,
+                    line: cursor.lines.map(|l| l + 1),
+                });
+                cursor.offset = cursor.offset.map(|o| o + bytes.len() as u64);
+                cursor.lines = cursor.lines.map(|l| l + 1);
+                if let Some(throttle) = throttle.as_mut() {
+                    throttle.consume(bytes.len());
+                }
+                let line = sink.decode(&bytes);
+                match &mut format {
+                    LineFormat::Files(demux) => {
+                        demux.feed(line).into_iter().for_each(|l| sink.push_line(l))
+                    }
+                    LineFormat::JournalJson => sink.push_line(journal_line(&line)),
+                    LineFormat::Plain => sink.push_line_at(line, position),
+                }
+            }
+            Err(e) if is_timeout(&e) => {}
+            Err(e) => {
+                sink.set_status(ConnectionStatus::Error(format!(
+                    "{} ({}): {}",
+                    tr(Msg::ReadErr),
+                    host,
+                    e
+                )));
+                break;
+            }
+        }
+    }
+    Ok(StreamEnd::Closed)
 }
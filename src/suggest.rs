@@ -0,0 +1,96 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashMap;
+
+use crate::config::HighlightConfig;
+
+static LEVEL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b(FATAL|ERROR|FAILURE|WARNING|WARN|SEVERE|CRITICAL)\b").unwrap());
+
+// 会随每条日志变化的部分: 引号字符串、UUID、十六进制、数字
+static VARIABLE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#""[^"]*"|'[^']*'|\b[0-9a-fA-F]{8}(?:-[0-9a-fA-F]{4}){3}-[0-9a-fA-F]{12}\b|\b0x[0-9a-fA-F]+\b|\d+(?:\.\d+)*"#)
+        .unwrap()
+});
+
+// 模板只取级别之后的一段, 太长的尾部通常是各不相同的参数
+const TEMPLATE_CHARS: usize = 80;
+const MAX_SUGGESTIONS: usize = 20;
+
+pub struct Suggestion {
+    pub rule: HighlightConfig,
+    pub count: usize,
+    pub example: String,
+    pub saved: bool,
+}
+
+// 把可变部分替换成对应的正则, 其余部分转义, 得到可以匹配同类日志的模式
+pub fn template(line: &str) -> Option<String> {
+    let level = LEVEL.find(line)?;
+    let mut rest = &line[level.start()..];
+    if let Some((end, _)) = rest.char_indices().nth(TEMPLATE_CHARS) {
+        rest = &rest[..end];
+    }
+
+    let mut pattern = String::new();
+    let mut last = 0;
+    for m in VARIABLE.find_iter(rest) {
+        pattern.push_str(&regex::escape(&rest[last..m.start()]));
+        pattern.push_str(match m.as_str().chars().next() {
+            Some('"') => r#""[^"]*""#,
+            Some('\'') => r"'[^']*'",
+            _ if m.as_str().len() == 36 => r"[0-9a-fA-F-]{36}",
+            _ if m.as_str().starts_with("0x") => r"0x[0-9a-fA-F]+",
+            _ => r"\d+(?:\.\d+)*",
+        });
+        last = m.end();
+    }
+    pattern.push_str(&regex::escape(rest[last..].trim_end()));
+    Some(pattern)
+}
+
+// 可变部分统一替换成 #, 用于比较不同来源输出的同类日志
+pub fn normalize(line: &str) -> String {
+    VARIABLE
+        .replace_all(line.trim_end_matches(['\r', '\n']), "#")
+        .trim()
+        .to_string()
+}
+
+fn color_for(pattern: &str) -> &'static str {
+    if pattern.starts_with("FATAL") || pattern.starts_with("ERROR") || pattern.starts_with("CRITICAL") {
+        "red"
+    } else if pattern.starts_with("WARN") {
+        "yellow"
+    } else {
+        "magenta"
+    }
+}
+
+// 按模板聚类缓冲区中带告警级别的行, 出现次数多的排在前面
+pub fn suggest_rules(lines: &[String]) -> Vec<Suggestion> {
+    let mut clusters: HashMap<String, (usize, &str)> = HashMap::new();
+    for line in lines {
+        let line = line.trim_end_matches(['\r', '\n']);
+        if let Some(pattern) = template(line) {
+            clusters.entry(pattern).or_insert((0, line)).0 += 1;
+        }
+    }
+
+    let mut suggestions: Vec<Suggestion> = clusters
+        .into_iter()
+        .map(|(pattern, (count, example))| Suggestion {
+            rule: HighlightConfig {
+                color: Some(color_for(&pattern).to_string()),
+                pattern,
+                alert: None,
+            },
+            count,
+            example: example.to_string(),
+            saved: false,
+        })
+        .collect();
+    suggestions.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.rule.pattern.cmp(&b.rule.pattern)));
+    suggestions.truncate(MAX_SUGGESTIONS);
+    suggestions
+}
@@ -0,0 +1,226 @@
+use std::io::{self, BufRead, BufReader, Read};
+use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::command::handle_request;
+use crate::config;
+use crate::i18n::{tr, Msg};
+use crate::ssh::{ConnectionStatus, LogSink, SourceRequest};
+use crate::timestamp;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const MAX_FRAME: usize = 64 * 1024;
+
+const FACILITIES: [&str; 24] = [
+    "kern", "user", "mail", "daemon", "auth", "syslog", "lpr", "news", "uucp", "cron", "authpriv",
+    "ftp", "ntp", "audit", "alert", "clock", "local0", "local1", "local2", "local3", "local4",
+    "local5", "local6", "local7",
+];
+
+// 严重级别映射成格式化规则能识别的关键字
+pub fn severity_name(severity: u8) -> &'static str {
+    match severity {
+        0..=2 => "FATAL",
+        3 => "ERROR",
+        4 => "WARN",
+        5 => "NOTICE",
+        7 => "DEBUG",
+        _ => "INFO",
+    }
+}
+
+struct Frame {
+    peer: SocketAddr,
+    data: Vec<u8>,
+}
+
+pub fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+// 解析 RFC5424 和 RFC3164 报文, 输出 "时间 主机 应用[进程]: facility.级别 消息"
+fn format_message(frame: &str, peer: &SocketAddr) -> String {
+    let frame = frame.trim_end_matches(['\r', '\n', '\0']);
+    let Some((pri, rest)) = frame
+        .strip_prefix('<')
+        .and_then(|s| s.split_once('>'))
+        .and_then(|(pri, rest)| Some((pri.parse::<u8>().ok()?, rest)))
+    else {
+        return format!(
+            "{} {}: {}\n",
+            timestamp::format_date_time(now_millis()),
+            peer.ip(),
+            frame
+        );
+    };
+    let facility = FACILITIES
+        .get((pri >> 3) as usize)
+        .copied()
+        .unwrap_or("unknown");
+    let level = severity_name(pri & 7);
+
+    // RFC5424: VERSION TIMESTAMP HOSTNAME APP-NAME PROCID MSGID STRUCTURED-DATA MSG
+    if let Some(rest) = rest.strip_prefix("1 ") {
+        let mut fields = rest.splitn(6, ' ');
+        let mut next = || fields.next().filter(|f| *f != "-");
+        let time = next().map(|t| t.replacen('T', " ", 1));
+        let host = next()
+            .map(str::to_string)
+            .unwrap_or_else(|| peer.ip().to_string());
+        let app = next().unwrap_or_default().to_string();
+        let proc_id = next().map(|p| format!("[{}]", p)).unwrap_or_default();
+        let _msg_id = next();
+        let message = fields_rest(next());
+        return format!(
+            "{} {} {}{}: {}.{} {}\n",
+            time.unwrap_or_else(|| timestamp::format_date_time(now_millis())),
+            host,
+            app,
+            proc_id,
+            facility,
+            level,
+            message
+        );
+    }
+
+    // RFC3164: "Mmm dd hh:mm:ss HOST TAG: MSG", 时间不带年份, 改用接收时间
+    let bytes = rest.as_bytes();
+    let has_time = bytes.len() > 16 && bytes[3] == b' ' && bytes[15] == b' ';
+    let (host, body) = match rest
+        .get(16..)
+        .filter(|_| has_time)
+        .and_then(|r| r.split_once(' '))
+    {
+        Some((host, body)) => (host.to_string(), body),
+        None => (peer.ip().to_string(), rest),
+    };
+    format!(
+        "{} {} {}.{} {}\n",
+        timestamp::format_date_time(now_millis()),
+        host,
+        facility,
+        level,
+        body
+    )
+}
+
+// STRUCTURED-DATA 和 MSG 一起保留, 只去掉空的 "-"
+fn fields_rest(rest: Option<&str>) -> String {
+    match rest {
+        Some(rest) => rest
+            .strip_prefix("- ")
+            .unwrap_or(rest)
+            .trim_start_matches('\u{feff}')
+            .to_string(),
+        None => String::new(),
+    }
+}
+
+fn listen_udp(address: &str, frames: Sender<Frame>) -> io::Result<()> {
+    let socket = UdpSocket::bind(address)?;
+    thread::spawn(move || {
+        let mut buf = vec![0u8; MAX_FRAME];
+        while let Ok((n, peer)) = socket.recv_from(&mut buf) {
+            let frame = Frame {
+                peer,
+                data: buf[..n].to_vec(),
+            };
+            if frames.send(frame).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(())
+}
+
+// TCP 同时支持换行分隔和 RFC6587 的长度前缀两种分帧方式
+fn read_tcp(stream: TcpStream, frames: Sender<Frame>) {
+    let Ok(peer) = stream.peer_addr() else {
+        return;
+    };
+    let mut reader = BufReader::new(stream);
+    loop {
+        let starts_with_digit = match reader.fill_buf() {
+            Ok([]) | Err(_) => return,
+            Ok(buf) => buf[0].is_ascii_digit(),
+        };
+        let mut data = Vec::new();
+        if starts_with_digit {
+            let mut length = Vec::new();
+            if reader.read_until(b' ', &mut length).is_err() {
+                return;
+            }
+            let Some(length) = std::str::from_utf8(&length)
+                .ok()
+                .and_then(|l| l.trim().parse::<usize>().ok())
+                .filter(|l| *l <= MAX_FRAME)
+            else {
+                return;
+            };
+            data.resize(length, 0);
+            if reader.read_exact(&mut data).is_err() {
+                return;
+            }
+        } else if !matches!(reader.read_until(b'\n', &mut data), Ok(n) if n > 0) {
+            return;
+        }
+        if frames.send(Frame { peer, data }).is_err() {
+            return;
+        }
+    }
+}
+
+fn listen_tcp(address: &str, frames: Sender<Frame>) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let frames = frames.clone();
+            thread::spawn(move || read_tcp(stream, frames));
+        }
+    });
+    Ok(())
+}
+
+pub fn listen(log: &config::LogConfig, sink: LogSink, requests: Receiver<SourceRequest>) {
+    let address = log
+        .listen
+        .clone()
+        .unwrap_or_else(|| "0.0.0.0:5514".to_string());
+    let protocol = log.protocol.clone().unwrap_or_else(|| "udp".to_string());
+    let (frame_sender, frames) = mpsc::channel();
+
+    let result = match protocol.as_str() {
+        "udp" => listen_udp(&address, frame_sender),
+        "tcp" => listen_tcp(&address, frame_sender),
+        _ => listen_udp(&address, frame_sender.clone())
+            .and_then(|_| listen_tcp(&address, frame_sender)),
+    };
+    if let Err(e) = result {
+        sink.set_status(ConnectionStatus::Error(format!(
+            "{} ({}): {}",
+            tr(Msg::ListenErr),
+            address,
+            e
+        )));
+    }
+
+    loop {
+        match frames.recv_timeout(POLL_INTERVAL) {
+            Ok(frame) => {
+                sink.stats.lock().unwrap().bytes_received += frame.data.len() as u64;
+                let text = sink.decode(&frame.data);
+                sink.push_line(format_message(&text, &frame.peer));
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => thread::sleep(POLL_INTERVAL),
+        }
+        while let Ok(request) = requests.try_recv() {
+            handle_request(request, &sink);
+        }
+    }
+}
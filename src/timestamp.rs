@@ -0,0 +1,273 @@
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+static DATE_TIME: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(\d{4})-(\d{2})-(\d{2})[ T](\d{2}):(\d{2}):(\d{2})(?:[.,](\d{1,9}))?").unwrap()
+});
+// "Oct 14 09:30:00", RFC3164 的 syslog 时间不带年份
+static SYSLOG: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b([A-Z][a-z]{2}) {1,2}(\d{1,2}) (\d{2}):(\d{2}):(\d{2})(?:\.(\d{1,9}))?").unwrap()
+});
+// "14/Oct/2026:09:30:00 +0000", nginx/apache 的访问日志
+static NGINX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b(\d{2})/([A-Z][a-z]{2})/(\d{4}):(\d{2}):(\d{2}):(\d{2})").unwrap()
+});
+// 2001 到 2033 年之间的秒或毫秒
+static EPOCH: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b(1\d{9})(?:(\d{3})|\.(\d{1,9}))?\b").unwrap());
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Iso8601,
+    Syslog,
+    Nginx,
+    Epoch,
+}
+
+const FORMATS: [Format; 4] = [Format::Iso8601, Format::Syslog, Format::Nginx, Format::Epoch];
+
+fn fraction_millis(fraction: Option<regex::Match>) -> i64 {
+    fraction
+        .map(|m| {
+            let digits = &m.as_str()[..m.as_str().len().min(3)];
+            digits.parse::<i64>().unwrap_or(0) * 10_i64.pow(3 - digits.len() as u32)
+        })
+        .unwrap_or(0)
+}
+
+fn month(name: &str) -> Option<i64> {
+    MONTHS.iter().position(|m| *m == name).map(|i| i as i64 + 1)
+}
+
+fn current_year() -> i64 {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0);
+    civil_from_days(millis.div_euclid(86_400_000)).0
+}
+
+fn civil_millis(caps: &Captures, date: [i64; 3], time: [usize; 3], fraction: usize) -> Option<i64> {
+    let field = |i: usize| caps[i].parse::<i64>().ok();
+    let days = days_from_civil(date[0], date[1], date[2]);
+    let seconds = days * 86_400 + field(time[0])? * 3600 + field(time[1])? * 60 + field(time[2])?;
+    Some(seconds * 1000 + fraction_millis(caps.get(fraction)))
+}
+
+impl Format {
+    // 行内第一个该格式的时间戳, 返回毫秒; 不处理时区, 只用于同一窗口内的相对比较
+    pub fn parse(self, line: &str) -> Option<i64> {
+        match self {
+            Format::Iso8601 => {
+                let caps = DATE_TIME.captures(line)?;
+                let field = |i: usize| caps[i].parse::<i64>().ok();
+                civil_millis(&caps, [field(1)?, field(2)?, field(3)?], [4, 5, 6], 7)
+            }
+            Format::Syslog => {
+                let caps = SYSLOG.captures(line)?;
+                let date = [current_year(), month(&caps[1])?, caps[2].parse().ok()?];
+                civil_millis(&caps, date, [3, 4, 5], 6)
+            }
+            Format::Nginx => {
+                let caps = NGINX.captures(line)?;
+                let date = [caps[3].parse().ok()?, month(&caps[2])?, caps[1].parse().ok()?];
+                civil_millis(&caps, date, [4, 5, 6], 7)
+            }
+            Format::Epoch => {
+                let caps = EPOCH.captures(line)?;
+                let seconds = caps[1].parse::<i64>().ok()?;
+                let millis = match caps.get(2) {
+                    Some(m) => m.as_str().parse().ok()?,
+                    None => fraction_millis(caps.get(3)),
+                };
+                Some(seconds * 1000 + millis)
+            }
+        }
+    }
+}
+
+// 统计最早的若干行分别能被哪种格式解析, 某种格式先命中足够多行就固定下来
+const SAMPLE_LINES: usize = 50;
+const MIN_HITS: usize = 5;
+
+#[derive(Default)]
+struct Sample {
+    seen: usize,
+    hits: [usize; 4],
+    decided: Option<Format>,
+}
+
+pub struct Detector {
+    configured: Option<Format>,
+    sample: Mutex<Sample>,
+}
+
+impl Detector {
+    pub fn new(configured: Option<Format>) -> Self {
+        Detector {
+            configured,
+            sample: Mutex::new(Sample::default()),
+        }
+    }
+
+    pub fn observe(&self, line: &str) {
+        if self.configured.is_some() {
+            return;
+        }
+        let mut sample = self.sample.lock().unwrap();
+        if sample.decided.is_some() || sample.seen >= SAMPLE_LINES {
+            return;
+        }
+        sample.seen += 1;
+        for (i, format) in FORMATS.iter().enumerate() {
+            if format.parse(line).is_some() {
+                sample.hits[i] += 1;
+            }
+        }
+        // 同样多时按 FORMATS 的顺序优先
+        let best = (0..FORMATS.len()).rev().max_by_key(|&i| sample.hits[i]).unwrap_or(0);
+        if sample.hits[best] >= MIN_HITS || (sample.seen == SAMPLE_LINES && sample.hits[best] > 0) {
+            sample.decided = Some(FORMATS[best]);
+        }
+    }
+
+    // 还没有识别出格式时按 ISO8601 处理
+    pub fn format(&self) -> Format {
+        self.configured
+            .or(self.sample.lock().unwrap().decided)
+            .unwrap_or(Format::Iso8601)
+    }
+}
+
+pub fn detect(lines: &[String]) -> Format {
+    let detector = Detector::new(None);
+    lines.iter().take(SAMPLE_LINES).for_each(|line| detector.observe(line));
+    let sample = detector.sample.lock().unwrap();
+    match sample.decided {
+        Some(format) => format,
+        // 不足 SAMPLE_LINES 行时取命中最多的格式
+        None => (0..FORMATS.len())
+            .rev()
+            .max_by_key(|&i| sample.hits[i])
+            .filter(|&i| sample.hits[i] > 0)
+            .map_or(Format::Iso8601, |i| FORMATS[i]),
+    }
+}
+
+// 公历日期到 1970-01-01 的天数 (Howard Hinnant 的 days_from_civil)
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// "14:02" 或 "14:02:30", 返回当天零点起的毫秒数
+pub fn parse_time_of_day(value: &str) -> Option<i64> {
+    let parts: Vec<i64> = value.trim().split(':').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+    let (hour, minute, second) = match parts[..] {
+        [hour, minute] => (hour, minute, 0),
+        [hour, minute, second] => (hour, minute, second),
+        _ => return None,
+    };
+    if !(0..24).contains(&hour) || !(0..60).contains(&minute) || !(0..60).contains(&second) {
+        return None;
+    }
+    Some(((hour * 60 + minute) * 60 + second) * 1000)
+}
+
+// "90s" "30m" "2h" "7d" 形式的时长, 不带单位按秒
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => value.split_at(i),
+        None => (value, "s"),
+    };
+    let seconds = number.parse::<u64>().ok()?
+        * match unit.trim() {
+            "s" => 1,
+            "m" => 60,
+            "h" => 3600,
+            "d" => 86_400,
+            _ => return None,
+        };
+    Some(Duration::from_secs(seconds))
+}
+
+// 没有时间戳的行(如堆栈)沿用前面最近一行的时间
+pub fn line_millis(lines: &[String], index: usize, format: Format) -> Option<i64> {
+    lines[..=index.min(lines.len().checked_sub(1)?)]
+        .iter()
+        .rev()
+        .take(LOOKBACK_LINES)
+        .find_map(|line| format.parse(line))
+}
+
+const LOOKBACK_LINES: usize = 50;
+
+// 假设日志按时间递增, 二分查找与 millis 最接近的行
+pub fn nearest_line(lines: &[String], millis: i64, format: Format) -> Option<usize> {
+    if lines.is_empty() {
+        return None;
+    }
+
+    let (mut low, mut high) = (0, lines.len());
+    while low < high {
+        let mid = (low + high) / 2;
+        match line_millis(lines, mid, format) {
+            Some(t) if t < millis => low = mid + 1,
+            Some(_) => high = mid,
+            None => low = mid + 1,
+        }
+    }
+
+    let after = low.min(lines.len() - 1);
+    let before = low.saturating_sub(1);
+    let distance = |i: usize| line_millis(lines, i, format).map(|t| (t - millis).abs());
+    match (distance(before), distance(after)) {
+        (Some(b), Some(a)) if b <= a => Some(before),
+        (_, Some(_)) => Some(after),
+        (Some(_), None) => Some(before),
+        (None, None) => None,
+    }
+}
+
+pub fn format_date_time(millis: i64) -> String {
+    let (year, month, day) = civil_from_days(millis.div_euclid(86_400_000));
+    format!("{:04}-{:02}-{:02} {}", year, month, day, format_time_of_day(millis))
+}
+
+// days_from_civil 的逆运算
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+pub fn format_time_of_day(millis: i64) -> String {
+    let day_millis = millis.rem_euclid(86_400_000);
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        day_millis / 3_600_000,
+        day_millis / 60_000 % 60,
+        day_millis / 1000 % 60,
+        day_millis % 1000
+    )
+}
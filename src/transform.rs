@@ -0,0 +1,161 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::config::TransformConfig;
+use crate::fields::json_fields;
+use crate::grok::Grok;
+
+static ANSI_ESCAPE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\x1b\[[0-9;?]*[A-Za-z]|\x1b\][^\x07\x1b]*(\x07|\x1b\\)").unwrap());
+
+enum Transform {
+    Redact { pattern: Regex, replacement: String },
+    StripAnsi,
+    JsonFlatten,
+    Prefix(String),
+    Script(Mutex<Script>),
+    Grok(Grok),
+}
+
+// 常驻的外部脚本, 每写入一行就读回一行
+struct Script {
+    command: String,
+    process: Option<(Child, ChildStdin, BufReader<ChildStdout>)>,
+}
+
+impl Script {
+    fn spawn(command: &str) -> Option<(Child, ChildStdin, BufReader<ChildStdout>)> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let stdin = child.stdin.take()?;
+        let stdout = BufReader::new(child.stdout.take()?);
+        Some((child, stdin, stdout))
+    }
+
+    // 脚本退出或出错时原样放行, 下一行再尝试重启
+    fn apply(&mut self, line: String) -> String {
+        if self.process.is_none() {
+            self.process = Script::spawn(&self.command);
+        }
+        let Some((_, stdin, stdout)) = self.process.as_mut() else {
+            return line;
+        };
+
+        let mut output = String::new();
+        let ok = writeln!(stdin, "{}", line.trim_end_matches(['\r', '\n'])).is_ok()
+            && stdin.flush().is_ok()
+            && matches!(stdout.read_line(&mut output), Ok(n) if n > 0);
+        if !ok {
+            if let Some((mut child, _, _)) = self.process.take() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+            return line;
+        }
+        output
+    }
+}
+
+impl Drop for Script {
+    fn drop(&mut self) {
+        if let Some((mut child, _, _)) = self.process.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+// 按配置顺序在写入缓冲区之前处理每一行
+#[derive(Default)]
+pub struct Pipeline {
+    transforms: Vec<Transform>,
+}
+
+impl Pipeline {
+    pub fn new(configs: &[TransformConfig]) -> Result<Self, String> {
+        let transforms = configs
+            .iter()
+            .map(|config| {
+                Ok(match config {
+                    TransformConfig::Redact {
+                        pattern,
+                        replacement,
+                    } => Transform::Redact {
+                        pattern: Regex::new(pattern)
+                            .map_err(|e| format!("Invalid redact pattern {}: {}", pattern, e))?,
+                        replacement: replacement.clone().unwrap_or_else(|| "***".to_string()),
+                    },
+                    TransformConfig::StripAnsi => Transform::StripAnsi,
+                    TransformConfig::JsonFlatten => Transform::JsonFlatten,
+                    TransformConfig::Prefix { text } => Transform::Prefix(text.clone()),
+                    TransformConfig::Script { command } => Transform::Script(Mutex::new(Script {
+                        command: command.clone(),
+                        process: None,
+                    })),
+                    TransformConfig::Grok {
+                        pattern,
+                        patterns_files,
+                        definitions,
+                    } => Transform::Grok(Grok::compile(
+                        pattern,
+                        patterns_files.as_deref().unwrap_or_default(),
+                        &definitions.clone().unwrap_or_default(),
+                    )?),
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Pipeline { transforms })
+    }
+
+    pub fn apply(&self, mut line: String) -> String {
+        for transform in &self.transforms {
+            line = match transform {
+                Transform::Redact {
+                    pattern,
+                    replacement,
+                } => pattern.replace_all(&line, replacement.as_str()).into_owned(),
+                Transform::StripAnsi => ANSI_ESCAPE.replace_all(&line, "").into_owned(),
+                Transform::JsonFlatten => json_flatten(&line).unwrap_or(line),
+                Transform::Prefix(text) => format!("{}{}", text, line),
+                Transform::Script(script) => script.lock().unwrap().apply(line),
+                Transform::Grok(grok) => grok_line(grok, &line).unwrap_or(line),
+            };
+        }
+        line
+    }
+}
+
+// 不匹配的行原样保留
+fn grok_line(grok: &Grok, line: &str) -> Option<String> {
+    let trimmed = line.trim_end_matches(['\r', '\n']);
+    let mut record = grok.parse(trimmed)?;
+    record
+        .entry("message")
+        .or_insert_with(|| serde_json::Value::from(trimmed));
+    let newline = &line[trimmed.len()..];
+    Some(format!("{}{}", serde_json::Value::Object(record), newline))
+}
+
+// {"a":{"b":1},"msg":"x"} 展开为 a.b=1 msg=x, 非 JSON 行返回 None
+fn json_flatten(line: &str) -> Option<String> {
+    let trimmed = line.trim_end_matches(['\r', '\n']);
+    if !trimmed.trim_start().starts_with('{') {
+        return None;
+    }
+    let fields: Vec<String> = json_fields(trimmed)?
+        .into_iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect();
+    let newline = &line[trimmed.len()..];
+    Some(format!("{}{}", fields.join(" "), newline))
+}
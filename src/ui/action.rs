@@ -0,0 +1,1202 @@
+use super::layout::{
+    adjust_weight, cycle_layout, hide_selected, save_current_layout, selection_order, shown_windows,
+    toggle_collapsed, toggle_host_groups,
+};
+use super::state::{
+    AppState, Chip, FieldPopup, FilePicker, GlobalSearch, GrepPopup, Incremental, LineFilter, LogWindow, MacroKind,
+    Prompt, PromptKind, RulePopup, Search, SearchOptions, SearchesPopup, SharedPopup,
+    TimeCursor,
+};
+use crate::dedup;
+use crate::config;
+use crate::fields;
+use crate::history;
+use crate::i18n::{tr, Msg};
+use crate::silence;
+use crate::ssh::{shell_quote, SourceRequest};
+use crate::suggest;
+use crate::timestamp;
+
+use std::{
+    cell::Cell,
+    io,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use regex::{Regex, RegexBuilder};
+use tui::style::Color;
+
+// 显示在屏幕上的窗口算作在看; 空闲超过 idle_disconnect 的窗口断开连接, 重新显示时重连
+pub(super) fn suspend_idle(app_state: &mut AppState) {
+    let shown = shown_windows(app_state);
+    for (i, window) in app_state.log_windows.iter_mut().enumerate() {
+        let Some(idle) = window.idle_disconnect else {
+            continue;
+        };
+        let viewed = i == app_state.selected_window
+            || (!app_state.is_maximized && shown.contains(&i));
+        if viewed {
+            window.last_viewed = Instant::now();
+            if window.suspended {
+                window.suspended = false;
+                let _ = window.requests.send(SourceRequest::Resume);
+            }
+        } else if !window.suspended && window.last_viewed.elapsed() >= idle {
+            window.suspended = true;
+            let _ = window.requests.send(SourceRequest::Suspend);
+        }
+    }
+}
+
+// 按键先映射成 Action, 再由 update 统一修改状态
+pub(super) enum Action {
+    Quit,
+    ToggleMaximize,
+    RemoteGrepPrompt,
+    ReturnToGrep,
+    SearchPrompt,
+    SearchNext(bool),
+    ClearSearch,
+    GlobalSearchPrompt,
+    GlobalSearchKey(KeyCode),
+    LineFilterPrompt,
+    AdjustContext(isize),
+    PopFilter,
+    ClearFilters,
+    ExcludePrompt,
+    CycleSeverity,
+    Download,
+    SaveLayoutPrompt,
+    CycleLayout,
+    SuggestRules,
+    AdjustWeight(i32),
+    HideWindow,
+    ShowAllWindows,
+    ToggleHostGroups,
+    ToggleCollapsed,
+    ToggleTimeCursor,
+    MoveTimeCursor(isize),
+    OpenPicker,
+    ToggleInvisibles,
+    ToggleWrap,
+    ScrollColumns(isize),
+    ToggleMouse,
+    Scroll(ScrollDirection),
+    MoveSelection(MoveDirection),
+    ScrollToEnd,
+    OpenPager { with_archive: bool },
+    ToggleInspector,
+    ClearHistory,
+    Refresh,
+    ClearChip(usize),
+    ToggleMark,
+    ToggleFollow,
+    ToggleFollowErrors,
+    ToggleFreeze,
+    JumpToMark(bool),
+    ApplyPreset(usize),
+    PromptKey(KeyEvent),
+    PickerKey(KeyCode),
+    GrepKey(KeyCode),
+    RuleKey(KeyCode),
+    OpenShared,
+    OpenSearches,
+    SearchesKey(KeyCode),
+    SharedKey(KeyCode),
+    OpenFieldFilter,
+    CycleSampling,
+    BeginMacro(MacroKind),
+    MacroRegister(KeyCode),
+    StopRecording,
+    CopyPosition,
+    SilenceLine,
+    FieldKey(KeyCode),
+}
+
+// 需要终端参与的副作用交给 run_ui 执行
+pub(super) enum Effect {
+    Quit,
+    SetMouseCapture(bool),
+    OpenPager { with_archive: bool },
+    Replay(Vec<KeyEvent>),
+    Copy(String),
+}
+
+pub(super) fn update(app_state: &mut AppState, action: Action, window_height: usize) -> Option<Effect> {
+    match action {
+        Action::Quit => return Some(Effect::Quit),
+        Action::ToggleMaximize => {
+            app_state.is_maximized = !app_state.is_maximized;
+            app_state.set_follow(true);
+            app_state.show_inspector = false;
+            let window = &mut app_state.log_windows[app_state.selected_window];
+            let content_len = window.content.lock().unwrap().len();
+            let mut scroll_position = window.scroll_position.lock().unwrap();
+            *scroll_position = content_len.saturating_sub(1);
+        }
+        Action::RemoteGrepPrompt => {
+            app_state.prompt = Some(Prompt {
+                kind: PromptKind::RemoteGrep,
+                input: String::new(),
+            });
+        }
+        Action::SearchPrompt => {
+            let incremental = Incremental {
+                origin: app_state.view_top.get().filter(|_| app_state.is_maximized),
+                view: (app_state.is_maximized, app_state.follows()),
+                edited: None,
+                previous: app_state.search.take(),
+                recall: None,
+                draft: String::new(),
+            };
+            app_state.prompt = Some(Prompt {
+                kind: PromptKind::Search(incremental),
+                input: String::new(),
+            });
+        }
+        Action::SearchNext(forward) => jump_to_match(app_state, forward),
+        Action::ClearSearch => app_state.search = None,
+        Action::GlobalSearchPrompt => {
+            app_state.prompt = Some(Prompt {
+                kind: PromptKind::GlobalSearch,
+                input: String::new(),
+            });
+        }
+        Action::GlobalSearchKey(code) => handle_global_search_key(app_state, code),
+        Action::LineFilterPrompt => {
+            app_state.prompt = Some(Prompt {
+                kind: PromptKind::LineFilter,
+                input: String::new(),
+            });
+        }
+        Action::AdjustContext(delta) => {
+            let window = &mut app_state.log_windows[app_state.selected_window];
+            window.filter_context = window.filter_context.saturating_add_signed(delta).min(MAX_CONTEXT);
+        }
+        Action::PopFilter => {
+            let window = &mut app_state.log_windows[app_state.selected_window];
+            window.filters.pop();
+            sync_dropped(window);
+        }
+        Action::ClearFilters => {
+            let window = &mut app_state.log_windows[app_state.selected_window];
+            window.filters.clear();
+            sync_dropped(window);
+        }
+        Action::ExcludePrompt => {
+            app_state.prompt = Some(Prompt {
+                kind: PromptKind::Exclude,
+                input: String::new(),
+            });
+        }
+        Action::CycleSeverity => {
+            let window = &mut app_state.log_windows[app_state.selected_window];
+            window.severity = window.severity.next();
+        }
+        Action::ReturnToGrep => {
+            // 从跳转位置返回 grep 结果
+            if let Some(grep) = app_state.grep.as_mut() {
+                if let Some((is_maximized, follow)) = grep.return_view.take() {
+                    app_state.is_maximized = is_maximized;
+                    app_state.log_windows[app_state.selected_window].follow = follow;
+                }
+                grep.visible = true;
+            }
+        }
+        Action::Download => {
+            let window = &app_state.log_windows[app_state.selected_window];
+            if let Ok(path) = download_path(&window.name) {
+                let _ = window.requests.send(SourceRequest::Download(path));
+            }
+        }
+        Action::SaveLayoutPrompt => {
+            let input = app_state.layout_label.clone().unwrap_or_default();
+            app_state.prompt = Some(Prompt {
+                kind: PromptKind::SaveLayout,
+                input,
+            });
+        }
+        Action::CycleLayout => cycle_layout(app_state),
+        Action::SuggestRules => {
+            let window = &app_state.log_windows[app_state.selected_window];
+            let suggestions = suggest::suggest_rules(&window.content.lock().unwrap());
+            app_state.rule_popup = Some(RulePopup {
+                suggestions,
+                selected: 0,
+                message: None,
+            });
+        }
+        Action::OpenFieldFilter => {
+            let window = &app_state.log_windows[app_state.selected_window];
+            let fields = fields::observed_fields(&window.content.lock().unwrap());
+            app_state.field_popup = Some(FieldPopup {
+                fields,
+                selected: 0,
+                field: None,
+                value_selected: 0,
+            });
+        }
+        Action::CycleSampling => {
+            let window = &mut app_state.log_windows[app_state.selected_window];
+            window.sample = match window.sample {
+                1 => 10,
+                n if n < 1000 => n * 10,
+                _ => 1,
+            };
+            app_state.set_follow(true);
+        }
+        Action::BeginMacro(kind) => app_state.macro_pending = Some(kind),
+        Action::MacroRegister(code) => match (app_state.macro_pending.take(), code) {
+            (Some(MacroKind::Record), KeyCode::Char(register)) => {
+                app_state.recording = Some((register, Vec::new()));
+            }
+            (Some(MacroKind::Replay), KeyCode::Char(register)) => {
+                if let Some(keys) = app_state.macros.get(&register) {
+                    return Some(Effect::Replay(keys.clone()));
+                }
+            }
+            // 其它键取消
+            _ => {}
+        },
+        Action::StopRecording => {
+            if let Some((register, keys)) = app_state.recording.take() {
+                app_state.macros.insert(register, keys);
+            }
+        }
+        Action::CopyPosition => {
+            let line = anchor_line(app_state);
+            let window = &app_state.log_windows[app_state.selected_window];
+            match line.and_then(|line| window.position(line)) {
+                Some(position) => {
+                    let permalink = position.permalink();
+                    app_state.notice =
+                        Some((format!("{} {}", tr(Msg::Copied), permalink), Instant::now()));
+                    return Some(Effect::Copy(permalink));
+                }
+                None => app_state.notice = Some((tr(Msg::NoPosition).to_string(), Instant::now())),
+            }
+        }
+        Action::SilenceLine => silence_line(app_state),
+        Action::AdjustWeight(delta) => adjust_weight(app_state, delta),
+        Action::HideWindow => hide_selected(app_state),
+        Action::ToggleHostGroups => toggle_host_groups(app_state),
+        Action::ToggleCollapsed => toggle_collapsed(app_state),
+        Action::ShowAllWindows => {
+            app_state.visible = (0..app_state.log_windows.len()).collect();
+            app_state.weights = vec![1; app_state.visible.len()];
+        }
+        Action::ToggleTimeCursor => toggle_time_cursor(app_state),
+        Action::MoveTimeCursor(delta) => move_time_cursor(app_state, delta),
+        Action::OpenPicker => {
+            let window = &app_state.log_windows[app_state.selected_window];
+            app_state.file_picker = Some(FilePicker::open(window, window.browse_dir.clone()));
+        }
+        Action::ToggleWrap => {
+            let window = &mut app_state.log_windows[app_state.selected_window];
+            window.wrap = !window.wrap;
+            window.h_scroll = 0;
+        }
+        Action::ScrollColumns(columns) => scroll_columns(app_state, columns),
+        Action::ToggleInvisibles => {
+            app_state.show_invisibles = !app_state.show_invisibles;
+        }
+        Action::ToggleMouse => {
+            // 释放鼠标以便使用终端原生的选择/复制
+            app_state.mouse_captured = !app_state.mouse_captured;
+            return Some(Effect::SetMouseCapture(app_state.mouse_captured));
+        }
+        Action::Scroll(direction) => scroll_log(app_state, direction, window_height),
+        Action::MoveSelection(direction) => move_selection(app_state, direction),
+        Action::ScrollToEnd => {
+            let window = &mut app_state.log_windows[app_state.selected_window];
+            *window.paused_at.lock().unwrap() = None;
+            window.frozen = None;
+            window.follow = true;
+            if app_state.is_maximized {
+                scroll_log(app_state, ScrollDirection::Bottom, window_height);
+            }
+        }
+        Action::OpenPager { with_archive } => return Some(Effect::OpenPager { with_archive }),
+        Action::ToggleInspector => {
+            app_state.show_inspector = !app_state.show_inspector;
+        }
+        Action::ClearHistory => {
+            clear_history(app_state);
+            app_state.set_follow(true);
+        }
+        Action::Refresh => {
+            let window = &app_state.log_windows[app_state.selected_window];
+            let _ = window.requests.send(SourceRequest::Refresh);
+            app_state.set_follow(true);
+        }
+        Action::ClearChip(index) => clear_chip(app_state, index),
+        Action::ToggleMark => toggle_mark(app_state),
+        Action::ToggleFreeze => {
+            let window = &mut app_state.log_windows[app_state.selected_window];
+            window.frozen = match window.frozen {
+                Some(_) => None,
+                None => Some(window.stats.lock().unwrap().lines_received),
+            };
+        }
+        Action::ToggleFollow => {
+            let window = &mut app_state.log_windows[app_state.selected_window];
+            window.follow = !window.follow;
+        }
+        Action::ToggleFollowErrors => {
+            let window = &mut app_state.log_windows[app_state.selected_window];
+            window.follow_errors = !window.follow_errors;
+            app_state.set_follow(true);
+        }
+        Action::JumpToMark(forward) => jump_to_mark(app_state, forward),
+        Action::ApplyPreset(index) => apply_preset(app_state, index),
+        Action::PromptKey(key) => handle_prompt_key(app_state, key),
+        Action::PickerKey(code) => handle_picker_key(app_state, code),
+        Action::GrepKey(code) => handle_grep_key(app_state, code),
+        Action::RuleKey(code) => handle_rule_key(app_state, code),
+        Action::FieldKey(code) => handle_field_key(app_state, code),
+        Action::OpenShared => {
+            app_state.shared_popup = Some(SharedPopup {
+                shared: dedup::shared(),
+                selected: 0,
+            });
+        }
+        Action::SharedKey(code) => handle_shared_key(app_state, code),
+        Action::OpenSearches => app_state.searches_popup = Some(SearchesPopup { selected: 0 }),
+        Action::SearchesKey(code) => handle_searches_key(app_state, code),
+    }
+    None
+}
+
+fn toggle_time_cursor(app_state: &mut AppState) {
+    if app_state.time_cursor.take().is_some() {
+        return;
+    }
+    let window = app_state.selected_window;
+    let format = app_state.log_windows[window].timestamps.format();
+    let content = app_state.log_windows[window].content.lock().unwrap();
+    let Some(line) = content.len().checked_sub(1) else {
+        return;
+    };
+    let cursor = timestamp::line_millis(&content, line, format).map(|millis| TimeCursor {
+        window,
+        line,
+        millis,
+    });
+    drop(content);
+    app_state.time_cursor = cursor;
+}
+
+fn move_time_cursor(app_state: &mut AppState, delta: isize) {
+    let selected = app_state.selected_window;
+    let Some(cursor) = app_state.time_cursor.as_mut() else {
+        return;
+    };
+    let format = app_state.log_windows[selected].timestamps.format();
+    let content = app_state.log_windows[selected].content.lock().unwrap();
+    if content.is_empty() {
+        return;
+    }
+
+    // 切换窗口后先定位到当前时间在新窗口中的行
+    if cursor.window != selected {
+        cursor.window = selected;
+        cursor.line = timestamp::nearest_line(&content, cursor.millis, format).unwrap_or(content.len() - 1);
+    }
+    cursor.line = cursor
+        .line
+        .saturating_add_signed(delta)
+        .min(content.len() - 1);
+    if let Some(millis) = timestamp::line_millis(&content, cursor.line, format) {
+        cursor.millis = millis;
+    }
+}
+
+// 屏蔽光标所在行这一类日志的告警, 并解除由它触发的暂停
+fn silence_line(app_state: &mut AppState) {
+    let Some(line) = anchor_line(app_state) else {
+        return;
+    };
+    let window = &app_state.log_windows[app_state.selected_window];
+    let pattern = {
+        let content = window.content.lock().unwrap();
+        let Some(text) = content.get(line) else {
+            return;
+        };
+        silence::pattern_for(text)
+    };
+    if let Err(e) = silence::add(&pattern) {
+        app_state.notice = Some((e.to_string(), Instant::now()));
+        return;
+    }
+    for window in &app_state.log_windows {
+        let content = window.content.lock().unwrap();
+        let mut paused_at = window.paused_at.lock().unwrap();
+        if paused_at.and_then(|line| content.get(line)).is_some_and(|text| silence::is_silenced(text)) {
+            *paused_at = None;
+        }
+    }
+    let saved = match config::append_silence(&app_state.config_path, &pattern) {
+        Ok(()) => String::new(),
+        Err(e) => format!(" ({}: {})", tr(Msg::SaveErr), e),
+    };
+    app_state.notice = Some((format!("{} {}{}", tr(Msg::Silenced), pattern, saved), Instant::now()));
+}
+
+// 时间游标所在行, 其次是手动滚动后视图顶部的行, 否则是最新一行
+fn anchor_line(app_state: &AppState) -> Option<usize> {
+    let selected = app_state.selected_window;
+    if let Some(cursor) = app_state.time_cursor.as_ref().filter(|c| c.window == selected) {
+        return Some(cursor.line);
+    }
+    if app_state.is_maximized && !app_state.follows() {
+        if let Some(line) = app_state.view_top.get() {
+            return Some(line);
+        }
+    }
+    app_state.log_windows[selected].content.lock().unwrap().len().checked_sub(1)
+}
+
+// 标记时间游标或视图顶部的行, 已经标记过的取消
+fn toggle_mark(app_state: &mut AppState) {
+    let Some(line) = anchor_line(app_state) else {
+        return;
+    };
+    let window = &mut app_state.log_windows[app_state.selected_window];
+    let len = window.content.lock().unwrap().len();
+    let number = window.first_number(len) + line as u64;
+    let notice = match window.marks.binary_search(&number) {
+        Ok(i) => {
+            window.marks.remove(i);
+            Msg::Unmarked
+        }
+        Err(i) => {
+            window.marks.insert(i, number);
+            Msg::Marked
+        }
+    };
+    app_state.notice = Some((format!("{} #{}", tr(notice), line + 1), Instant::now()));
+}
+
+// ' 跳到下一个标记, ` 跳到上一个, 到头后回绕; 已经被淘汰的标记顺便清掉
+fn jump_to_mark(app_state: &mut AppState, forward: bool) {
+    let current = anchor_line(app_state);
+    let window = &mut app_state.log_windows[app_state.selected_window];
+    let len = window.content.lock().unwrap().len();
+    let first = window.first_number(len);
+    window.marks.retain(|&mark| mark >= first);
+    let current = first + current.unwrap_or(0) as u64;
+    let target = if forward {
+        window.marks.iter().find(|&&mark| mark > current).or(window.marks.first())
+    } else {
+        window.marks.iter().rev().find(|&&mark| mark < current).or(window.marks.last())
+    };
+    match target {
+        Some(&mark) => {
+            app_state.is_maximized = true;
+            app_state.set_follow(false);
+            app_state.jump_to_line.set(Some((mark - first) as usize));
+        }
+        None => app_state.notice = Some((tr(Msg::NoMarks).to_string(), Instant::now())),
+    }
+}
+
+// 从 origin 开始向下找第一个匹配; 把 origin 的上一行当作当前匹配, 越过末尾时同样提示已回绕
+fn start_search(app_state: &mut AppState, query: String, origin: Option<usize>) {
+    let regex = search_regex(app_state.search_options, &query);
+    app_state.search = Some(Search {
+        query,
+        regex,
+        line: origin.and_then(|line| line.checked_sub(1)),
+    });
+    jump_to_match(app_state, true);
+}
+
+// 不是合法的正则时按字面匹配
+fn search_regex(options: SearchOptions, query: &str) -> Regex {
+    let build = |pattern: &str| RegexBuilder::new(pattern).case_insensitive(options.ignore_case).build();
+    if options.literal { None } else { build(query).ok() }
+        .unwrap_or_else(|| build(&regex::escape(query)).unwrap())
+}
+
+// 从最新的行往前找, 与按 / 搜索后按 N 相同
+pub(super) fn search_in(app_state: &mut AppState, query: String) {
+    app_state.search = Some(Search {
+        regex: search_regex(app_state.search_options, &query),
+        query,
+        line: None,
+    });
+    jump_to_match(app_state, false);
+}
+
+const MAX_CONTEXT: usize = 50;
+
+// & 和 ! 都是在已有的过滤上再叠加一层, Esc 撤掉最后一层
+pub(super) fn push_filter(app_state: &mut AppState, pattern: String, exclude: bool) {
+    let regex = search_regex(app_state.search_options, &pattern);
+    add_filter(&mut app_state.log_windows[app_state.selected_window], pattern, regex, exclude);
+}
+
+fn add_filter(window: &mut LogWindow, pattern: String, regex: Regex, exclude: bool) {
+    window.filters.push(LineFilter {
+        pattern,
+        regex,
+        exclude,
+        hidden: Cell::new(0),
+    });
+    sync_dropped(window);
+}
+
+pub(super) fn clear_filters(app_state: &mut AppState, exclude: bool) {
+    let window = &mut app_state.log_windows[app_state.selected_window];
+    window.filters.retain(|filter| filter.exclude != exclude);
+    sync_dropped(window);
+}
+
+// drop_excluded 的窗口让数据源也丢弃 ! 过滤的行
+fn sync_dropped(window: &LogWindow) {
+    if let Some(dropped) = &window.dropped {
+        *dropped.lock().unwrap() = window
+            .filters
+            .iter()
+            .filter(|filter| filter.exclude)
+            .map(|filter| filter.regex.clone())
+            .collect();
+    }
+}
+
+// [[filters]] 里按键绑定的过滤, 已经生效时再按一次取消; 模式总是正则, 不受搜索框开关影响
+fn apply_preset(app_state: &mut AppState, index: usize) {
+    let preset = &app_state.filter_presets[index];
+    let Ok(regex) = Regex::new(&preset.pattern) else {
+        return;
+    };
+    let (pattern, exclude) = (preset.pattern.clone(), preset.exclude.unwrap_or(false));
+    let window = &mut app_state.log_windows[app_state.selected_window];
+    let active = window
+        .filters
+        .iter()
+        .position(|filter| filter.exclude == exclude && filter.pattern == pattern);
+    match active {
+        Some(i) => {
+            window.filters.remove(i);
+            sync_dropped(window);
+        }
+        None => add_filter(window, pattern, regex, exclude),
+    }
+}
+
+pub(super) fn handle_prompt_key(app_state: &mut AppState, key: KeyEvent) {
+    let Some(prompt) = app_state.prompt.as_mut() else {
+        return;
+    };
+    let code = key.code;
+    match code {
+        KeyCode::Char(c @ ('r' | 'c'))
+            if key.modifiers.contains(KeyModifiers::ALT) && matches!(prompt.kind, PromptKind::Search(_)) =>
+        {
+            let options = &mut app_state.search_options;
+            if c == 'r' {
+                options.literal = !options.literal;
+            } else {
+                options.ignore_case = !options.ignore_case;
+            }
+            if let PromptKind::Search(incremental) = &mut prompt.kind {
+                incremental.edited = Some(Instant::now());
+            }
+        }
+        KeyCode::Esc => {
+            if let Some(PromptKind::Search(incremental)) = app_state.prompt.take().map(|p| p.kind) {
+                cancel_search(app_state, incremental);
+            }
+        }
+        // 上键翻到更早的搜索词, 下键翻回来, 越过最新的一条时恢复原来的输入
+        KeyCode::Up | KeyCode::Down if matches!(prompt.kind, PromptKind::Search(_)) => {
+            let PromptKind::Search(incremental) = &mut prompt.kind else {
+                return;
+            };
+            let history = &app_state.search_history;
+            let recall = match (code, incremental.recall) {
+                (KeyCode::Up, None) if !history.is_empty() => Some(history.len() - 1),
+                (KeyCode::Up, Some(i)) => Some(i.saturating_sub(1)),
+                (KeyCode::Down, Some(i)) if i + 1 < history.len() => Some(i + 1),
+                (KeyCode::Down, Some(_)) => None,
+                _ => return,
+            };
+            if incremental.recall.is_none() {
+                incremental.draft = prompt.input.clone();
+            }
+            prompt.input = match recall {
+                Some(i) => history[i].clone(),
+                None => incremental.draft.clone(),
+            };
+            incremental.recall = recall;
+            incremental.edited = Some(Instant::now());
+        }
+        KeyCode::Backspace | KeyCode::Char(_) => {
+            if let KeyCode::Char(c) = code {
+                prompt.input.push(c);
+            } else {
+                prompt.input.pop();
+            }
+            if let PromptKind::Search(incremental) = &mut prompt.kind {
+                incremental.edited = Some(Instant::now());
+            }
+        }
+        KeyCode::Enter => {
+            let prompt = app_state.prompt.take().unwrap();
+            if prompt.input.is_empty() && !matches!(prompt.kind, PromptKind::Search(_)) {
+                return;
+            }
+            match prompt.kind {
+                PromptKind::RemoteGrep => {
+                    let window = &app_state.log_windows[app_state.selected_window];
+                    app_state.grep = Some(GrepPopup::open(window, prompt.input));
+                }
+                PromptKind::Search(incremental) => finish_search(app_state, prompt.input, incremental),
+                PromptKind::GlobalSearch => search_all(app_state, prompt.input),
+                PromptKind::LineFilter => push_filter(app_state, prompt.input, false),
+                PromptKind::Exclude => push_filter(app_state, prompt.input, true),
+                PromptKind::SaveLayout => save_current_layout(app_state, prompt.input),
+                PromptKind::EditRule => {
+                    let Some(popup) = app_state.rule_popup.as_mut() else {
+                        return;
+                    };
+                    match Regex::new(&prompt.input) {
+                        Ok(_) => {
+                            popup.suggestions[popup.selected].rule.pattern = prompt.input;
+                            popup.suggestions[popup.selected].saved = false;
+                            popup.message = None;
+                        }
+                        Err(e) => popup.message = Some((e.to_string(), Color::Red)),
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+pub(super) fn handle_grep_key(app_state: &mut AppState, code: KeyCode) {
+    let Some(grep) = app_state.grep.as_mut() else {
+        return;
+    };
+    let page = 10;
+    match code {
+        KeyCode::Esc | KeyCode::Char('q') => app_state.grep = None,
+        KeyCode::Up => grep.selected = grep.selected.saturating_sub(1),
+        KeyCode::Down if grep.selected + 1 < grep.results.len() => grep.selected += 1,
+        KeyCode::PageUp => grep.selected = grep.selected.saturating_sub(page),
+        KeyCode::PageDown => {
+            grep.selected = (grep.selected + page).min(grep.results.len().saturating_sub(1))
+        }
+        KeyCode::Enter => {
+            let Some(result) = grep.results.get(grep.selected) else {
+                return;
+            };
+            // grep -n -H 输出 "文件:行号:内容"
+            let text = result.splitn(3, ':').nth(2).unwrap_or(result);
+            let window = &app_state.log_windows[app_state.selected_window];
+            let content = window.content.lock().unwrap();
+            let found = content
+                .iter()
+                .rposition(|line| line.trim_end_matches(['\r', '\n']).ends_with(text));
+            drop(content);
+
+            match found {
+                Some(line) => {
+                    let window = &mut app_state.log_windows[app_state.selected_window];
+                    grep.return_view = Some((app_state.is_maximized, window.follow));
+                    grep.visible = false;
+                    grep.error = None;
+                    app_state.is_maximized = true;
+                    window.follow = false;
+                    app_state.jump_to_line.set(Some(line));
+                }
+                None => grep.error = Some(tr(Msg::NotInBuffer).to_string()),
+            }
+        }
+        _ => {}
+    }
+}
+
+const RULE_COLORS: [&str; 4] = ["red", "yellow", "magenta", "cyan"];
+
+pub(super) fn handle_rule_key(app_state: &mut AppState, code: KeyCode) {
+    let Some(popup) = app_state.rule_popup.as_mut() else {
+        return;
+    };
+    let count = popup.suggestions.len();
+    match code {
+        KeyCode::Esc | KeyCode::Char('q') => app_state.rule_popup = None,
+        KeyCode::Up => popup.selected = popup.selected.saturating_sub(1),
+        KeyCode::Down if popup.selected + 1 < count => popup.selected += 1,
+        _ if count == 0 => {}
+        KeyCode::Enter | KeyCode::Char('e') => {
+            app_state.prompt = Some(Prompt {
+                kind: PromptKind::EditRule,
+                input: popup.suggestions[popup.selected].rule.pattern.clone(),
+            });
+        }
+        KeyCode::Char('a') => {
+            let suggestion = &mut popup.suggestions[popup.selected];
+            suggestion.rule.alert = (suggestion.rule.alert != Some(true)).then_some(true);
+            suggestion.saved = false;
+        }
+        KeyCode::Char('c') => {
+            let suggestion = &mut popup.suggestions[popup.selected];
+            let current = RULE_COLORS
+                .iter()
+                .position(|c| Some(*c) == suggestion.rule.color.as_deref());
+            let next = current.map_or(0, |i| (i + 1) % RULE_COLORS.len());
+            suggestion.rule.color = Some(RULE_COLORS[next].to_string());
+            suggestion.saved = false;
+        }
+        KeyCode::Char('s') => {
+            let suggestion = &mut popup.suggestions[popup.selected];
+            popup.message = Some(
+                match config::append_highlight(&app_state.config_path, &suggestion.rule) {
+                    Ok(()) => {
+                        suggestion.saved = true;
+                        (
+                            format!("{} {}", tr(Msg::Saved), app_state.config_path),
+                            Color::Green,
+                        )
+                    }
+                    Err(e) => (format!("{}: {}", tr(Msg::SaveErr), e), Color::Red),
+                },
+            );
+        }
+        _ => {}
+    }
+}
+
+pub(super) fn handle_field_key(app_state: &mut AppState, code: KeyCode) {
+    let Some(popup) = app_state.field_popup.as_mut() else {
+        return;
+    };
+    let window = &mut app_state.log_windows[app_state.selected_window];
+    match (popup.field, code) {
+        (_, KeyCode::Char('q')) | (None, KeyCode::Esc) => app_state.field_popup = None,
+        (_, KeyCode::Char('c')) => window.json_filter.clear(),
+        (None, KeyCode::Up) => popup.selected = popup.selected.saturating_sub(1),
+        (None, KeyCode::Down) if popup.selected + 1 < popup.fields.len() => popup.selected += 1,
+        (None, KeyCode::Enter | KeyCode::Right) if !popup.fields.is_empty() => {
+            popup.field = Some(popup.selected);
+            popup.value_selected = 0;
+        }
+        (Some(_), KeyCode::Esc | KeyCode::Left | KeyCode::Backspace) => popup.field = None,
+        (Some(_), KeyCode::Up) => popup.value_selected = popup.value_selected.saturating_sub(1),
+        (Some(field), KeyCode::Down)
+            if popup.value_selected + 1 < popup.fields[field].values.len() =>
+        {
+            popup.value_selected += 1
+        }
+        (Some(field), KeyCode::Enter) => {
+            let summary = &popup.fields[field];
+            let Some((value, _)) = summary.values.get(popup.value_selected) else {
+                return;
+            };
+            // 同一字段只保留一个取值, 再次选中同一取值则取消
+            let existing = window.json_filter.iter().position(|(name, _)| *name == summary.name);
+            let same = existing.is_some_and(|i| window.json_filter[i].1 == *value);
+            if let Some(i) = existing {
+                window.json_filter.remove(i);
+            }
+            if !same {
+                window.json_filter.push((summary.name.clone(), value.clone()));
+            }
+        }
+        _ => {}
+    }
+}
+
+pub(super) fn handle_shared_key(app_state: &mut AppState, code: KeyCode) {
+    let Some(popup) = app_state.shared_popup.as_mut() else {
+        return;
+    };
+    match code {
+        KeyCode::Esc | KeyCode::Char('q') => app_state.shared_popup = None,
+        KeyCode::Up => popup.selected = popup.selected.saturating_sub(1),
+        KeyCode::Down if popup.selected + 1 < popup.shared.len() => popup.selected += 1,
+        KeyCode::Char('D') => popup.shared = dedup::shared(),
+        _ => {}
+    }
+}
+
+pub(super) fn handle_global_search_key(app_state: &mut AppState, code: KeyCode) {
+    let Some(search) = app_state.global_search.as_mut() else {
+        return;
+    };
+    let page = 10;
+    let last = search.len().saturating_sub(1);
+    match code {
+        KeyCode::Esc | KeyCode::Char('q') => app_state.global_search = None,
+        KeyCode::Up => search.selected = search.selected.saturating_sub(1),
+        KeyCode::Down => search.selected = (search.selected + 1).min(last),
+        KeyCode::PageUp => search.selected = search.selected.saturating_sub(page),
+        KeyCode::PageDown => search.selected = (search.selected + page).min(last),
+        KeyCode::Enter => open_global_hit(app_state),
+        _ => {}
+    }
+}
+
+pub(super) fn handle_picker_key(app_state: &mut AppState, code: KeyCode) {
+    let Some(picker) = app_state.file_picker.as_mut() else {
+        return;
+    };
+    match code {
+        KeyCode::Esc | KeyCode::Char('q') => app_state.file_picker = None,
+        KeyCode::Up => picker.selected = picker.selected.saturating_sub(1),
+        KeyCode::Down if picker.selected + 1 < picker.entries.len() => picker.selected += 1,
+        KeyCode::Enter => {
+            let Some(entry) = picker.entries.get(picker.selected).cloned() else {
+                return;
+            };
+            let path = picker.entry_path(&entry);
+            let window = &mut app_state.log_windows[app_state.selected_window];
+            if entry.ends_with('/') {
+                app_state.file_picker = Some(FilePicker::open(window, path));
+            } else {
+                window.source_paths = shell_quote(&path);
+                let _ = window.requests.send(SourceRequest::SwitchPath(path));
+                app_state.set_follow(true);
+                app_state.file_picker = None;
+            }
+        }
+        _ => {}
+    }
+}
+
+// S 列表里的模式是配置里写好的正则, 不受搜索框的字面/大小写开关影响
+pub(super) fn handle_searches_key(app_state: &mut AppState, code: KeyCode) {
+    let Some(popup) = app_state.searches_popup.as_mut() else {
+        return;
+    };
+    let count = app_state.saved_searches.len();
+    match code {
+        KeyCode::Esc | KeyCode::Char('q') => app_state.searches_popup = None,
+        KeyCode::Up => popup.selected = popup.selected.saturating_sub(1),
+        KeyCode::Down if popup.selected + 1 < count => popup.selected += 1,
+        _ if count == 0 => {}
+        KeyCode::Enter | KeyCode::Char('/' | '&' | '!') => {
+            let pattern = app_state.saved_searches[popup.selected].1.clone();
+            app_state.searches_popup = None;
+            let Ok(regex) = Regex::new(&pattern) else {
+                return;
+            };
+            match code {
+                KeyCode::Char(c @ ('&' | '!')) => {
+                    let window = &mut app_state.log_windows[app_state.selected_window];
+                    add_filter(window, pattern, regex, c == '!');
+                }
+                _ => {
+                    app_state.search = Some(Search {
+                        query: pattern,
+                        regex,
+                        line: None,
+                    });
+                    jump_to_match(app_state, false);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+// 每个窗口最多保留的结果数, 取最新的
+const GLOBAL_MAX_HITS: usize = 200;
+
+pub(super) fn search_all(app_state: &mut AppState, query: String) {
+    let regex = search_regex(app_state.search_options, &query);
+    let groups: Vec<(usize, Vec<(usize, String)>)> = app_state
+        .log_windows
+        .iter()
+        .enumerate()
+        .filter_map(|(i, window)| {
+            let content = window.content.lock().unwrap();
+            let mut hits: Vec<(usize, String)> = content
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| regex.is_match(line) && window.shows(line))
+                .map(|(line, text)| (line, text.clone()))
+                .collect();
+            let excess = hits.len().saturating_sub(GLOBAL_MAX_HITS);
+            hits.drain(..excess);
+            (!hits.is_empty()).then_some((i, hits))
+        })
+        .collect();
+    history::push(&mut app_state.search_history, &query);
+    app_state.global_search = Some(GlobalSearch {
+        query,
+        regex,
+        groups,
+        selected: 0,
+    });
+}
+
+// 最大化结果所在的窗口并跳到匹配行, 之后可以用 n/N 在该窗口里继续找
+pub(super) fn open_global_hit(app_state: &mut AppState) {
+    let Some(search) = app_state.global_search.take() else {
+        return;
+    };
+    let Some((window, line)) = search.hit(search.selected) else {
+        return;
+    };
+    app_state.selected_window = window;
+    app_state.is_maximized = true;
+    app_state.set_follow(false);
+    app_state.jump_to_line.set(Some(line));
+    app_state.search = Some(Search {
+        query: search.query,
+        regex: search.regex,
+        line: Some(line),
+    });
+}
+
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(150);
+
+// 输入停顿一会儿再搜索, 避免大缓冲区在每次按键时都全量匹配
+pub(super) fn incremental_search(app_state: &mut AppState) {
+    let Some(Prompt {
+        kind: PromptKind::Search(incremental),
+        input,
+    }) = app_state.prompt.as_mut()
+    else {
+        return;
+    };
+    if incremental.edited.is_none_or(|edited| edited.elapsed() < SEARCH_DEBOUNCE) {
+        return;
+    }
+    incremental.edited = None;
+    let (query, origin, view) = (input.clone(), incremental.origin, incremental.view);
+    if query.is_empty() {
+        app_state.search = None;
+        restore_view(app_state, origin, view);
+    } else {
+        start_search(app_state, query, origin);
+    }
+}
+
+pub(super) fn finish_search(app_state: &mut AppState, query: String, incremental: Incremental) {
+    if query.is_empty() {
+        cancel_search(app_state, incremental);
+        return;
+    }
+    history::push(&mut app_state.search_history, &query);
+    if app_state.save_search_history {
+        if let Err(e) = history::save(&app_state.search_history) {
+            app_state.notice = Some((format!("{}: {}", tr(Msg::SaveErr), e), Instant::now()));
+        }
+    }
+    if incremental.edited.is_some() || app_state.search.is_none() {
+        start_search(app_state, query, incremental.origin);
+    }
+}
+
+pub(super) fn cancel_search(app_state: &mut AppState, incremental: Incremental) {
+    app_state.search = incremental.previous;
+    restore_view(app_state, incremental.origin, incremental.view);
+}
+
+fn restore_view(app_state: &mut AppState, origin: Option<usize>, (is_maximized, follow): (bool, bool)) {
+    app_state.is_maximized = is_maximized;
+    app_state.set_follow(follow);
+    app_state.jump_to_line.set(origin);
+}
+
+// 从当前匹配开始找下一个匹配行, 到头后从另一端继续
+fn jump_to_match(app_state: &mut AppState, forward: bool) {
+    let Some(search) = app_state.search.as_ref() else {
+        return;
+    };
+    let window = &app_state.log_windows[app_state.selected_window];
+    let content = window.content.lock().unwrap();
+    let len = content.len();
+    if len == 0 {
+        return;
+    }
+    let start = match search.line {
+        Some(line) if forward => line + 1,
+        Some(line) => line + len - 1,
+        None => 0,
+    };
+    let found = (0..len)
+        .map(|offset| {
+            if forward {
+                (start + offset) % len
+            } else {
+                (start + len - offset % len) % len
+            }
+        })
+        .find(|&i| search.regex.is_match(&content[i]) && window.shows(&content[i]));
+    drop(content);
+
+    let Some(line) = found else {
+        app_state.notice = Some((tr(Msg::NoMatch).to_string(), Instant::now()));
+        return;
+    };
+    if search.line.is_some_and(|previous| (line <= previous) == forward) {
+        app_state.notice = Some((tr(Msg::SearchWrapped).to_string(), Instant::now()));
+    }
+    app_state.search.as_mut().unwrap().line = Some(line);
+    app_state.is_maximized = true;
+    app_state.set_follow(false);
+    app_state.jump_to_line.set(Some(line));
+}
+
+// ~/.rogger/download/{timestamp}_{log_name}.log
+fn download_path(name: &str) -> io::Result<PathBuf> {
+    let home = std::env::var("HOME").map_err(|_| {
+        io::Error::new(io::ErrorKind::NotFound, "HOME environment variable not set")
+    })?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    Ok(PathBuf::from(home)
+        .join(".rogger/download")
+        .join(format!("{}_{}.log", timestamp, name)))
+}
+
+fn clear_chip(app_state: &mut AppState, index: usize) {
+    let selected = app_state.selected_window;
+    let chips = app_state.chips(&app_state.log_windows[selected], true);
+    let Some((chip, _)) = chips.into_iter().nth(index) else {
+        return;
+    };
+    let window = &mut app_state.log_windows[selected];
+    match chip {
+        Chip::Field(i) => {
+            window.json_filter.remove(i);
+        }
+        Chip::Sampling => window.sample = 1,
+        Chip::Paused => *window.paused_at.lock().unwrap() = None,
+        Chip::Grep => app_state.grep = None,
+        Chip::Search => app_state.search = None,
+        Chip::Filter(i) => {
+            window.filters.remove(i);
+            sync_dropped(window);
+        }
+    }
+}
+
+fn clear_history(app_state: &mut AppState) {
+    let window = &mut app_state.log_windows[app_state.selected_window];
+    let mut content = window.content.lock().unwrap();
+    content.clear();
+    window.stats.lock().unwrap().buffer_bytes = 0;
+    *window.paused_at.lock().unwrap() = None;
+}
+
+// 最多滚到最长的一行只剩一列可见
+fn scroll_columns(app_state: &mut AppState, columns: isize) {
+    let window = &mut app_state.log_windows[app_state.selected_window];
+    let widest = window
+        .content
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|line| unicode_width::UnicodeWidthStr::width(line.as_str()))
+        .max()
+        .unwrap_or(0);
+    window.h_scroll = window
+        .h_scroll
+        .saturating_add_signed(columns)
+        .min(widest.saturating_sub(1));
+}
+
+fn scroll_log(app_state: &mut AppState, direction: ScrollDirection, window_height: usize) {
+    if !app_state.is_maximized {
+        return;
+    }
+
+    let window = &mut app_state.log_windows[app_state.selected_window];
+    let content_len = window.content.lock().unwrap().len();
+    let mut scroll_position = window.scroll_position.lock().unwrap();
+
+    // 计算每页的行数，减去2是为了考虑边框
+    let page_size = window_height.saturating_sub(2);
+
+    let old_scroll_position = *scroll_position;
+
+    match direction {
+        ScrollDirection::Up => {
+            if *scroll_position > 0 {
+                *scroll_position -= 1;
+            }
+        }
+        ScrollDirection::Down => {
+            if *scroll_position < content_len.saturating_sub(page_size) {
+                *scroll_position += 1;
+            }
+        }
+        ScrollDirection::PageUp => {
+            *scroll_position = scroll_position.saturating_sub(page_size);
+        }
+        ScrollDirection::PageDown => {
+            *scroll_position =
+                (*scroll_position + page_size).min(content_len.saturating_sub(page_size));
+        }
+        ScrollDirection::Top => {
+            *scroll_position = 0;
+        }
+        ScrollDirection::Bottom => {
+            *scroll_position = content_len.saturating_sub(page_size);
+        }
+    }
+
+    // 往上翻就不再跟随, 往下翻到底也保持手动, 用 End 或 f 恢复
+    if *scroll_position < old_scroll_position {
+        drop(scroll_position);
+        window.follow = false;
+    }
+}
+
+fn move_selection(app_state: &mut AppState, direction: MoveDirection) {
+    let order = selection_order(app_state);
+    let window_count = order.len();
+    let Some(position) = order.iter().position(|&i| i == app_state.selected_window) else {
+        if let Some(&first) = order.first() {
+            app_state.selected_window = first;
+        }
+        return;
+    };
+
+    match direction {
+        MoveDirection::Up => {
+            if position > 0 {
+                app_state.selected_window = order[position - 1];
+            }
+        }
+        MoveDirection::Down => {
+            if position + 1 < window_count {
+                app_state.selected_window = order[position + 1];
+            }
+        }
+        MoveDirection::Left | MoveDirection::Right => {
+            // 在单列布局中,左右移动不做任何操作
+        }
+    }
+}
+
+pub(super) enum MoveDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+pub(super) enum ScrollDirection {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+}
@@ -0,0 +1,94 @@
+use super::action::{clear_filters, push_filter, search_in};
+use super::state::AppState;
+use crate::i18n::{tr, Msg};
+use crate::timestamp;
+
+fn window_index(app_state: &AppState, name: Option<&String>) -> Result<usize, String> {
+    let name = name.ok_or("missing window name")?;
+    app_state
+        .log_windows
+        .iter()
+        .position(|window| window.name == *name)
+        .ok_or_else(|| format!("{}: {}", tr(Msg::UnknownWindow), name))
+}
+
+// 被隐藏的窗口先重新显示出来
+fn select(app_state: &mut AppState, window: usize) {
+    if !app_state.visible.contains(&window) {
+        app_state.visible.push(window);
+        app_state.weights.push(1);
+    }
+    app_state.selected_window = window;
+}
+
+// 按窗口最新一行的日期解释 HH:MM[:SS], 晚于最新一行时认为是前一天
+fn goto(app_state: &mut AppState, window: usize, time: &str) -> Result<String, String> {
+    let time_of_day = timestamp::parse_time_of_day(time).ok_or_else(|| format!("invalid time: {}", time))?;
+    let log_window = &app_state.log_windows[window];
+    let format = log_window.timestamps.format();
+    let content = log_window.content.lock().unwrap();
+    let latest = content
+        .len()
+        .checked_sub(1)
+        .and_then(|last| timestamp::line_millis(&content, last, format))
+        .ok_or_else(|| tr(Msg::NoTimestamp).to_string())?;
+    let mut target = latest - latest.rem_euclid(86_400_000) + time_of_day;
+    if target > latest {
+        target -= 86_400_000;
+    }
+    let line = timestamp::nearest_line(&content, target, format).ok_or_else(|| tr(Msg::NoTimestamp).to_string())?;
+    let found = content[line].trim_end().to_string();
+    drop(content);
+    select(app_state, window);
+    app_state.is_maximized = true;
+    app_state.set_follow(false);
+    app_state.jump_to_line.set(Some(line));
+    Ok(found)
+}
+
+fn run(app_state: &mut AppState, args: &[String]) -> Result<String, String> {
+    let command = args.first().map(String::as_str).unwrap_or_default();
+    if command == "list" {
+        let names: Vec<&str> = app_state.log_windows.iter().map(|w| w.name.as_str()).collect();
+        return Ok(names.join("\n"));
+    }
+    let window = window_index(app_state, args.get(1))?;
+    let rest = args[2..].join(" ");
+    match command {
+        "select" => {
+            select(app_state, window);
+            app_state.is_maximized = false;
+        }
+        "maximize" => {
+            select(app_state, window);
+            app_state.is_maximized = true;
+        }
+        "filter" | "exclude" => {
+            select(app_state, window);
+            let exclude = command == "exclude";
+            if rest.is_empty() {
+                clear_filters(app_state, exclude);
+            } else {
+                push_filter(app_state, rest, exclude);
+            }
+        }
+        "search" if !rest.is_empty() => {
+            select(app_state, window);
+            search_in(app_state, rest);
+        }
+        "goto" if !rest.is_empty() => return goto(app_state, window, &rest),
+        _ => return Err(format!("unknown command: {}", args.join(" "))),
+    }
+    Ok(String::new())
+}
+
+// rogger-ctl 的命令在 UI 线程里执行, 和按键的效果一样
+pub(super) fn poll(app_state: &mut AppState) {
+    let Some(control) = &app_state.control else {
+        return;
+    };
+    let requests: Vec<_> = control.try_iter().collect();
+    for request in requests {
+        let _ = request.reply.send(run(app_state, &request.args));
+    }
+}
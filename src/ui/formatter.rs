@@ -0,0 +1,239 @@
+use crate::config;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+};
+
+pub(super) fn to_ansi(spans: &Spans) -> String {
+    let mut out = String::new();
+    for span in &spans.0 {
+        match span.style.fg.and_then(ansi_color_code) {
+            Some(code) => out.push_str(&format!("\x1b[{}m{}\x1b[0m", code, span.content)),
+            None => out.push_str(&span.content),
+        }
+    }
+    out
+}
+
+fn ansi_color_code(color: Color) -> Option<u8> {
+    let code = match color {
+        Color::Black => 30,
+        Color::Red => 31,
+        Color::Green => 32,
+        Color::Yellow => 33,
+        Color::Blue => 34,
+        Color::Magenta => 35,
+        Color::Cyan => 36,
+        Color::Gray => 37,
+        Color::DarkGray => 90,
+        Color::LightRed => 91,
+        Color::LightGreen => 92,
+        Color::LightYellow => 93,
+        Color::LightBlue => 94,
+        Color::LightMagenta => 95,
+        Color::LightCyan => 96,
+        Color::White => 97,
+        _ => return None,
+    };
+    Some(code)
+}
+
+// 级别规则同时用于 e 的级别过滤
+pub(super) static WARN_LEVEL: Lazy<Regex> = Lazy::new(|| Regex::new(r"WARNING|WARN").unwrap());
+pub(super) static ERROR_LEVEL: Lazy<Regex> = Lazy::new(|| Regex::new(r"ERROR|FATAL|FAILURE").unwrap());
+
+pub fn create_log_formatter(highlights: &[config::HighlightConfig]) -> LogFormatter {
+    let mut formatter = LogFormatter::new();
+
+    formatter
+        .add_rule(
+            r"\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}(?:\.\d{3})?(?:\,\d{3})?",
+            Style::default().fg(Color::Green),
+        )
+        .unwrap();
+    formatter
+        .add_rule(WARN_LEVEL.as_str(), Style::default().fg(Color::Yellow))
+        .unwrap();
+    formatter
+        .add_rule(ERROR_LEVEL.as_str(), Style::default().fg(Color::Red))
+        .unwrap();
+    formatter
+        .add_rule(r"\{.*?\}", Style::default().fg(Color::Cyan))
+        .unwrap();
+    formatter
+        .add_rule(r"INFO", Style::default().fg(Color::Blue))
+        .unwrap();
+    formatter
+        .add_rule(
+            r"\b(?:\d{1,3}\.){3}\d{1,3}\b",
+            Style::default().fg(Color::Magenta),
+        )
+        .unwrap();
+
+    formatter
+        .add_rule(
+            r#"\b(?:https?|ftp)://[^\s"'<>`]+[^\s"'<>`.,;:!?)\]}]"#,
+            Style::default().fg(Color::Blue).add_modifier(Modifier::UNDERLINED),
+        )
+        .unwrap();
+
+    // 配置中的规则在读取时已经校验过
+    for rule in highlights {
+        let color = rule.color.as_deref().and_then(parse_color).unwrap_or(Color::LightRed);
+        let _ = formatter.add_rule(&rule.pattern, Style::default().fg(color));
+    }
+
+    formatter
+}
+
+pub(super) fn parse_color(name: &str) -> Option<Color> {
+    if let Some(hex) = name.strip_prefix('#').filter(|hex| hex.len() == 6) {
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        return Some(Color::Rgb((value >> 16) as u8, (value >> 8) as u8, value as u8));
+    }
+    Some(match name.to_lowercase().as_str() {
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => return None,
+    })
+}
+
+fn search_style() -> Style {
+    Style::default().fg(Color::Black).bg(Color::Rgb(230, 160, 60))
+}
+
+struct MatchRule {
+    regex: Regex,
+    style: Style,
+}
+
+pub struct RuleMatch<'a> {
+    pub rule_index: usize,
+    pub pattern: &'a str,
+    pub start: usize,
+    pub end: usize,
+    pub style: Style,
+    pub overlapped: bool,
+}
+
+pub struct LogFormatter {
+    rules: Vec<MatchRule>,
+}
+
+impl LogFormatter {
+    pub(super) fn new() -> Self {
+        LogFormatter { rules: Vec::new() }
+    }
+
+    pub(super) fn add_rule(&mut self, pattern: &str, style: Style) -> Result<(), regex::Error> {
+        let regex = Regex::new(pattern)?;
+        self.rules.push(MatchRule { regex, style });
+        Ok(())
+    }
+
+    pub(super) fn format_line(&self, line: &str) -> Spans<'_> {
+        self.format_line_searched(line, None)
+    }
+
+    // 搜索词的每个匹配在规则样式之上再加背景色
+    pub(super) fn format_line_searched(&self, line: &str, search: Option<&Regex>) -> Spans<'_> {
+        let mut segments: Vec<(usize, usize, Style)> = Vec::new();
+        let mut last_match_end = 0;
+
+        let mut matches: Vec<(usize, usize, &Style)> = Vec::new();
+
+        for rule in &self.rules {
+            for cap in rule.regex.find_iter(line) {
+                matches.push((cap.start(), cap.end(), &rule.style));
+            }
+        }
+
+        matches.sort_by_key(|&(start, _, _)| start);
+
+        for (start, end, style) in matches {
+            if start > last_match_end {
+                segments.push((last_match_end, start, Style::default()));
+            }
+            segments.push((start, end, *style));
+            last_match_end = end;
+        }
+
+        if last_match_end < line.len() {
+            segments.push((last_match_end, line.len(), Style::default()));
+        }
+
+        let found: Vec<(usize, usize)> = search
+            .map(|regex| {
+                regex
+                    .find_iter(line)
+                    .filter(|m| !m.as_str().is_empty())
+                    .map(|m| (m.start(), m.end()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut spans = Vec::new();
+        for (start, end, style) in segments {
+            let mut offset = start;
+            for &(found_start, found_end) in &found {
+                let (from, to) = (found_start.max(offset), found_end.min(end));
+                if from >= to {
+                    continue;
+                }
+                if from > offset {
+                    spans.push(Span::styled(line[offset..from].to_string(), style));
+                }
+                spans.push(Span::styled(line[from..to].to_string(), style.patch(search_style())));
+                offset = to;
+            }
+            if offset < end {
+                spans.push(Span::styled(line[offset..end].to_string(), style));
+            }
+        }
+
+        Spans::from(spans)
+    }
+
+    pub fn inspect(&self, line: &str) -> Vec<RuleMatch<'_>> {
+        let mut matches = Vec::new();
+
+        for (rule_index, rule) in self.rules.iter().enumerate() {
+            for cap in rule.regex.find_iter(line) {
+                matches.push(RuleMatch {
+                    rule_index,
+                    pattern: rule.regex.as_str(),
+                    start: cap.start(),
+                    end: cap.end(),
+                    style: rule.style,
+                    overlapped: false,
+                });
+            }
+        }
+
+        matches.sort_by_key(|m| m.start);
+
+        let mut last_match_end = 0;
+        for m in matches.iter_mut() {
+            m.overlapped = m.start < last_match_end;
+            last_match_end = last_match_end.max(m.end);
+        }
+
+        matches
+    }
+}
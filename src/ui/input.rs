@@ -0,0 +1,129 @@
+use super::action::{Action, MoveDirection, ScrollDirection};
+use super::state::{AppState, MacroKind};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+// 弹窗打开时按键只交给弹窗处理
+pub(super) fn key_action(app_state: &AppState, key: KeyEvent) -> Option<Action> {
+    if app_state.macro_pending.is_some() {
+        return Some(Action::MacroRegister(key.code));
+    }
+    if app_state.prompt.is_some() {
+        return Some(Action::PromptKey(key));
+    }
+    if app_state.file_picker.is_some() {
+        return Some(Action::PickerKey(key.code));
+    }
+    if app_state.grep.as_ref().is_some_and(|g| g.visible) {
+        return Some(Action::GrepKey(key.code));
+    }
+    if app_state.rule_popup.is_some() {
+        return Some(Action::RuleKey(key.code));
+    }
+    if app_state.field_popup.is_some() {
+        return Some(Action::FieldKey(key.code));
+    }
+    if app_state.shared_popup.is_some() {
+        return Some(Action::SharedKey(key.code));
+    }
+    if app_state.searches_popup.is_some() {
+        return Some(Action::SearchesKey(key.code));
+    }
+    if app_state.global_search.is_some() {
+        return Some(Action::GlobalSearchKey(key.code));
+    }
+
+    let maximized = app_state.is_maximized;
+    let time_cursor = app_state.time_cursor.is_some();
+    let filtered = app_state.log_windows[app_state.selected_window].has_include();
+    let no_wrap = !app_state.log_windows[app_state.selected_window].wrap;
+    let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+    if let Some(index) = match key.code {
+        KeyCode::Char(c) => app_state.preset_for(c),
+        _ => None,
+    } {
+        return Some(Action::ApplyPreset(index));
+    }
+    Some(match key.code {
+        KeyCode::Char('q') => Action::Quit,
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Action::Quit,
+        KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => Action::GlobalSearchPrompt,
+        KeyCode::Enter => Action::ToggleMaximize,
+        KeyCode::Char('m') => Action::ToggleMark,
+        KeyCode::Char('f') => Action::ToggleFollow,
+        KeyCode::Char('F') => Action::ToggleFollowErrors,
+        KeyCode::Char('p') => Action::ToggleFreeze,
+        KeyCode::Char('\'') => Action::JumpToMark(true),
+        KeyCode::Char('`') => Action::JumpToMark(false),
+        // KeyCode::Char('s') => {
+        //     // Save log
+        //     todo!()
+        // }
+        // KeyCode::Char('h') => {
+        //     // Help
+        //     todo!()
+        // }
+        KeyCode::Char('g') => Action::RemoteGrepPrompt,
+        KeyCode::Esc if app_state.grep.is_some() => Action::ReturnToGrep,
+        KeyCode::Esc if app_state.search.is_some() => Action::ClearSearch,
+        KeyCode::Esc | KeyCode::Backspace if !app_state.log_windows[app_state.selected_window].filters.is_empty() => {
+            Action::PopFilter
+        }
+        KeyCode::Char('X') => Action::ClearFilters,
+        KeyCode::Char('&') => Action::LineFilterPrompt,
+        KeyCode::Char('!') => Action::ExcludePrompt,
+        KeyCode::Char('e') => Action::CycleSeverity,
+        KeyCode::Char('/') => Action::SearchPrompt,
+        KeyCode::Char('n') if app_state.search.is_some() => Action::SearchNext(true),
+        KeyCode::Char('N') if app_state.search.is_some() => Action::SearchNext(false),
+        KeyCode::Char('d') => Action::Download,
+        KeyCode::Char('L') => Action::SaveLayoutPrompt,
+        KeyCode::Char('v') => Action::CycleLayout,
+        KeyCode::Char('E') => Action::SuggestRules,
+        KeyCode::Char('J') => Action::OpenFieldFilter,
+        KeyCode::Char('D') => Action::OpenShared,
+        KeyCode::Char('S') => Action::OpenSearches,
+        KeyCode::Char('%') => Action::CycleSampling,
+        KeyCode::Char('Q') if app_state.recording.is_some() => Action::StopRecording,
+        KeyCode::Char('Q') => Action::BeginMacro(MacroKind::Record),
+        KeyCode::Char('@') => Action::BeginMacro(MacroKind::Replay),
+        KeyCode::Char('y') => Action::CopyPosition,
+        KeyCode::Char('z') => Action::SilenceLine,
+        KeyCode::Char('+') if filtered => Action::AdjustContext(1),
+        KeyCode::Char('-') if filtered => Action::AdjustContext(-1),
+        KeyCode::Char('+') if !maximized => Action::AdjustWeight(1),
+        KeyCode::Char('-') if !maximized => Action::AdjustWeight(-1),
+        KeyCode::Char('x') if !maximized => Action::HideWindow,
+        KeyCode::Char('=') => Action::ShowAllWindows,
+        KeyCode::Char('G') if !maximized => Action::ToggleHostGroups,
+        KeyCode::Char('H') if !maximized => Action::ToggleCollapsed,
+        KeyCode::Char('t') => Action::ToggleTimeCursor,
+        KeyCode::Char('[') if time_cursor => Action::MoveTimeCursor(-1),
+        KeyCode::Char(']') if time_cursor => Action::MoveTimeCursor(1),
+        KeyCode::Char('{') if time_cursor => Action::MoveTimeCursor(-10),
+        KeyCode::Char('}') if time_cursor => Action::MoveTimeCursor(10),
+        KeyCode::Char('o') => Action::OpenPicker,
+        KeyCode::Char('w') => Action::ToggleInvisibles,
+        KeyCode::Char('W') => Action::ToggleWrap,
+        KeyCode::Char('c') => Action::ToggleMouse,
+        KeyCode::Down if maximized => Action::Scroll(ScrollDirection::Down),
+        KeyCode::Down => Action::MoveSelection(MoveDirection::Down),
+        KeyCode::Up if maximized => Action::Scroll(ScrollDirection::Up),
+        KeyCode::Up => Action::MoveSelection(MoveDirection::Up),
+        KeyCode::Left if maximized && no_wrap => Action::ScrollColumns(if shift { -40 } else { -4 }),
+        KeyCode::Right if maximized && no_wrap => Action::ScrollColumns(if shift { 40 } else { 4 }),
+        KeyCode::Left if !maximized => Action::MoveSelection(MoveDirection::Left),
+        KeyCode::Right if !maximized => Action::MoveSelection(MoveDirection::Right),
+        KeyCode::PageDown if maximized => Action::Scroll(ScrollDirection::PageDown),
+        KeyCode::PageUp if maximized => Action::Scroll(ScrollDirection::PageUp),
+        KeyCode::Home if maximized => Action::Scroll(ScrollDirection::Top),
+        KeyCode::End => Action::ScrollToEnd,
+        KeyCode::Char('l') => Action::OpenPager { with_archive: false },
+        KeyCode::Char('a') => Action::OpenPager { with_archive: true },
+        KeyCode::Char('i') if maximized => Action::ToggleInspector,
+        KeyCode::Char('r') => Action::ClearHistory,
+        KeyCode::Char('R') => Action::Refresh,
+        KeyCode::Char(c @ '1'..='9') => Action::ClearChip(c as usize - '1' as usize),
+        _ => return None,
+    })
+}
@@ -0,0 +1,389 @@
+use super::state::{AppState, LogWindow};
+use super::widgets::{format_buffer, render_inspector, render_window};
+use crate::i18n::{tr, Msg};
+use crate::layout::{self, LayoutConfig};
+use crate::{io::Stdout, ssh::ConnectionStatus};
+use tui::layout::Direction as LayoutDirection;
+
+use tui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+pub fn apply_layout(app_state: &mut AppState, name: &str) -> Result<(), String> {
+    let layout = app_state
+        .layouts
+        .get(name)
+        .cloned()
+        .ok_or_else(|| format!("{}: {}", tr(Msg::UnknownLayout), name))?;
+    let index_of = |window: &str| app_state.log_windows.iter().position(|w| w.name == window);
+
+    let mut visible = Vec::new();
+    let mut weights = Vec::new();
+    for (i, window) in layout.windows.iter().enumerate() {
+        if let Some(index) = index_of(window).filter(|index| !visible.contains(index)) {
+            visible.push(index);
+            let weight = layout.weights.as_ref().and_then(|w| w.get(i)).copied();
+            weights.push(weight.unwrap_or(1).max(1));
+        }
+    }
+    // 布局里的窗口都不存在时退回显示全部
+    if visible.is_empty() {
+        visible = (0..app_state.log_windows.len()).collect();
+        weights = vec![1; visible.len()];
+    }
+
+    let maximized = layout.maximized.as_deref().and_then(index_of);
+    app_state.selected_window = maximized.or(visible.first().copied()).unwrap_or(0);
+    app_state.is_maximized = maximized.is_some();
+    for window in app_state.log_windows.iter_mut() {
+        window.follow = true;
+    }
+    app_state.show_inspector = false;
+    app_state.visible = visible;
+    app_state.weights = weights;
+    app_state.layout_label = Some(name.to_string());
+    Ok(())
+}
+
+fn current_layout(app_state: &AppState) -> LayoutConfig {
+    LayoutConfig {
+        windows: app_state
+            .visible
+            .iter()
+            .map(|&i| app_state.log_windows[i].name.clone())
+            .collect(),
+        weights: Some(app_state.weights.clone()),
+        maximized: app_state
+            .is_maximized
+            .then(|| app_state.log_windows[app_state.selected_window].name.clone()),
+    }
+}
+
+pub(super) fn save_current_layout(app_state: &mut AppState, name: String) {
+    let layout = current_layout(app_state);
+    app_state.layout_label = Some(match layout::save_layout(&name, &layout) {
+        Ok(()) => name.clone(),
+        Err(e) => format!("{} ({}: {})", name, tr(Msg::SaveErr), e),
+    });
+    app_state.layouts.insert(name, layout);
+}
+
+pub(super) fn adjust_weight(app_state: &mut AppState, delta: i32) {
+    if let Some(position) = app_state
+        .visible
+        .iter()
+        .position(|&i| i == app_state.selected_window)
+    {
+        let weight = &mut app_state.weights[position];
+        *weight = (*weight as i32 + delta).clamp(1, 20) as u16;
+    }
+}
+
+// 从当前布局中移除选中的窗口, 至少保留一个
+pub(super) fn hide_selected(app_state: &mut AppState) {
+    if app_state.visible.len() <= 1 {
+        return;
+    }
+    if let Some(position) = app_state
+        .visible
+        .iter()
+        .position(|&i| i == app_state.selected_window)
+    {
+        app_state.visible.remove(position);
+        app_state.weights.remove(position);
+        let next = position.min(app_state.visible.len() - 1);
+        app_state.selected_window = app_state.visible[next];
+    }
+}
+
+// 按名字顺序切换到下一个布局
+pub(super) fn cycle_layout(app_state: &mut AppState) {
+    let current = app_state.layout_label.as_deref();
+    let next = app_state
+        .layouts
+        .keys()
+        .find(|name| current.is_some_and(|c| name.as_str() > c))
+        .or_else(|| app_state.layouts.keys().next())
+        .cloned();
+    if let Some(name) = next {
+        let _ = apply_layout(app_state, &name);
+    }
+}
+
+fn host_of(window: &LogWindow) -> &str {
+    if window.host.is_empty() {
+        tr(Msg::LocalHost)
+    } else {
+        &window.host
+    }
+}
+
+// 主机按第一次出现的顺序排列, 组内保持窗口的显示顺序
+fn host_groups(app_state: &AppState) -> Vec<(&str, Vec<usize>)> {
+    let mut groups: Vec<(&str, Vec<usize>)> = Vec::new();
+    for &i in &app_state.visible {
+        let host = host_of(&app_state.log_windows[i]);
+        match groups.iter_mut().find(|(h, _)| *h == host) {
+            Some((_, members)) => members.push(i),
+            None => groups.push((host, vec![i])),
+        }
+    }
+    groups
+}
+
+// 上下移动选择的顺序, 折叠的组只停在组里的第一个窗口上(即组的标题行)
+pub(super) fn selection_order(app_state: &AppState) -> Vec<usize> {
+    if !app_state.group_by_host {
+        return app_state.visible.clone();
+    }
+    host_groups(app_state)
+        .into_iter()
+        .flat_map(|(host, members)| {
+            if app_state.collapsed.contains(host) {
+                members[..1].to_vec()
+            } else {
+                members
+            }
+        })
+        .collect()
+}
+
+// 普通布局中实际画出内容的窗口, 折叠的组里的窗口不算
+pub(super) fn shown_windows(app_state: &AppState) -> Vec<usize> {
+    if !app_state.group_by_host {
+        return app_state.visible.clone();
+    }
+    host_groups(app_state)
+        .into_iter()
+        .filter(|(host, _)| !app_state.collapsed.contains(*host))
+        .flat_map(|(_, members)| members)
+        .collect()
+}
+
+pub(super) fn toggle_host_groups(app_state: &mut AppState) {
+    app_state.group_by_host = !app_state.group_by_host;
+}
+
+// 折叠或展开选中窗口所在主机的组, 折叠后选中组里的第一个窗口
+pub(super) fn toggle_collapsed(app_state: &mut AppState) {
+    if !app_state.group_by_host {
+        return;
+    }
+    let host = host_of(&app_state.log_windows[app_state.selected_window]).to_string();
+    if app_state.collapsed.remove(&host) {
+        return;
+    }
+    let first = host_groups(app_state)
+        .into_iter()
+        .find(|(h, _)| *h == host)
+        .map(|(_, members)| members[0]);
+    if let Some(first) = first {
+        app_state.selected_window = first;
+    }
+    app_state.collapsed.insert(host);
+}
+
+pub(super) fn centered_rect(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let width = area.width * percent_x / 100;
+    let height = area.height * percent_y / 100;
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+// 每个窗口至少需要边框加一行内容
+const MIN_WINDOW_HEIGHT: u16 = 3;
+
+const MIN_WINDOW_WIDTH: u16 = 10;
+
+pub(super) fn render_normal_layout(f: &mut Frame<CrosstermBackend<Stdout>>, app_state: &AppState) {
+    if app_state.group_by_host {
+        if !render_grouped_layout(f, app_state) {
+            render_window_summaries(f, app_state);
+        }
+        return;
+    }
+
+    let size = f.size();
+    let window_count = app_state.visible.len() as u16;
+    if window_count == 0
+        || size.height / window_count.max(1) < MIN_WINDOW_HEIGHT
+        || size.width < MIN_WINDOW_WIDTH
+    {
+        render_window_summaries(f, app_state);
+        return;
+    }
+
+    let chunks = create_layout(size, &app_state.weights);
+
+    for (&i, chunk) in app_state.visible.iter().zip(chunks) {
+        render_window(
+            f,
+            app_state,
+            &app_state.log_windows[i],
+            chunk,
+            i == app_state.selected_window,
+        );
+    }
+}
+
+// 每个主机一行标题, 展开的组在下面按权重分配剩余高度; 放不下时返回 false
+fn render_grouped_layout(f: &mut Frame<CrosstermBackend<Stdout>>, app_state: &AppState) -> bool {
+    let size = f.size();
+    let groups = host_groups(app_state);
+    let shown = shown_windows(app_state);
+    let headers = groups.len() as u16;
+    if size.width < MIN_WINDOW_WIDTH
+        || size.height < headers + shown.len() as u16 * MIN_WINDOW_HEIGHT
+    {
+        return false;
+    }
+
+    let weight_of = |i: usize| {
+        let position = app_state.visible.iter().position(|&v| v == i).unwrap_or(0);
+        app_state.weights.get(position).copied().unwrap_or(1) as u32
+    };
+    let mut remaining_height = (size.height - headers) as u32;
+    let mut remaining_weight: u32 = shown.iter().map(|&i| weight_of(i)).sum();
+    let mut y = size.y;
+    for (host, members) in groups {
+        let collapsed = app_state.collapsed.contains(host);
+        render_host_header(f, app_state, host, &members, collapsed, Rect::new(size.x, y, size.width, 1));
+        y += 1;
+        if collapsed {
+            continue;
+        }
+        for i in members {
+            let weight = weight_of(i);
+            let height = remaining_height * weight / remaining_weight.max(1);
+            remaining_height -= height;
+            remaining_weight -= weight;
+            render_window(
+                f,
+                app_state,
+                &app_state.log_windows[i],
+                Rect::new(size.x, y, size.width, height as u16),
+                i == app_state.selected_window,
+            );
+            y += height as u16;
+        }
+    }
+    true
+}
+
+// ▾ host · 3 windows · ✔ 2 ✘ 1 · 1200 lines; 折叠的组里有选中窗口时高亮标题
+fn render_host_header(
+    f: &mut Frame<CrosstermBackend<Stdout>>,
+    app_state: &AppState,
+    host: &str,
+    members: &[usize],
+    collapsed: bool,
+    area: Rect,
+) {
+    let (mut up, mut failed, mut suspended, mut lines) = (0, 0, 0, 0);
+    for &i in members {
+        let window = &app_state.log_windows[i];
+        match &*window.connection_status.lock().unwrap() {
+            ConnectionStatus::Error(_) => failed += 1,
+            ConnectionStatus::Suspended => suspended += 1,
+            _ => up += 1,
+        }
+        lines += window.content.lock().unwrap().len();
+    }
+
+    let mut spans = vec![
+        Span::styled(
+            format!("{} {}", if collapsed { "▸" } else { "▾" }, host),
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!(" · {} {} · ", members.len(), tr(Msg::WindowsCount))),
+        Span::styled(format!("✔ {}", up), Style::default().fg(Color::Green)),
+    ];
+    if failed > 0 {
+        spans.push(Span::styled(format!(" ✘ {}", failed), Style::default().fg(Color::Red)));
+    }
+    if suspended > 0 {
+        spans.push(Span::styled(format!(" ⏸ {}", suspended), Style::default().fg(Color::DarkGray)));
+    }
+    spans.push(Span::raw(format!(" · {} {}", lines, tr(Msg::Lines))));
+
+    let style = if collapsed && members.contains(&app_state.selected_window) {
+        Style::default().bg(Color::DarkGray)
+    } else {
+        Style::default()
+    };
+    f.render_widget(Paragraph::new(Spans::from(spans)).style(style), area);
+}
+
+// 终端放不下所有窗口时, 改为可滚动的窗口摘要列表
+fn render_window_summaries(f: &mut Frame<CrosstermBackend<Stdout>>, app_state: &AppState) {
+    let items: Vec<ListItem> = app_state
+        .visible
+        .iter()
+        .map(|&i| {
+            let window = &app_state.log_windows[i];
+            let (status, color) = match &*window.connection_status.lock().unwrap() {
+                ConnectionStatus::Error(e) => (e.clone(), Color::Red),
+                ConnectionStatus::Authenticated(method) => (method.as_str().to_string(), Color::Green),
+                ConnectionStatus::Connected => ("-".to_string(), Color::White),
+                ConnectionStatus::Suspended => (tr(Msg::Suspended).to_string(), Color::DarkGray),
+            };
+            let lines = window.content.lock().unwrap().len();
+            let buffer = format_buffer(lines, window.stats.lock().unwrap().buffer_bytes);
+            ListItem::new(Spans::from(vec![
+                Span::styled(window.name.clone(), Style::default().fg(Color::Yellow)),
+                Span::raw(format!(" {} | ", buffer)),
+                Span::styled(status, Style::default().fg(color)),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(tr(Msg::TooSmall))
+                .borders(Borders::ALL),
+        )
+        .style(Style::default().fg(Color::White).bg(Color::Black))
+        .highlight_style(Style::default().fg(Color::Black).bg(Color::Yellow));
+
+    let mut state = ListState::default();
+    state.select(
+        app_state
+            .visible
+            .iter()
+            .position(|&i| i == app_state.selected_window),
+    );
+    f.render_stateful_widget(list, f.size(), &mut state);
+}
+
+fn create_layout(area: Rect, weights: &[u16]) -> Vec<Rect> {
+    let total: u32 = weights.iter().map(|&w| w as u32).sum();
+    let constraints: Vec<Constraint> = weights
+        .iter()
+        .map(|&w| Constraint::Ratio(w as u32, total.max(1)))
+        .collect();
+
+    Layout::default()
+        .direction(LayoutDirection::Vertical)
+        .constraints(constraints)
+        .split(area)
+}
+
+pub(super) fn render_maximized_window(f: &mut Frame<CrosstermBackend<Stdout>>, app_state: &AppState) {
+    let selected_window = &app_state.log_windows[app_state.selected_window];
+    let top_line = render_window(f, app_state, selected_window, f.size(), true);
+    app_state.view_top.set(top_line);
+
+    if app_state.show_inspector {
+        render_inspector(f, selected_window, top_line);
+    }
+}
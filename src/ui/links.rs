@@ -0,0 +1,136 @@
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tui::{
+    backend::Backend,
+    buffer::{Buffer, Cell},
+};
+
+// 扫描的是整个屏幕, 窗口边框 │ 不能算进链接
+static URL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"\b(?:https?|ftp)://[^\s"'<>`│]+[^\s"'<>`│.,;:!?)\]}]"#).unwrap());
+// 只识别带扩展名的绝对路径, 可以跟 :行号
+static FILE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?:^|[\s(\["'=│])(/(?:[\w.@+-]+/)+[\w@+-][\w.@+-]*\.\w+)(?::\d+)*"#).unwrap());
+
+// 屏幕上的一个链接: 所在行、起始列和目标地址
+#[derive(Hash, PartialEq, Eq)]
+pub(super) struct Link {
+    y: u16,
+    x: u16,
+    target: String,
+    // 同一位置的文字或样式变化后 tui 会重绘这些格子, 需要重新写入
+    style: Vec<(String, String)>,
+}
+
+// 大多数终端都能忽略不认识的 OSC 8, 但 Linux 控制台等会把它当字符显示, 只对已知支持的终端开启
+pub fn supports_hyperlinks() -> bool {
+    let var = |name: &str| std::env::var(name).unwrap_or_default();
+    if var("TERM") == "linux" || var("TERM").starts_with("screen") || std::env::var_os("TMUX").is_some() {
+        return false;
+    }
+    let program = var("TERM_PROGRAM");
+    ["iTerm.app", "WezTerm", "vscode", "ghostty", "Hyper", "Tabby", "rio"].contains(&program.as_str())
+        || var("VTE_VERSION").parse::<u32>().is_ok_and(|version| version >= 5000)
+        || std::env::var_os("KITTY_WINDOW_ID").is_some()
+        || std::env::var_os("WT_SESSION").is_some()
+        || std::env::var_os("KONSOLE_VERSION").is_some()
+        || ["xterm-kitty", "foot", "alacritty", "contour"]
+            .iter()
+            .any(|term| var("TERM").starts_with(term))
+}
+
+fn hostname() -> String {
+    std::fs::read_to_string("/etc/hostname")
+        .map(|name| name.trim().to_string())
+        .unwrap_or_default()
+}
+
+fn file_url(path: &str) -> String {
+    let mut url = format!("file://{}", hostname());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                url.push(byte as char)
+            }
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    url
+}
+
+pub(super) fn scan(buffer: &Buffer) -> Vec<(Link, Vec<Cell>)> {
+    let area = buffer.area;
+    let mut links = Vec::new();
+    for y in area.top()..area.bottom() {
+        // 拼出整行文字, 记下每个字节所在的列; 宽字符后面的格子是空串
+        let mut row = String::new();
+        let mut columns = Vec::new();
+        for x in area.left()..area.right() {
+            let symbol = &buffer.get(x, y).symbol;
+            row.push_str(symbol);
+            columns.extend(std::iter::repeat_n(x, symbol.len()));
+        }
+        let urls = URL.find_iter(&row).map(|m| (m.start(), m.end(), m.as_str().to_string()));
+        let files = FILE.captures_iter(&row).filter_map(|caps| {
+            let (whole, path) = (caps.get(0)?, caps.get(1)?);
+            Some((path.start(), whole.end(), file_url(path.as_str())))
+        });
+        for (start, end, target) in urls.chain(files) {
+            let (first, last) = (columns[start], columns[end - 1]);
+            let cells: Vec<Cell> = (first..=last).map(|x| buffer.get(x, y).clone()).collect();
+            let style = cells
+                .iter()
+                .map(|cell| (cell.symbol.clone(), format!("{:?}{:?}{:?}", cell.fg, cell.bg, cell.modifier)))
+                .collect();
+            links.push((
+                Link {
+                    y,
+                    x: first,
+                    target,
+                    style,
+                },
+                cells,
+            ));
+        }
+    }
+    links
+}
+
+// 每帧绘制后用 OSC 8 重写链接所在的格子; 只重写新出现的链接, 文字没变的格子 tui 不会覆盖
+#[derive(Default)]
+pub(super) struct Hyperlinks {
+    drawn: HashSet<Link>,
+}
+
+impl Hyperlinks {
+    // 清屏或尺寸变化后终端内容都会重画, 之前写入的链接也随之失效
+    pub(super) fn reset(&mut self) {
+        self.drawn.clear();
+    }
+
+    pub(super) fn update<B: Backend + Write>(
+        &mut self,
+        backend: &mut B,
+        links: Vec<(Link, Vec<Cell>)>,
+    ) -> io::Result<()> {
+        let mut drawn = HashSet::new();
+        let mut written = false;
+        for (link, cells) in links {
+            if !self.drawn.contains(&link) {
+                write!(backend, "\x1b]8;;{}\x1b\\", link.target)?;
+                backend.draw(cells.iter().zip(link.x..).map(|(cell, x)| (x, link.y, cell)))?;
+                write!(backend, "\x1b]8;;\x1b\\")?;
+                written = true;
+            }
+            drawn.insert(link);
+        }
+        if written {
+            Backend::flush(backend)?;
+        }
+        self.drawn = drawn;
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+mod action;
+mod control;
+mod formatter;
+mod input;
+mod layout;
+mod links;
+mod state;
+mod widgets;
+
+pub use formatter::create_log_formatter;
+pub use layout::apply_layout;
+pub use links::supports_hyperlinks;
+pub use state::{AppState, LogWindow, WrapOptions};
+
+use action::{Action, Effect};
+
+use std::io::{self, Stdout, Write};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use tui::{backend::CrosstermBackend, Terminal};
+
+use crossterm::{
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyEvent},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+use crate::codec::base64;
+use crate::i18n::{tr, Msg};
+use formatter::to_ansi;
+
+pub fn run_ui(app_state: &mut AppState) -> io::Result<()> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+    let mut hyperlinks = links::Hyperlinks::default();
+    let mut size = terminal.size()?;
+
+    loop {
+        if terminal.size()? != size {
+            size = terminal.size()?;
+            hyperlinks.reset();
+        }
+        let window_height = size.height as usize;
+
+        if let Some(picker) = app_state.file_picker.as_mut() {
+            picker.poll();
+        }
+        if let Some(grep) = app_state.grep.as_mut() {
+            grep.poll();
+        }
+        action::suspend_idle(app_state);
+        action::incremental_search(app_state);
+        control::poll(app_state);
+
+        let frame = terminal.draw(|f| widgets::draw(f, app_state))?;
+        if app_state.hyperlinks {
+            let links = links::scan(frame.buffer);
+            hyperlinks.update(terminal.backend_mut(), links)?;
+        }
+
+        if event::poll(Duration::from_millis(10))? {
+            if let Event::Key(key) = event::read()? {
+                // 按键可能打开分页器等清屏的操作, 之后重新写入所有链接
+                hyperlinks.reset();
+                if handle_key(&mut terminal, app_state, key, window_height, false)? {
+                    break;
+                }
+            }
+        }
+    }
+
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
+    terminal.show_cursor()?;
+
+    Ok(())
+}
+
+// 返回 true 表示退出; 回放宏时不再展开宏里的 @, 避免递归
+fn handle_key(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    app_state: &mut AppState,
+    key: KeyEvent,
+    window_height: usize,
+    replaying: bool,
+) -> io::Result<bool> {
+    let action = input::key_action(app_state, key);
+    if let Some((_, keys)) = app_state.recording.as_mut() {
+        if !matches!(action, Some(Action::StopRecording)) {
+            keys.push(key);
+        }
+    }
+    let Some(action) = action else {
+        return Ok(false);
+    };
+    match action::update(app_state, action, window_height) {
+        Some(Effect::Quit) => return Ok(true),
+        Some(Effect::SetMouseCapture(true)) => execute!(terminal.backend_mut(), EnableMouseCapture)?,
+        Some(Effect::SetMouseCapture(false)) => {
+            execute!(terminal.backend_mut(), DisableMouseCapture)?
+        }
+        Some(Effect::OpenPager { with_archive }) => open_in_pager(terminal, app_state, with_archive)?,
+        Some(Effect::Replay(keys)) if !replaying => {
+            for key in keys {
+                if handle_key(terminal, app_state, key, window_height, true)? {
+                    return Ok(true);
+                }
+            }
+        }
+        Some(Effect::Copy(text)) => {
+            // OSC 52 由终端写入系统剪贴板, 经过 SSH 也能用
+            let backend = terminal.backend_mut();
+            write!(backend, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+            backend.flush()?;
+        }
+        Some(Effect::Replay(_)) | None => {}
+    }
+    Ok(false)
+}
+
+// 暂停 TUI, 把选中窗口的缓冲区快照(保留颜色)交给 $PAGER,
+// with_archive 时在前面加上已归档的历史
+fn open_in_pager(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    app_state: &AppState,
+    with_archive: bool,
+) -> io::Result<()> {
+    let window = &app_state.log_windows[app_state.selected_window];
+    let mut snapshot: Vec<String> = Vec::new();
+    if let Some(archive) = window.archive.as_ref().filter(|_| with_archive) {
+        if let Some(e) = archive.last_error() {
+            snapshot.push(format!("{}: {}", tr(Msg::ArchiveErr), e));
+        }
+        match archive.load() {
+            Ok(lines) => snapshot.extend(lines),
+            Err(e) => snapshot.push(format!("{}: {}", tr(Msg::ArchiveErr), e)),
+        }
+    }
+    snapshot.extend(window.content.lock().unwrap().iter().cloned());
+
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
+
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| "less -R".to_string());
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(&pager).stdin(Stdio::piped());
+    if std::env::var_os("LESS").is_none() {
+        command.env("LESS", "-R");
+    }
+
+    let result = command.spawn().and_then(|mut child| {
+        if let Some(mut stdin) = child.stdin.take() {
+            for line in &snapshot {
+                let line = line.trim_end_matches(['\r', '\n']);
+                // 分页器提前退出时会关闭管道, 忽略写入错误
+                if writeln!(stdin, "{}", to_ansi(&window.formatter.format_line(line))).is_err() {
+                    break;
+                }
+            }
+        }
+        child.wait()
+    });
+
+    enable_raw_mode()?;
+    execute!(terminal.backend_mut(), EnterAlternateScreen)?;
+    if app_state.mouse_captured {
+        execute!(terminal.backend_mut(), EnableMouseCapture)?;
+    }
+    terminal.clear()?;
+
+    result.map(|_| ())
+}
@@ -0,0 +1,498 @@
+use super::formatter::{LogFormatter, ERROR_LEVEL, WARN_LEVEL};
+use crate::archive::Archive;
+use crate::compare::Divergence;
+use crate::dedup::Shared;
+use crate::fields::{self, FieldSummary};
+use crate::i18n::{tr, Msg};
+use crate::layout::LayoutConfig;
+use crate::ssh::{
+    position_of, shell_quote, ConnectionStats, ConnectionStatus, DownloadProgress, Position,
+    Recent, SourceRequest,
+};
+use crate::suggest::Suggestion;
+use crate::timestamp::Detector;
+
+use std::{
+    cell::Cell,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    sync::{
+        atomic::AtomicUsize,
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use regex::Regex;
+use tui::style::Color;
+
+use crossterm::event::KeyEvent;
+
+pub struct AppState {
+    pub log_windows: Vec<LogWindow>,
+    pub selected_window: usize,
+    pub is_maximized: bool,
+    pub show_inspector: bool,
+    pub mouse_captured: bool,
+    pub show_invisibles: bool,
+    // 用 OSC 8 把识别出的网址和文件路径写成可点击的链接
+    pub hyperlinks: bool,
+    pub file_picker: Option<FilePicker>,
+    pub prompt: Option<Prompt>,
+    pub grep: Option<GrepPopup>,
+    pub search: Option<Search>,
+    pub search_options: SearchOptions,
+    pub global_search: Option<GlobalSearch>,
+    pub control: Option<Receiver<crate::ipc::Request>>,
+    // 本次会话(以及保存下来的)搜索词, 在搜索框里用上下键调出
+    pub search_history: Vec<String>,
+    pub save_search_history: bool,
+    pub jump_to_line: Cell<Option<usize>>,
+    // 最大化窗口当前视图顶部的行, 渲染时更新
+    pub view_top: Cell<Option<usize>>,
+    // 标题栏上短暂显示的提示, 例如复制的行位置
+    pub notice: Option<(String, Instant)>,
+    pub time_cursor: Option<TimeCursor>,
+    // 普通布局中显示的窗口下标(按显示顺序)和对应的高度权重
+    pub visible: Vec<usize>,
+    pub weights: Vec<u16>,
+    // 按主机分组时折叠起来的主机, 折叠的组只显示标题行
+    pub group_by_host: bool,
+    pub collapsed: HashSet<String>,
+    pub layouts: BTreeMap<String, LayoutConfig>,
+    pub layout_label: Option<String>,
+    pub rule_popup: Option<RulePopup>,
+    pub field_popup: Option<FieldPopup>,
+    pub shared_popup: Option<SharedPopup>,
+    pub saved_searches: Vec<(String, String)>,
+    pub searches_popup: Option<SearchesPopup>,
+    pub config_path: String,
+    // [[filters]]: 绑定了数字键的过滤预设, 没被绑定的数字键仍然用来清除标签
+    pub filter_presets: Vec<crate::config::FilterPreset>,
+    // 按寄存器保存的按键宏, Q<寄存器> 开始录制, 再按 Q 结束, @<寄存器> 回放
+    pub macros: HashMap<char, Vec<KeyEvent>>,
+    pub recording: Option<(char, Vec<KeyEvent>)>,
+    pub macro_pending: Option<MacroKind>,
+}
+
+// 窗口标题下方的标签, 列出隐藏或冻结内容的状态, 按对应的数字键逐个清除
+pub(super) enum Chip {
+    Field(usize),
+    Sampling,
+    Paused,
+    Grep,
+    Search,
+    Filter(usize),
+}
+
+impl AppState {
+    pub(super) fn follows(&self) -> bool {
+        self.log_windows[self.selected_window].follow
+    }
+
+    pub(super) fn set_follow(&mut self, follow: bool) {
+        self.log_windows[self.selected_window].follow = follow;
+    }
+
+    pub(super) fn preset_for(&self, key: char) -> Option<usize> {
+        self.filter_presets
+            .iter()
+            .position(|preset| preset.key.as_deref().is_some_and(|k| k.chars().eq([key])))
+    }
+
+    pub(super) fn chips(&self, window: &LogWindow, is_selected: bool) -> Vec<(Chip, String)> {
+        let mut chips: Vec<(Chip, String)> = window
+            .json_filter
+            .iter()
+            .enumerate()
+            .map(|(i, (name, value))| (Chip::Field(i), format!("⧩ {}={}", name, value)))
+            .collect();
+        // 上下文行数只标在第一个 & 过滤上
+        let first_include = window.filters.iter().position(|filter| !filter.exclude);
+        for (i, filter) in window.filters.iter().enumerate() {
+            let context = if first_include == Some(i) && window.filter_context > 0 {
+                format!(" ±{}", window.filter_context)
+            } else {
+                String::new()
+            };
+            chips.push((
+                Chip::Filter(i),
+                format!(
+                    "{} {}{} ({} {})",
+                    tr(if filter.exclude { Msg::Excluded } else { Msg::Filtered }),
+                    filter.pattern,
+                    context,
+                    filter.hidden.get(),
+                    tr(Msg::Hidden)
+                ),
+            ));
+        }
+        if window.sample > 1 {
+            chips.push((Chip::Sampling, format!("1/{} {}", window.sample, tr(Msg::Sampled))));
+        }
+        if window.paused_at.lock().unwrap().is_some() {
+            chips.push((Chip::Paused, tr(Msg::PausedOnError).to_string()));
+        }
+        // 隐藏起来的 grep 结果只属于当前窗口
+        if let Some(grep) = self.grep.as_ref().filter(|grep| is_selected && !grep.visible) {
+            chips.push((Chip::Grep, format!("grep {}", grep.pattern)));
+        }
+        if let Some(search) = self.search.as_ref().filter(|_| is_selected) {
+            chips.push((Chip::Search, format!("/{}", search.query)));
+        }
+        chips
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum MacroKind {
+    Record,
+    Replay,
+}
+
+// JSON 字段过滤器: 先选字段, 再选取值, 条件之间是 AND
+pub struct FieldPopup {
+    pub(super) fields: Vec<FieldSummary>,
+    pub(super) selected: usize,
+    pub(super) field: Option<usize>,
+    pub(super) value_selected: usize,
+}
+
+// 最近在多个窗口里几乎同时出现的行, 通常是共用的基础设施出了问题
+pub struct SharedPopup {
+    pub(super) shared: Vec<Shared>,
+    pub(super) selected: usize,
+}
+
+// [searches] 的列表, selected 是 saved_searches 的下标
+pub struct SearchesPopup {
+    pub(super) selected: usize,
+}
+
+// 从缓冲区归纳出的高亮/告警规则, 编辑后追加到配置文件
+pub struct RulePopup {
+    pub(super) suggestions: Vec<Suggestion>,
+    pub(super) selected: usize,
+    pub(super) message: Option<(String, Color)>,
+}
+
+// 全局时间游标: 选中窗口里的一行, 其他窗口高亮时间最接近的行
+pub struct TimeCursor {
+    pub(super) window: usize,
+    pub(super) line: usize,
+    pub(super) millis: i64,
+}
+
+pub struct Prompt {
+    pub(super) kind: PromptKind,
+    pub(super) input: String,
+}
+
+pub(super) enum PromptKind {
+    RemoteGrep,
+    Search(Incremental),
+    GlobalSearch,
+    LineFilter,
+    Exclude,
+    SaveLayout,
+    EditRule,
+}
+
+// 边输入边搜索: 每次都从打开输入框时的视图位置找起, 取消时恢复原来的视图和搜索
+pub struct Incremental {
+    pub(super) origin: Option<usize>,
+    pub(super) view: (bool, bool),
+    pub(super) edited: Option<Instant>,
+    pub(super) previous: Option<Search>,
+    // 正在查看的历史条目, 以及开始翻历史前输入的内容
+    pub(super) recall: Option<usize>,
+    pub(super) draft: String,
+}
+
+// e 依次切换: 全部 → WARN 及以上 → 只看 ERROR
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Severity {
+    #[default]
+    All,
+    Warn,
+    Error,
+}
+
+impl Severity {
+    pub(super) fn next(self) -> Self {
+        match self {
+            Severity::All => Severity::Warn,
+            Severity::Warn => Severity::Error,
+            Severity::Error => Severity::All,
+        }
+    }
+
+    pub(super) fn admits(self, line: &str) -> bool {
+        match self {
+            Severity::All => true,
+            Severity::Warn => WARN_LEVEL.is_match(line) || ERROR_LEVEL.is_match(line),
+            Severity::Error => ERROR_LEVEL.is_match(line),
+        }
+    }
+
+    pub(super) fn label(self) -> &'static str {
+        match self {
+            Severity::All => "",
+            Severity::Warn => " [WARN+]",
+            Severity::Error => " [ERROR]",
+        }
+    }
+}
+
+// 搜索框里 Alt-r 切换正则/字面匹配, Alt-c 切换是否区分大小写, 之后的搜索沿用
+#[derive(Clone, Copy, Default)]
+pub struct SearchOptions {
+    pub(super) literal: bool,
+    pub(super) ignore_case: bool,
+}
+
+// 在选中窗口的缓冲区里搜索, n/N 在匹配行之间跳转
+pub struct Search {
+    pub(super) query: String,
+    pub(super) regex: Regex,
+    pub(super) line: Option<usize>,
+}
+
+// Ctrl-f 在所有窗口的缓冲区里搜索, 结果按窗口分组
+pub struct GlobalSearch {
+    pub(super) query: String,
+    pub(super) regex: Regex,
+    pub(super) groups: Vec<(usize, Vec<(usize, String)>)>,
+    pub(super) selected: usize,
+}
+
+impl GlobalSearch {
+    pub(super) fn len(&self) -> usize {
+        self.groups.iter().map(|(_, hits)| hits.len()).sum()
+    }
+
+    // 第 index 个结果所在的窗口和行
+    pub(super) fn hit(&self, mut index: usize) -> Option<(usize, usize)> {
+        for (window, hits) in &self.groups {
+            if let Some((line, _)) = hits.get(index) {
+                return Some((*window, *line));
+            }
+            index -= hits.len();
+        }
+        None
+    }
+}
+
+pub struct GrepPopup {
+    pub(super) pattern: String,
+    pub(super) results: Vec<String>,
+    pub(super) selected: usize,
+    pub(super) visible: bool,
+    pub(super) pending: Option<Receiver<Result<String, String>>>,
+    pub(super) error: Option<String>,
+    pub(super) return_view: Option<(bool, bool)>,
+}
+
+pub struct FilePicker {
+    pub(super) dir: String,
+    pub(super) entries: Vec<String>,
+    pub(super) selected: usize,
+    pub(super) pending: Option<Receiver<Result<String, String>>>,
+    pub(super) error: Option<String>,
+}
+
+pub struct LogWindow {
+    pub name: String,
+    pub host: String,
+    pub content: Arc<Mutex<Vec<String>>>,
+    pub formatter: Arc<LogFormatter>,
+    pub scroll_position: Arc<Mutex<usize>>,
+    // 上次绘制时视图顶部: (累计行号, 在该行折出的第几行, 当时的 scroll_position)
+    pub view_anchor: Cell<Option<(u64, usize, usize)>>,
+    pub connection_status: Arc<Mutex<ConnectionStatus>>,
+    pub stats: Arc<Mutex<ConnectionStats>>,
+    pub wrap_options: WrapOptions,
+    pub requests: Sender<SourceRequest>,
+    pub browse_dir: String,
+    pub paused_at: Arc<Mutex<Option<usize>>>,
+    pub source_paths: String,
+    pub download: Arc<Mutex<Option<DownloadProgress>>>,
+    pub archive: Option<Arc<Archive>>,
+    pub max_history: Arc<AtomicUsize>,
+    pub history_limit: usize,
+    pub json_filter: Vec<(String, String)>,
+    // & 和 ! 过滤按添加顺序叠加; 设置了 drop_excluded 时 ! 过滤的行在存入前就被丢弃
+    pub filters: Vec<LineFilter>,
+    // m 标记的行, 按累计行号记录, 淘汰旧行后仍然对得上
+    pub marks: Vec<u64>,
+    // W 切换不折行, 长行截断后用 ←/→ 横向滚动, h_scroll 是左边跳过的列数
+    pub wrap: bool,
+    pub h_scroll: usize,
+    // f 切换: 跟随时视图停在最新一行, 手动时保持滚动位置
+    pub follow: bool,
+    // F: 视图跟着最新的 ERROR 行走, 而不是跟着最新一行
+    pub follow_errors: bool,
+    // p 冻结显示: 只画到按下时为止的行(累计行号), 之后的行照常缓冲
+    pub frozen: Option<u64>,
+    pub severity: Severity,
+    pub dropped: Option<Arc<Mutex<Vec<Regex>>>>,
+    pub filter_context: usize,
+    // 抽样显示: 每 N 行显示一行, 错误行总是显示; 1 表示不抽样
+    pub sample: usize,
+    pub subtitle: Arc<Mutex<Option<String>>>,
+    pub positions: Arc<Mutex<VecDeque<Option<Position>>>>,
+    pub divergence: Arc<Mutex<Option<Divergence>>>,
+    pub timestamps: Arc<Detector>,
+    pub idle_disconnect: Option<Duration>,
+    pub recent: Option<Arc<Recent>>,
+    pub last_viewed: Instant,
+    pub suspended: bool,
+}
+
+// & 只显示匹配的行, ! 隐藏匹配的行; hidden 是上次渲染时因为这一条被隐藏的行数
+pub struct LineFilter {
+    pub(super) pattern: String,
+    pub(super) regex: Regex,
+    pub(super) exclude: bool,
+    pub(super) hidden: Cell<usize>,
+}
+
+impl LineFilter {
+    pub(super) fn admits(&self, line: &str) -> bool {
+        self.regex.is_match(line) != self.exclude
+    }
+}
+
+impl LogWindow {
+    // 是否通过了 JSON 字段过滤、所有 & 和 ! 过滤以及级别过滤
+    pub(super) fn shows(&self, line: &str) -> bool {
+        fields::matches(line, &self.json_filter)
+            && self.filters.iter().all(|filter| filter.admits(line))
+            && self.severity.admits(line)
+    }
+
+    // 缓冲区第一行的累计行号
+    pub(super) fn first_number(&self, len: usize) -> u64 {
+        self.stats.lock().unwrap().lines_received.saturating_sub(len as u64)
+    }
+
+    pub(super) fn has_include(&self) -> bool {
+        self.filters.iter().any(|filter| !filter.exclude)
+    }
+
+    pub(super) fn position(&self, line: usize) -> Option<Position> {
+        let content = self.content.lock().unwrap();
+        position_of(&content, &self.positions.lock().unwrap(), line)
+    }
+}
+
+pub struct WrapOptions {
+    pub marker: String,
+    pub hanging_indent: usize,
+    pub words: bool,
+}
+
+impl WrapOptions {
+    pub(super) fn prefix(&self) -> String {
+        format!("{}{}", self.marker, " ".repeat(self.hanging_indent))
+    }
+}
+
+const GREP_MAX_RESULTS: usize = 1000;
+
+impl GrepPopup {
+    pub(super) fn open(window: &LogWindow, pattern: String) -> Self {
+        let (reply, pending) = mpsc::channel();
+        let command = format!(
+            "grep -n -H -e {} -- {} | tail -n {}",
+            shell_quote(&pattern),
+            window.source_paths,
+            GREP_MAX_RESULTS
+        );
+        let error = window
+            .requests
+            .send(SourceRequest::Exec { command, reply })
+            .err()
+            .map(|e| e.to_string());
+
+        GrepPopup {
+            pattern,
+            results: Vec::new(),
+            selected: 0,
+            visible: true,
+            pending: Some(pending),
+            error,
+            return_view: None,
+        }
+    }
+
+    pub(super) fn poll(&mut self) {
+        let Some(pending) = &self.pending else {
+            return;
+        };
+        match pending.try_recv() {
+            Ok(Ok(output)) => {
+                self.results = output.lines().map(str::to_string).collect();
+                self.selected = self.results.len().saturating_sub(1);
+                self.pending = None;
+            }
+            Ok(Err(e)) => {
+                self.error = Some(e);
+                self.pending = None;
+            }
+            Err(mpsc::TryRecvError::Empty) => {}
+            Err(mpsc::TryRecvError::Disconnected) => self.pending = None,
+        }
+    }
+}
+
+impl FilePicker {
+    pub(super) fn open(window: &LogWindow, dir: String) -> Self {
+        let (reply, pending) = mpsc::channel();
+        let command = format!("cd {} && ls -1pA", shell_quote(&dir));
+        let error = window
+            .requests
+            .send(SourceRequest::Exec { command, reply })
+            .err()
+            .map(|e| e.to_string());
+
+        FilePicker {
+            dir,
+            entries: Vec::new(),
+            selected: 0,
+            pending: Some(pending),
+            error,
+        }
+    }
+
+    pub(super) fn poll(&mut self) {
+        let Some(pending) = &self.pending else {
+            return;
+        };
+        match pending.try_recv() {
+            Ok(Ok(output)) => {
+                self.entries = std::iter::once("../".to_string())
+                    .chain(output.lines().map(str::to_string))
+                    .collect();
+                self.pending = None;
+            }
+            Ok(Err(e)) => {
+                self.error = Some(e);
+                self.pending = None;
+            }
+            Err(mpsc::TryRecvError::Empty) => {}
+            Err(mpsc::TryRecvError::Disconnected) => self.pending = None,
+        }
+    }
+
+    pub(super) fn entry_path(&self, entry: &str) -> String {
+        if entry == "../" {
+            let trimmed = self.dir.trim_end_matches('/');
+            return match trimmed.rsplit_once('/') {
+                Some(("", _)) | None => "/".to_string(),
+                Some((parent, _)) => parent.to_string(),
+            };
+        }
+        format!("{}/{}", self.dir.trim_end_matches('/'), entry.trim_end_matches('/'))
+    }
+}
@@ -0,0 +1,1119 @@
+use super::formatter::{parse_color, ERROR_LEVEL};
+use super::layout::{centered_rect, render_maximized_window, render_normal_layout};
+use super::state::{
+    AppState, Chip, FieldPopup, FilePicker, GrepPopup, LogWindow, Prompt, PromptKind, RulePopup,
+    GlobalSearch, SearchOptions, SearchesPopup, SharedPopup,
+};
+use crate::fields;
+use crate::i18n::{tr, Msg};
+use crate::timestamp;
+use crate::{
+    io::Stdout,
+    ssh::{ConnectionStats, ConnectionStatus, DownloadProgress, SEVERE_LINE},
+};
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+
+use std::{
+    borrow::Cow,
+    sync::atomic::Ordering,
+    time::Duration,
+};
+
+use tui::{
+    backend::CrosstermBackend,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+pub(super) fn draw(f: &mut Frame<CrosstermBackend<Stdout>>, app_state: &AppState) {
+    if app_state.is_maximized {
+        render_maximized_window(f, app_state);
+    } else {
+        render_normal_layout(f, app_state);
+    }
+    if let Some(picker) = &app_state.file_picker {
+        render_file_picker(f, picker);
+    }
+    if let Some(grep) = app_state.grep.as_ref().filter(|g| g.visible) {
+        render_grep_popup(f, grep);
+    }
+    if let Some(popup) = &app_state.rule_popup {
+        render_rule_popup(f, popup);
+    }
+    if let Some(popup) = &app_state.field_popup {
+        render_field_popup(f, popup, &app_state.log_windows[app_state.selected_window]);
+    }
+    if let Some(popup) = &app_state.searches_popup {
+        render_searches_popup(f, app_state, popup);
+    }
+    if let Some(popup) = &app_state.shared_popup {
+        render_shared_popup(f, popup);
+    }
+    if let Some(search) = &app_state.global_search {
+        render_global_search(f, search, app_state);
+    }
+    if let Some(prompt) = &app_state.prompt {
+        render_prompt(f, prompt, app_state.search_options);
+    }
+}
+
+// 时间游标在该窗口对应的行
+fn time_cursor_line(
+    app_state: &AppState,
+    window: &LogWindow,
+    content: &[String],
+    is_selected: bool,
+) -> Option<usize> {
+    let cursor = app_state.time_cursor.as_ref()?;
+    if is_selected && cursor.window == app_state.selected_window {
+        Some(cursor.line.min(content.len().checked_sub(1)?))
+    } else {
+        timestamp::nearest_line(content, cursor.millis, window.timestamps.format())
+    }
+}
+
+fn format_download(progress: &DownloadProgress) -> String {
+    if let Some(error) = &progress.error {
+        return format!(" [⇩ {}: {}]", tr(Msg::DownloadErr), error);
+    }
+    if progress.finished {
+        return format!(" [⇩ {} {}]", tr(Msg::Saved), progress.local_path.display());
+    }
+    let percent = (progress.done * 100).checked_div(progress.total).unwrap_or(0);
+    format!(" [⇩ {}% {}]", percent, format_bytes(progress.done))
+}
+
+fn render_prompt(f: &mut Frame<CrosstermBackend<Stdout>>, prompt: &Prompt, options: SearchOptions) {
+    let size = f.size();
+    let area = Rect::new(size.x, size.height.saturating_sub(3), size.width, 3.min(size.height));
+    let label = match prompt.kind {
+        PromptKind::RemoteGrep => tr(Msg::GrepPrompt).to_string(),
+        // 当前的匹配方式一直显示在输入框前面
+        PromptKind::Search(_) => format!(
+            "{} [Alt-r {} · Alt-c {}]",
+            tr(Msg::SearchPrompt),
+            tr(if options.literal { Msg::SearchLiteral } else { Msg::SearchRegex }),
+            tr(if options.ignore_case { Msg::SearchIgnoreCase } else { Msg::SearchMatchCase })
+        ),
+        PromptKind::GlobalSearch => tr(Msg::GlobalSearchPrompt).to_string(),
+        PromptKind::LineFilter => tr(Msg::FilterPrompt).to_string(),
+        PromptKind::Exclude => tr(Msg::ExcludePrompt).to_string(),
+        PromptKind::SaveLayout => tr(Msg::SaveLayoutPrompt).to_string(),
+        PromptKind::EditRule => tr(Msg::RulePattern).to_string(),
+    };
+    let paragraph = Paragraph::new(Spans::from(vec![
+        Span::styled(format!("{}: ", label), Style::default().fg(Color::Yellow)),
+        Span::raw(prompt.input.clone()),
+        Span::styled("█", Style::default().fg(Color::Gray)),
+    ]))
+    .block(Block::default().borders(Borders::ALL))
+    .style(Style::default().bg(Color::Black));
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+fn render_rule_popup(f: &mut Frame<CrosstermBackend<Stdout>>, popup: &RulePopup) {
+    let area = centered_rect(f.size(), 90, 80);
+    let block = Block::default()
+        .title(tr(Msg::RuleSuggestTitle))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    let mut items: Vec<ListItem> = popup
+        .suggestions
+        .iter()
+        .map(|suggestion| {
+            let color = suggestion.rule.color.as_deref().and_then(parse_color).unwrap_or(Color::White);
+            let flags = format!(
+                "{}{}",
+                if suggestion.saved { "✓ " } else { "" },
+                if suggestion.rule.alert == Some(true) { "⚑ " } else { "" }
+            );
+            ListItem::new(vec![
+                Spans::from(vec![
+                    Span::raw(format!("{:>5}× {}", suggestion.count, flags)),
+                    Span::styled(suggestion.rule.pattern.clone(), Style::default().fg(color)),
+                ]),
+                Spans::from(Span::styled(
+                    format!("       {}", suggestion.example),
+                    Style::default().fg(Color::DarkGray),
+                )),
+            ])
+        })
+        .collect();
+    if items.is_empty() {
+        items.push(ListItem::new(Span::raw(tr(Msg::NoSuggestions))));
+    }
+    if let Some((message, color)) = &popup.message {
+        items.push(ListItem::new(Span::styled(message.clone(), Style::default().fg(*color))));
+    }
+
+    let list = List::new(items)
+        .block(block)
+        .style(Style::default().bg(Color::Black))
+        .highlight_style(Style::default().bg(Color::Rgb(70, 70, 110)));
+
+    let mut state = ListState::default();
+    if !popup.suggestions.is_empty() {
+        state.select(Some(popup.selected));
+    }
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn format_json_filter(filter: &[(String, String)]) -> String {
+    filter
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join(" AND ")
+}
+
+fn render_field_popup(f: &mut Frame<CrosstermBackend<Stdout>>, popup: &FieldPopup, window: &LogWindow) {
+    let area = centered_rect(f.size(), 70, 70);
+    let block = Block::default()
+        .title(format!(
+            "{} [{}]",
+            tr(Msg::FieldFilterTitle),
+            format_json_filter(&window.json_filter)
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    let active = |name: &str, value: Option<&str>| {
+        window
+            .json_filter
+            .iter()
+            .any(|(n, v)| n == name && value.is_none_or(|value| v == value))
+    };
+    let (mut items, selected): (Vec<ListItem>, usize) = match popup.field {
+        None => (
+            popup
+                .fields
+                .iter()
+                .map(|field| {
+                    let mark = if active(&field.name, None) { "● " } else { "  " };
+                    ListItem::new(format!("{}{} ({})", mark, field.name, field.values.len()))
+                })
+                .collect(),
+            popup.selected,
+        ),
+        Some(i) => {
+            let field = &popup.fields[i];
+            (
+                field
+                    .values
+                    .iter()
+                    .map(|(value, count)| {
+                        let mark = if active(&field.name, Some(value)) { "● " } else { "  " };
+                        ListItem::new(format!("{}{:>5}× {}={}", mark, count, field.name, value))
+                    })
+                    .collect(),
+                popup.value_selected,
+            )
+        }
+    };
+    let empty = items.is_empty();
+    if empty {
+        items.push(ListItem::new(Span::raw(tr(Msg::NoJsonFields))));
+    }
+
+    let list = List::new(items)
+        .block(block)
+        .style(Style::default().bg(Color::Black))
+        .highlight_style(Style::default().bg(Color::Rgb(70, 70, 110)));
+
+    let mut state = ListState::default();
+    if !empty {
+        state.select(Some(selected));
+    }
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn render_global_search(f: &mut Frame<CrosstermBackend<Stdout>>, search: &GlobalSearch, app_state: &AppState) {
+    let area = centered_rect(f.size(), 90, 80);
+    let block = Block::default()
+        .title(format!(
+            "{} /{}/ ({}) - {}",
+            tr(Msg::GlobalSearchPrompt),
+            search.query,
+            search.len(),
+            tr(Msg::GlobalSearchHelp)
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    // 每组前面是窗口名, 选中项要跳过这些标题行
+    let mut items: Vec<ListItem> = Vec::new();
+    let mut selected_item = None;
+    let mut index = 0;
+    for (window, hits) in &search.groups {
+        items.push(ListItem::new(Span::styled(
+            format!("{} ({})", app_state.log_windows[*window].name, hits.len()),
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        )));
+        for (_, text) in hits {
+            if index == search.selected {
+                selected_item = Some(items.len());
+            }
+            let window = &app_state.log_windows[*window];
+            let mut spans = window.formatter.format_line_searched(text.trim_end(), Some(&search.regex));
+            spans.0.insert(0, Span::raw("  "));
+            items.push(ListItem::new(spans));
+            index += 1;
+        }
+    }
+    if items.is_empty() {
+        items.push(ListItem::new(Span::raw(tr(Msg::NoMatch))));
+    }
+
+    let list = List::new(items)
+        .block(block)
+        .style(Style::default().bg(Color::Black))
+        .highlight_style(Style::default().bg(Color::Rgb(70, 70, 110)));
+
+    let mut state = ListState::default();
+    state.select(selected_item);
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn render_shared_popup(f: &mut Frame<CrosstermBackend<Stdout>>, popup: &SharedPopup) {
+    let area = centered_rect(f.size(), 90, 80);
+    let block = Block::default()
+        .title(tr(Msg::SharedTitle))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    let mut items: Vec<ListItem> = popup
+        .shared
+        .iter()
+        .map(|shared| {
+            let sources: Vec<String> = shared
+                .sources
+                .iter()
+                .map(|(name, count)| format!("{}×{}", name, count))
+                .collect();
+            ListItem::new(vec![
+                Spans::from(vec![
+                    Span::styled(format!("{:>3} ", shared.sources.len()), Style::default().fg(Color::Red)),
+                    Span::raw(shared.template.clone()),
+                ]),
+                Spans::from(Span::styled(
+                    format!(
+                        "    {} {} · {}",
+                        format_duration(shared.last.elapsed()),
+                        tr(Msg::Ago),
+                        sources.join("  ")
+                    ),
+                    Style::default().fg(Color::DarkGray),
+                )),
+            ])
+        })
+        .collect();
+    if items.is_empty() {
+        items.push(ListItem::new(Span::raw(tr(Msg::NoSharedLines))));
+    }
+
+    let list = List::new(items)
+        .block(block)
+        .style(Style::default().bg(Color::Black))
+        .highlight_style(Style::default().bg(Color::Rgb(70, 70, 110)));
+
+    let mut state = ListState::default();
+    if !popup.shared.is_empty() {
+        state.select(Some(popup.selected));
+    }
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn render_searches_popup(f: &mut Frame<CrosstermBackend<Stdout>>, app_state: &AppState, popup: &SearchesPopup) {
+    let area = centered_rect(f.size(), 70, 60);
+    let block = Block::default()
+        .title(tr(Msg::SearchesTitle))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    let width = app_state.saved_searches.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0);
+    let mut items: Vec<ListItem> = app_state
+        .saved_searches
+        .iter()
+        .map(|(name, pattern)| {
+            ListItem::new(Spans::from(vec![
+                Span::styled(format!("{:<width$}  ", name), Style::default().fg(Color::Yellow)),
+                Span::raw(pattern.clone()),
+            ]))
+        })
+        .collect();
+    if items.is_empty() {
+        items.push(ListItem::new(Span::raw(tr(Msg::NoSavedSearches))));
+    }
+
+    let list = List::new(items)
+        .block(block)
+        .style(Style::default().bg(Color::Black))
+        .highlight_style(Style::default().bg(Color::Rgb(70, 70, 110)));
+
+    let mut state = ListState::default();
+    if !app_state.saved_searches.is_empty() {
+        state.select(Some(popup.selected));
+    }
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn render_grep_popup(f: &mut Frame<CrosstermBackend<Stdout>>, grep: &GrepPopup) {
+    let area = centered_rect(f.size(), 90, 80);
+    let title = format!(
+        "grep /{}/ ({}) - {}",
+        grep.pattern,
+        grep.results.len(),
+        tr(Msg::GrepHelp)
+    );
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    f.render_widget(Clear, area);
+
+    if grep.pending.is_some() {
+        let paragraph = Paragraph::new(tr(Msg::Loading))
+            .block(block)
+            .style(Style::default().bg(Color::Black));
+        f.render_widget(paragraph, area);
+        return;
+    }
+
+    let mut items: Vec<ListItem> = grep
+        .results
+        .iter()
+        .map(|result| ListItem::new(Span::raw(result.clone())))
+        .collect();
+    if let Some(error) = &grep.error {
+        items.push(ListItem::new(Span::styled(
+            error.clone(),
+            Style::default().fg(Color::Red),
+        )));
+    }
+    let list = List::new(items)
+        .block(block)
+        .style(Style::default().bg(Color::Black))
+        .highlight_style(Style::default().fg(Color::Black).bg(Color::Yellow));
+
+    let mut state = ListState::default();
+    state.select(Some(grep.selected));
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn render_file_picker(f: &mut Frame<CrosstermBackend<Stdout>>, picker: &FilePicker) {
+    let area = centered_rect(f.size(), 60, 70);
+    let block = Block::default()
+        .title(format!("{} - {}", tr(Msg::PickerTitle), picker.dir))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    f.render_widget(Clear, area);
+
+    if picker.pending.is_some() || picker.error.is_some() {
+        let (message, color) = match &picker.error {
+            Some(e) => (e.clone(), Color::Red),
+            None => (tr(Msg::Loading).to_string(), Color::White),
+        };
+        let paragraph = Paragraph::new(Span::styled(message, Style::default().fg(color)))
+            .block(block)
+            .style(Style::default().bg(Color::Black));
+        f.render_widget(paragraph, area);
+        return;
+    }
+
+    let items: Vec<ListItem> = picker
+        .entries
+        .iter()
+        .map(|entry| {
+            let color = if entry.ends_with('/') { Color::Cyan } else { Color::White };
+            ListItem::new(Span::styled(entry.clone(), Style::default().fg(color)))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(block)
+        .style(Style::default().bg(Color::Black))
+        .highlight_style(Style::default().fg(Color::Black).bg(Color::Yellow));
+
+    let mut state = ListState::default();
+    state.select(Some(picker.selected));
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+const NOTICE_DURATION: Duration = Duration::from_secs(5);
+
+pub(super) fn render_window(
+    f: &mut Frame<CrosstermBackend<Stdout>>,
+    app_state: &AppState,
+    window: &LogWindow,
+    area: Rect,
+    is_selected: bool,
+) -> Option<usize> {
+    let content = window.content.lock().unwrap();
+    let mut scroll_position = window.scroll_position.lock().unwrap();
+    let connection_status = window.connection_status.lock().unwrap();
+    let stats = format_stats(&window.stats.lock().unwrap(), content.len());
+    let paused_at = *window.paused_at.lock().unwrap();
+    let download = window
+        .download
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(format_download)
+        .unwrap_or_default();
+    let time_cursor = app_state
+        .time_cursor
+        .as_ref()
+        .map(|c| format!(" [⌖ {}]", timestamp::format_time_of_day(c.millis)))
+        .unwrap_or_default();
+
+    let auth = match &*connection_status {
+        ConnectionStatus::Authenticated(method) => format!(" [{}]", method.as_str()),
+        ConnectionStatus::Suspended => format!(" [{}]", tr(Msg::Suspended)),
+        _ => String::new(),
+    };
+    let mouse = if is_selected && !app_state.mouse_captured {
+        format!(" [{}]", tr(Msg::MouseOff))
+    } else {
+        String::new()
+    };
+    let recording = match &app_state.recording {
+        Some((register, _)) if is_selected => format!(" [● {} {}]", tr(Msg::Recording), register),
+        _ => String::new(),
+    };
+    let layout = match &app_state.layout_label {
+        Some(label) if is_selected => format!(" [⊞ {}]", label),
+        _ => String::new(),
+    };
+    let subtitle = window
+        .subtitle
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|s| format!(" {}", s))
+        .unwrap_or_default();
+    let notice = match &app_state.notice {
+        Some((notice, at)) if is_selected && at.elapsed() < NOTICE_DURATION => {
+            format!(" [{}]", notice)
+        }
+        _ => String::new(),
+    };
+    let divergence = match &*window.divergence.lock().unwrap() {
+        Some(divergence) if !divergence.patterns.is_empty() => {
+            let (template, count) = &divergence.patterns[0];
+            let template: String = template.chars().take(40).collect();
+            format!(
+                " [≠ {} {} {}: {} ×{}]",
+                divergence.patterns.len(),
+                tr(Msg::NotOnPeer),
+                divergence.peer,
+                template,
+                count
+            )
+        }
+        _ => String::new(),
+    };
+    let frozen = match window.frozen {
+        Some(number) => format!(
+            " [{} (+{} {})]",
+            tr(Msg::Frozen),
+            window.stats.lock().unwrap().lines_received.saturating_sub(number),
+            tr(Msg::NewLines)
+        ),
+        None => String::new(),
+    };
+    let no_wrap = if window.wrap {
+        String::new()
+    } else {
+        format!(" [{} +{}]", tr(Msg::NoWrap), window.h_scroll)
+    };
+    let max_history = window.max_history.load(Ordering::Relaxed);
+    let shrunk = if max_history < window.history_limit {
+        format!(" [{} {}]", tr(Msg::HistoryShrunk), max_history)
+    } else {
+        String::new()
+    };
+
+    let block = Block::default()
+        .title(format!(
+            "{} {}{}{}{}{}{} ({}: {}) [{}]{}{}{}{}{}{}{}{}{}",
+            if window.follow { tr(Msg::Follow) } else { tr(Msg::Manual) },
+            window.name,
+            subtitle,
+            window.severity.label(),
+            if window.follow_errors { " [→ ERROR]" } else { "" },
+            frozen,
+            no_wrap,
+            tr(Msg::Scroll),
+            *scroll_position,
+            stats,
+            auth,
+            time_cursor,
+            download,
+            mouse,
+            recording,
+            layout,
+            shrunk,
+            divergence,
+            notice
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(if is_selected {
+            Color::Yellow
+        } else {
+            Color::White
+        }));
+
+    let chips = app_state.chips(window, is_selected);
+    // 搜索只作用于选中的窗口
+    let search = app_state.search.as_ref().filter(|_| is_selected).map(|search| &search.regex);
+    // 有标记时左边留一列标出标记的行
+    let gutter = !window.marks.is_empty();
+    let inner_width = (area.width as usize).saturating_sub(if gutter { 3 } else { 2 }).max(1);
+    // 有标签时内容区让出第一行
+    let height = (area.height as usize).saturating_sub(if chips.is_empty() { 2 } else { 3 });
+
+    let mut wrapped_content: Vec<Spans> = Vec::new();
+    let mut row_sources: Vec<usize> = Vec::new();
+    let mut total_lines: usize = 0;
+
+    let continuation_prefix = window.wrap_options.prefix();
+    let continuation_width = inner_width
+        .saturating_sub(unicode_width::UnicodeWidthStr::width(continuation_prefix.as_str()))
+        .max(1);
+
+    // 按累计行号抽样, 淘汰旧行时抽中的行不会跳动
+    let first_number = window.first_number(content.len());
+    // 隐藏的行算在第一条不放行它的过滤上
+    let mut hidden = vec![0; window.filters.len()];
+    let mut gap = false;
+    let context = window.filter_context;
+    let includes: Vec<&Regex> = window.filters.iter().filter(|f| !f.exclude).map(|f| &f.regex).collect();
+    let kept = (!includes.is_empty()).then(|| context_mask(&content, &includes, context));
+    let frozen_at = window.frozen.map(|number| number.saturating_sub(first_number) as usize);
+    for (line_index, line) in content.iter().enumerate() {
+        if frozen_at.is_some_and(|end| line_index >= end) {
+            break;
+        }
+        if !fields::matches(line, &window.json_filter) {
+            continue;
+        }
+        if let Some(i) = window.filters.iter().position(|f| f.exclude && !f.admits(line)) {
+            hidden[i] += 1;
+            continue;
+        }
+        if !window.severity.admits(line) {
+            continue;
+        }
+        if let Some(kept) = &kept {
+            if !kept[line_index] {
+                if let Some(i) = window.filters.iter().position(|f| !f.admits(line)) {
+                    hidden[i] += 1;
+                }
+                gap = true;
+                continue;
+            }
+            // 和 grep -C 一样, 不相邻的匹配组之间用 -- 分隔
+            if gap && context > 0 && total_lines > 0 {
+                wrapped_content.push(Spans::from(Span::styled("--", Style::default().fg(Color::DarkGray))));
+                row_sources.push(line_index);
+                total_lines += 1;
+            }
+            gap = false;
+        }
+        if window.sample > 1
+            && !(first_number + line_index as u64).is_multiple_of(window.sample as u64)
+            && !SEVERE_LINE.is_match(line)
+        {
+            continue;
+        }
+        let line: Cow<str> = if app_state.show_invisibles {
+            Cow::Owned(reveal_invisibles(line))
+        } else {
+            Cow::Borrowed(line)
+        };
+        let (wrapped, cut) = if window.wrap {
+            (wrap_line(&line, inner_width, continuation_width, window.wrap_options.words), (false, false))
+        } else {
+            let (clipped, left, right) = clip_line(&line, window.h_scroll, inner_width);
+            (vec![clipped], (left, right))
+        };
+        for (row, wrapped_line) in wrapped.iter().enumerate() {
+            let mut spans = window.formatter.format_line_searched(wrapped_line, search);
+            if app_state.show_invisibles {
+                spans = mark_invisibles(spans);
+            }
+            // 不折行时左右两端被截掉的部分用 ‹ › 标出
+            if cut.0 {
+                spans.0.insert(0, Span::styled("‹", Style::default().fg(Color::DarkGray)));
+            }
+            if cut.1 {
+                spans.0.push(Span::styled("›", Style::default().fg(Color::DarkGray)));
+            }
+            if row > 0 && !continuation_prefix.is_empty() {
+                spans.0.insert(
+                    0,
+                    Span::styled(
+                        continuation_prefix.clone(),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                );
+            }
+            if gutter {
+                let marked = row == 0 && window.marks.binary_search(&(first_number + line_index as u64)).is_ok();
+                spans.0.insert(
+                    0,
+                    if marked {
+                        Span::styled("▌", Style::default().fg(Color::Yellow))
+                    } else {
+                        Span::raw(" ")
+                    },
+                );
+            }
+            wrapped_content.push(spans);
+            row_sources.push(line_index);
+            total_lines += 1;
+        }
+    }
+
+    for (filter, hidden) in window.filters.iter().zip(hidden) {
+        filter.hidden.set(hidden);
+    }
+
+    let manual = !window.follow;
+    // 手动时按行号留在原来的位置, 新行写入或旧行淘汰都不会挪动视图; 滚动过就以新位置为准
+    if let Some((number, offset, recorded)) = window.view_anchor.get().filter(|_| manual) {
+        if recorded == *scroll_position {
+            if let Some(row) = row_sources.iter().position(|&l| first_number + l as u64 >= number) {
+                let same = first_number + row_sources[row] as u64 == number;
+                *scroll_position = row + if same { offset } else { 0 };
+            }
+        }
+    }
+
+    if is_selected {
+        if let Some(line) = app_state.jump_to_line.take() {
+            if let Some(row) = row_sources.iter().position(|&l| l == line) {
+                *scroll_position = row;
+            }
+        }
+    }
+
+    let pause_row = paused_at.and_then(|line| row_sources.iter().rposition(|&l| l == line));
+    let cursor_line = time_cursor_line(app_state, window, &content, is_selected);
+    let cursor_row = cursor_line.and_then(|line| row_sources.iter().position(|&l| l == line));
+    let error_row = window
+        .follow_errors
+        .then(|| row_sources.iter().rposition(|&l| ERROR_LEVEL.is_match(&content[l])))
+        .flatten();
+
+    if let Some(row) = cursor_row {
+        // 时间游标所在行保持在视图中间
+        *scroll_position = row
+            .saturating_sub(height / 2)
+            .min(total_lines.saturating_sub(height));
+    } else if let (false, Some(row)) = (manual, error_row) {
+        // 最新的错误行停在视图中间, 上下文都能看到
+        *scroll_position = row
+            .saturating_sub(height / 2)
+            .min(total_lines.saturating_sub(height));
+    } else if let (false, Some(row)) = (manual, pause_row) {
+        // 让触发暂停的错误行停在视图底部
+        *scroll_position = (row + 1).saturating_sub(height);
+    } else if !manual {
+        *scroll_position = total_lines.saturating_sub(height);
+    } else {
+        *scroll_position = (*scroll_position).min(total_lines.saturating_sub(height));
+    }
+
+    let start = *scroll_position;
+    let top_line = row_sources.get(start).copied();
+    window.view_anchor.set(top_line.map(|line| {
+        let first_row = row_sources.iter().position(|&l| l == line).unwrap_or(start);
+        (first_number + line as u64, start - first_row, start)
+    }));
+    let mut text: Vec<Spans> = wrapped_content
+        .into_iter()
+        .skip(start)
+        .take(height)
+        .collect();
+
+    if let Some(cursor_line) = cursor_line {
+        for (row, spans) in text.iter_mut().enumerate() {
+            if row_sources.get(start + row) == Some(&cursor_line) {
+                for span in spans.0.iter_mut() {
+                    span.style = span.style.bg(Color::Rgb(70, 70, 110));
+                }
+            }
+        }
+    }
+
+    if let Some(error_line) = error_row.map(|row| row_sources[row]) {
+        for (row, spans) in text.iter_mut().enumerate() {
+            if row_sources.get(start + row) == Some(&error_line) {
+                for span in spans.0.iter_mut() {
+                    span.style = span.style.bg(Color::Rgb(90, 30, 30));
+                }
+            }
+        }
+    }
+
+    // 新到的行底色从亮到暗淡出, 已有底色(搜索、游标)的片段不变; 冻结时不变化
+    if let Some(recent) = window.recent.as_ref().filter(|_| window.frozen.is_none()) {
+        for (row, spans) in text.iter_mut().enumerate() {
+            let Some(&line) = row_sources.get(start + row) else {
+                continue;
+            };
+            if let Some(fresh) = recent.freshness(content.len() - 1 - line) {
+                let level = |max: f32| (max * fresh) as u8;
+                let bg = Color::Rgb(level(70.0), level(70.0), level(35.0));
+                for span in spans.0.iter_mut().filter(|span| span.style.bg.is_none()) {
+                    span.style = span.style.bg(bg);
+                }
+            }
+        }
+    }
+
+    if let ConnectionStatus::Error(err_msg) = &*connection_status {
+        if text.len() < height {
+            text.push(Spans::from(Span::styled(
+                err_msg,
+                Style::default().fg(Color::Red),
+            )));
+        } else if height > 0 {
+            text[height - 1] = Spans::from(Span::styled(err_msg, Style::default().fg(Color::Red)));
+        }
+    }
+
+    if !chips.is_empty() {
+        text.insert(0, chip_spans(&chips, is_selected));
+    }
+
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .style(Style::default().fg(Color::White).bg(Color::Black));
+
+    f.render_widget(paragraph, area);
+
+    top_line
+}
+
+// 同时匹配所有 & 过滤的行及其前后 context 行
+fn context_mask(lines: &[String], regexes: &[&Regex], context: usize) -> Vec<bool> {
+    let mut kept = vec![false; lines.len()];
+    for (i, line) in lines.iter().enumerate() {
+        if regexes.iter().all(|regex| regex.is_match(line)) {
+            let end = (i + context + 1).min(lines.len());
+            kept[i.saturating_sub(context)..end].fill(true);
+        }
+    }
+    kept
+}
+
+fn chip_spans(chips: &[(Chip, String)], is_selected: bool) -> Spans<'static> {
+    let key_style = if is_selected {
+        Style::default().fg(Color::Black).bg(Color::Yellow)
+    } else {
+        Style::default().fg(Color::Black).bg(Color::Gray)
+    };
+    let spans = chips.iter().enumerate().flat_map(|(i, (_, label))| {
+        [
+            Span::styled(format!(" {} ", i + 1), key_style),
+            Span::styled(format!(" {} ", label), Style::default().fg(Color::White).bg(Color::DarkGray)),
+            Span::raw(" "),
+        ]
+    });
+    Spans::from(spans.collect::<Vec<_>>())
+}
+
+// 列出视图顶部那一行命中的所有规则及其区间, 用于排查重叠规则导致的错误着色
+pub(super) fn render_inspector(
+    f: &mut Frame<CrosstermBackend<Stdout>>,
+    window: &LogWindow,
+    line_index: Option<usize>,
+) {
+    let position = line_index.and_then(|i| window.position(i));
+    let content = window.content.lock().unwrap();
+    let line = line_index
+        .and_then(|i| content.get(i))
+        .map(|line| line.trim_end_matches(['\r', '\n']))
+        .unwrap_or("");
+
+    let mut text = vec![
+        Spans::from(Span::styled(
+            format!("{} {}:", tr(Msg::Line), line_index.map(|i| i + 1).unwrap_or(0)),
+            Style::default().fg(Color::Yellow),
+        )),
+        Spans::from(Span::raw(line.to_string())),
+        Spans::from(Span::raw("")),
+    ];
+    if let Some(position) = position {
+        text.insert(
+            1,
+            Spans::from(Span::styled(
+                format!("{}: {}", tr(Msg::Position), position.permalink()),
+                Style::default().fg(Color::DarkGray),
+            )),
+        );
+    }
+
+    let matches = window.formatter.inspect(line);
+    if matches.is_empty() {
+        text.push(Spans::from(Span::raw(tr(Msg::NoRuleMatched))));
+    }
+
+    for rule_match in matches {
+        let overlap = if rule_match.overlapped {
+            format!(" ({})", tr(Msg::Overlapped))
+        } else {
+            String::new()
+        };
+        text.push(Spans::from(vec![
+            Span::raw(format!(
+                "#{} [{}..{}]{} ",
+                rule_match.rule_index, rule_match.start, rule_match.end, overlap
+            )),
+            Span::styled(
+                line[rule_match.start..rule_match.end].to_string(),
+                rule_match.style,
+            ),
+            Span::styled(
+                format!("  /{}/", rule_match.pattern),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]));
+    }
+
+    let area = centered_rect(f.size(), 80, 60);
+    let paragraph = Paragraph::new(text)
+        .block(
+            Block::default()
+                .title(tr(Msg::InspectorTitle))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow)),
+        )
+        .style(Style::default().fg(Color::White).bg(Color::Black));
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+fn format_stats(stats: &ConnectionStats, buffered_lines: usize) -> String {
+    let idle = match stats.since_last_line() {
+        Some(elapsed) => format_duration(elapsed),
+        None => "-".to_string(),
+    };
+    format!(
+        "{} | ↓{} ↑{} | {:.1} {} | {} {}",
+        format_buffer(buffered_lines, stats.buffer_bytes),
+        format_bytes(stats.bytes_received),
+        format_bytes(stats.bytes_sent),
+        stats.lines_per_sec(),
+        tr(Msg::LinesPerSec),
+        tr(Msg::Idle),
+        idle
+    )
+}
+
+// 例如 "9.8k lines / 4.2 MB"
+pub(super) fn format_buffer(lines: usize, bytes: u64) -> String {
+    let lines = if lines < 1000 {
+        lines.to_string()
+    } else if lines < 1_000_000 {
+        format!("{:.1}k", lines as f64 / 1000.0)
+    } else {
+        format!("{:.1}M", lines as f64 / 1_000_000.0)
+    };
+    format!("{} {} / {}", lines, tr(Msg::Lines), format_bytes(bytes))
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m{}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h{}m", secs / 3600, secs % 3600 / 60)
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+const INVISIBLE_MARKERS: [char; 4] = ['·', '→', '¤', '␍'];
+
+// 行尾空白、制表符和零宽字符替换成可见符号
+fn reveal_invisibles(line: &str) -> String {
+    let line = line.trim_end_matches('\n');
+    let body = line.trim_end_matches([' ', '\t', '\r']);
+    let trailing = &line[body.len()..];
+
+    let mut revealed = String::with_capacity(line.len());
+    for c in body.chars() {
+        match c {
+            '\t' => revealed.push('→'),
+            '\r' => revealed.push('␍'),
+            '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}' => revealed.push('¤'),
+            _ => revealed.push(c),
+        }
+    }
+    for c in trailing.chars() {
+        revealed.push(match c {
+            '\t' => '→',
+            '\r' => '␍',
+            _ => '·',
+        });
+    }
+    revealed
+}
+
+fn mark_invisibles(spans: Spans<'_>) -> Spans<'_> {
+    let marker_style = Style::default().fg(Color::DarkGray).bg(Color::Rgb(60, 60, 60));
+    let mut marked = Vec::new();
+
+    for span in spans.0 {
+        if !span.content.contains(INVISIBLE_MARKERS) {
+            marked.push(span);
+            continue;
+        }
+        let mut plain = String::new();
+        for c in span.content.chars() {
+            if INVISIBLE_MARKERS.contains(&c) {
+                if !plain.is_empty() {
+                    marked.push(Span::styled(std::mem::take(&mut plain), span.style));
+                }
+                marked.push(Span::styled(c.to_string(), marker_style));
+            } else {
+                plain.push(c);
+            }
+        }
+        if !plain.is_empty() {
+            marked.push(Span::styled(plain, span.style));
+        }
+    }
+
+    Spans::from(marked)
+}
+
+// 跳过左边 offset 列后截取一屏宽; 两端有被截掉的内容时各让出一列放标记
+fn clip_line(line: &str, offset: usize, width: usize) -> (String, bool, bool) {
+    let left = offset > 0 && !line.is_empty();
+    let start = offset + left as usize;
+    let available = width.saturating_sub(left as usize);
+    let mut clipped: Vec<(&str, usize)> = Vec::new();
+    let (mut column, mut used, mut right) = (0, 0, false);
+    for grapheme in line.graphemes(true) {
+        let grapheme_width = unicode_width::UnicodeWidthStr::width(grapheme);
+        column += grapheme_width;
+        if column <= start {
+            continue;
+        }
+        if used + grapheme_width > available {
+            right = true;
+            break;
+        }
+        clipped.push((grapheme, grapheme_width));
+        used += grapheme_width;
+    }
+    if right {
+        while used + 1 > available {
+            let Some((_, grapheme_width)) = clipped.pop() else {
+                break;
+            };
+            used -= grapheme_width;
+        }
+    }
+    (clipped.into_iter().map(|(grapheme, _)| grapheme).collect(), left, right)
+}
+
+// 可以在其后换行的字符, 用于按词折行
+fn is_break_after(grapheme: &str) -> bool {
+    grapheme.chars().all(|c| {
+        c.is_whitespace()
+            || matches!(c, ',' | ';' | ':' | '/' | '-' | '.' | '&' | '=' | '?' | ')' | ']' | '}' | '|')
+    })
+}
+
+// 续行会加上前缀, 所以除第一行外使用 continuation_width
+fn wrap_line(line: &str, first_width: usize, continuation_width: usize, words: bool) -> Vec<String> {
+    let mut wrapped = Vec::new();
+    let mut current_line = String::new();
+    let mut current_width = 0;
+    // 当前行内最后一个可换行位置: 字节偏移和之前的宽度
+    let mut last_break: Option<(usize, usize)> = None;
+
+    for grapheme in line.graphemes(true) {
+        let grapheme_width = unicode_width::UnicodeWidthStr::width(grapheme);
+        let max_width = if wrapped.is_empty() {
+            first_width
+        } else {
+            continuation_width
+        };
+
+        if current_width + grapheme_width > max_width {
+            // 在最后一个词的边界处断开, 剩下的部分移到下一行; 放不下时退回按宽度硬断
+            if let Some((at, width)) = last_break.take().filter(|_| words) {
+                let rest = current_line.split_off(at);
+                let rest_width = current_width - width;
+                if rest_width + grapheme_width <= continuation_width {
+                    wrapped.push(std::mem::replace(&mut current_line, rest));
+                    current_line.push_str(grapheme);
+                    current_width = rest_width + grapheme_width;
+                    if is_break_after(grapheme) {
+                        last_break = Some((current_line.len(), current_width));
+                    }
+                    continue;
+                }
+                current_line.push_str(&rest);
+            }
+            if !current_line.is_empty() {
+                wrapped.push(current_line);
+                current_line = String::new();
+                current_width = 0;
+            }
+            if grapheme_width > max_width {
+                wrapped.push(grapheme.to_string());
+            } else {
+                current_line.push_str(grapheme);
+                current_width = grapheme_width;
+            }
+        } else {
+            current_line.push_str(grapheme);
+            current_width += grapheme_width;
+        }
+        if words && is_break_after(grapheme) {
+            last_break = Some((current_line.len(), current_width));
+        }
+    }
+
+    if !current_line.is_empty() {
+        wrapped.push(current_line);
+    }
+
+    wrapped
+}
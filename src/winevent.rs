@@ -0,0 +1,147 @@
+use std::cell::Cell;
+use std::process::Command;
+use std::sync::mpsc::Receiver;
+
+use serde_json::Value;
+
+use crate::command::supervise;
+use crate::config;
+use crate::ssh::{LogSink, SourceRequest};
+
+const BACKFILL: usize = 100;
+const PASSWORD_ENV: &str = "ROGGER_WINRM_PASSWORD";
+
+// 在远端循环查询比上次 RecordId 新的事件, 每个事件输出一行 JSON
+// 首次连接回填最近的事件; 日志为空时从 0 开始, 避免把 > -1 当成查询全部
+const REMOTE_SCRIPT: &str = r#"param($channel, $last, $backfill)
+if ($last -lt 0) {
+    $events = @(Get-WinEvent -LogName $channel -MaxEvents $backfill -ErrorAction SilentlyContinue)
+    [array]::Reverse($events)
+    $last = 0
+} else {
+    $events = @()
+}
+while ($true) {
+    foreach ($e in $events) {
+        $last = $e.RecordId
+        [pscustomobject]@{
+            RecordId = $e.RecordId
+            TimeCreated = $e.TimeCreated.ToUniversalTime().ToString('yyyy-MM-ddTHH:mm:ss.fffZ')
+            Level = [int]$e.Level
+            Provider = $e.ProviderName
+            Id = $e.Id
+            Message = $e.Message
+        } | ConvertTo-Json -Compress
+    }
+    Start-Sleep -Seconds 2
+    $events = @(Get-WinEvent -LogName $channel -FilterXPath "*[System[EventRecordID > $last]]" -ErrorAction SilentlyContinue | Sort-Object RecordId)
+}"#;
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+// host 为空时在本机执行; protocol = "ssh" 走 PowerShell 的 SSH 远程, 否则走 WinRM
+fn session_args(log: &config::LogConfig) -> String {
+    if log.host.is_empty() {
+        return String::new();
+    }
+    let mut args = if log.protocol.as_deref() == Some("ssh") {
+        let mut args = format!(" -HostName {}", quote(&log.host));
+        if let Some(username) = &log.username {
+            args.push_str(&format!(" -UserName {}", quote(username)));
+        }
+        if let Some(key) = &log.ssh_key {
+            args.push_str(&format!(" -KeyFilePath {}", quote(key)));
+        }
+        args
+    } else {
+        let mut args = format!(" -ComputerName {}", quote(&log.host));
+        if log.tls.unwrap_or(false) {
+            args.push_str(" -UseSSL");
+        }
+        if log.username.is_some() && log.password.is_some() {
+            args.push_str(" -Credential $credential -Authentication Negotiate");
+        }
+        args
+    };
+    if log.port != 0 {
+        args.push_str(&format!(" -Port {}", log.port));
+    }
+    args
+}
+
+fn command(log: &config::LogConfig, channel: &str, last: Option<u64>) -> Command {
+    let mut script = String::from("$ErrorActionPreference = 'Stop'\n");
+    // 密码通过环境变量传入, 不出现在进程参数里
+    if let (Some(username), Some(_)) = (&log.username, &log.password) {
+        script.push_str(&format!(
+            "$credential = New-Object System.Management.Automation.PSCredential({}, (ConvertTo-SecureString $env:{} -AsPlainText -Force))\n",
+            quote(username),
+            PASSWORD_ENV
+        ));
+    }
+    script.push_str(&format!(
+        "Invoke-Command{} -ArgumentList {}, {}, {} -ScriptBlock {{\n{}\n}}",
+        session_args(log),
+        quote(channel),
+        last.map_or(-1, |id| id as i64),
+        BACKFILL,
+        REMOTE_SCRIPT
+    ));
+    let mut command = Command::new("pwsh");
+    command.args(["-NoLogo", "-NoProfile", "-NonInteractive", "-Command", &script]);
+    if let Some(password) = &log.password {
+        command.env(PASSWORD_ENV, password);
+    }
+    command
+}
+
+// 事件的 Level: 1 严重, 2 错误, 3 警告, 4 信息, 5 详细; 安全日志的审核事件是 0
+fn level(level: u64) -> &'static str {
+    match level {
+        1 => "FATAL",
+        2 => "ERROR",
+        3 => "WARN",
+        5 => "DEBUG",
+        _ => "INFO",
+    }
+}
+
+// "时间 级别 [来源/事件ID] 消息", 多行消息合并成一行
+fn format_event(event: &Value) -> String {
+    let message = event["Message"].as_str().unwrap_or_default();
+    let message: Vec<&str> = message.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+    format!(
+        "{} {} [{}/{}] {}\n",
+        event["TimeCreated"].as_str().unwrap_or_default(),
+        level(event["Level"].as_u64().unwrap_or(4)),
+        event["Provider"].as_str().unwrap_or_default(),
+        event["Id"].as_u64().unwrap_or_default(),
+        message.join(" ")
+    )
+}
+
+// type = "winevent": 通过 pwsh 的 Invoke-Command 跟随 Windows 事件日志, 重连时从最后的 RecordId 继续
+pub fn tail(log: &config::LogConfig, sink: LogSink, requests: Receiver<SourceRequest>) {
+    let channel = log.channel.clone().unwrap_or_else(|| "System".to_string());
+    let last = Cell::new(None);
+    supervise(
+        &sink,
+        &requests,
+        || command(log, &channel, last.get()),
+        |line| match serde_json::from_str::<Value>(&line) {
+            Ok(event) => {
+                if let Some(id) = event["RecordId"].as_u64() {
+                    if last.get().is_some_and(|last| id <= last) {
+                        return;
+                    }
+                    last.set(Some(id));
+                }
+                sink.push_line(format_event(&event));
+            }
+            // 警告等非事件的输出原样显示
+            Err(_) => sink.push_line(line),
+        },
+    );
+}